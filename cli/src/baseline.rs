@@ -0,0 +1,113 @@
+use std::{
+	collections::HashMap,
+	fs::File,
+	path::{Path, PathBuf},
+};
+
+use typst::syntax::Source;
+use typst_languagetool::Diagnostic;
+
+use crate::output;
+
+/// Known diagnostics, only new diagnostics are reported.
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+	issues: HashMap<Issue, usize>,
+}
+
+#[derive(
+	serde::Serialize,
+	serde::Deserialize,
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	Hash
+)]
+struct Issue {
+	file: String,
+	rule: String,
+	/// Hash of the flagged text and its line, independent of the position
+	hash: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct BaselineFile {
+	version: usize,
+	issues: Vec<Issue>,
+}
+
+const VERSION: usize = 1;
+
+impl Baseline {
+	pub fn new(diagnostics: &[(PathBuf, Source, Diagnostic)]) -> Self {
+		let mut issues = HashMap::new();
+		for (path, source, diagnostic) in diagnostics {
+			*issues.entry(issue(path, source, diagnostic)).or_default() += 1;
+		}
+		Self { issues }
+	}
+
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		let file = serde_json::from_reader::<_, BaselineFile>(File::open(path)?)?;
+		if file.version != VERSION {
+			Err(anyhow::anyhow!(
+				"Unsupported baseline version {} in {}.",
+				file.version,
+				path.display()
+			))?;
+		}
+		let mut issues = HashMap::new();
+		for issue in file.issues {
+			*issues.entry(issue).or_default() += 1;
+		}
+		Ok(Self { issues })
+	}
+
+	pub fn write(path: &Path, diagnostics: &[(PathBuf, Source, Diagnostic)]) -> anyhow::Result<()> {
+		let file = BaselineFile {
+			version: VERSION,
+			issues: diagnostics
+				.iter()
+				.map(|(path, source, diagnostic)| issue(path, source, diagnostic))
+				.collect(),
+		};
+		serde_json::to_writer_pretty(File::create(path)?, &file)?;
+		Ok(())
+	}
+
+	/// Remove the diagnostics in the baseline, every entry matches one diagnostic.
+	pub fn filter(
+		&self,
+		diagnostics: Vec<(PathBuf, Source, Diagnostic)>,
+	) -> Vec<(PathBuf, Source, Diagnostic)> {
+		let mut remaining = self.issues.clone();
+		diagnostics
+			.into_iter()
+			.filter(|(path, source, diagnostic)| {
+				match remaining.get_mut(&issue(path, source, diagnostic)) {
+					Some(count) if *count > 0 => {
+						*count -= 1;
+						false
+					},
+					_ => true,
+				}
+			})
+			.collect()
+	}
+}
+
+fn issue(path: &Path, source: &Source, diagnostic: &Diagnostic) -> Issue {
+	let range = diagnostic.locations[0].1.clone();
+	let text = source.get(range.clone()).unwrap_or_default();
+	let line = source
+		.byte_to_line(range.start)
+		.and_then(|line| source.line_to_range(line))
+		.and_then(|line| source.get(line))
+		.unwrap_or_default();
+	Issue {
+		file: output::uri(path),
+		rule: diagnostic.rule_id.to_string(),
+		hash: output::fingerprint(&[text, line.trim()]),
+	}
+}
@@ -0,0 +1,338 @@
+use std::{
+	ops::Not,
+	path::Path,
+	process::{Command, ExitCode},
+};
+
+use colored::Colorize;
+use lt_world::WRAPPER_PLACEHOLDER;
+use typst_languagetool::{BackendOptions, LanguageTool, LanguageToolBackend, LanguageToolOptions};
+
+use crate::Args;
+
+/// Chunks below this size lose the context for grammar rules.
+const SMALL_CHUNK_SIZE: usize = 100;
+/// LanguageTool servers limit the text size for a request.
+const LARGE_CHUNK_SIZE: usize = 20_000;
+
+#[derive(Default)]
+struct Report {
+	errors: usize,
+	warnings: usize,
+}
+
+impl Report {
+	fn ok(&mut self, message: impl AsRef<str>) {
+		println!("{} {}", "ok".green().bold(), message.as_ref());
+	}
+
+	fn warning(&mut self, message: impl AsRef<str>, hint: impl AsRef<str>) {
+		self.warnings += 1;
+		println!("{} {}", "warning".yellow().bold(), message.as_ref());
+		println!("\t{}", hint.as_ref());
+	}
+
+	fn error(&mut self, message: impl AsRef<str>, hint: impl AsRef<str>) {
+		self.errors += 1;
+		println!("{} {}", "error".red().bold(), message.as_ref());
+		println!("\t{}", hint.as_ref());
+	}
+}
+
+/// Check the configuration end to end and print the problems with a hint to fix them.
+pub async fn run(args: &Args, options_path: Option<&Path>) -> anyhow::Result<ExitCode> {
+	let mut report = Report::default();
+
+	match options_path {
+		Some(path) => report.ok(format!("Options loaded from {}", path.display())),
+		None => report.ok("No options file, using the command line options"),
+	}
+
+	chunk_size(&mut report, args.lt.chunk_size);
+	match &args.lt.backend {
+		Some(backend) => backend_setup(&mut report, backend),
+		None => report.error(
+			"No backend specified.",
+			"Use '--bundle', '--jar-location', '--host' and '--port', '--server-location' or '--hunspell-directory'.",
+		),
+	}
+	if let Some(fallback) = &args.lt.fallback {
+		backend_setup(&mut report, fallback);
+	}
+	if args.lt.backend.is_some() {
+		backend(&mut report, &args.lt).await;
+	}
+	document(&mut report, args);
+
+	println!();
+	if report.errors > 0 {
+		println!(
+			"Found {} errors and {} warnings.",
+			report.errors, report.warnings
+		);
+		Ok(ExitCode::FAILURE)
+	} else {
+		println!("Found no errors and {} warnings.", report.warnings);
+		Ok(ExitCode::SUCCESS)
+	}
+}
+
+fn chunk_size(report: &mut Report, chunk_size: usize) {
+	if chunk_size == 0 {
+		report.error(
+			"Chunk size is 0.",
+			"Use a chunk size around 1000 with '--chunk-size'.",
+		);
+	} else if chunk_size < SMALL_CHUNK_SIZE {
+		report.warning(
+			format!("Chunk size {} is small.", chunk_size),
+			"Grammar rules may miss the context, use a chunk size around 1000.",
+		);
+	} else if chunk_size > LARGE_CHUNK_SIZE {
+		report.warning(
+			format!("Chunk size {} is large.", chunk_size),
+			"LanguageTool servers may reject large requests, use a chunk size around 1000.",
+		);
+	} else {
+		report.ok(format!("Chunk size {}", chunk_size));
+	}
+}
+
+/// Files and programs required by the backend.
+fn backend_setup(report: &mut Report, backend: &BackendOptions) {
+	match backend {
+		BackendOptions::Bundle { .. } | BackendOptions::Remote { .. } => {},
+		BackendOptions::Jar { jar_location, .. } => {
+			if Path::new(jar_location).is_file() {
+				report.ok(format!("Jar found at {}", jar_location));
+			} else {
+				report.error(
+					format!("Jar not found at {}.", jar_location),
+					"Download LanguageTool and use the path to 'languagetool.jar'.",
+				);
+			}
+		},
+		BackendOptions::Managed { server_location, java, .. } => {
+			if Path::new(server_location).exists() {
+				report.ok(format!("Server found at {}", server_location));
+			} else {
+				report.error(
+					format!("Server not found at {}.", server_location),
+					"Download LanguageTool and use the path to 'languagetool-server.jar'.",
+				);
+			}
+			let java = java.as_deref().unwrap_or("java");
+			match Command::new(java).arg("-version").output() {
+				Ok(output) if output.status.success() => {
+					report.ok(format!("Java found ({})", java))
+				},
+				_ => report.error(
+					format!("Java not found ({}).", java),
+					"Install Java or set the path with the 'java' option.",
+				),
+			}
+		},
+		BackendOptions::Hunspell { hunspell_directory } => {
+			let dictionaries = std::fs::read_dir(hunspell_directory)
+				.map(|entries| {
+					entries
+						.flatten()
+						.filter(|entry| entry.path().extension().is_some_and(|ext| ext == "dic"))
+						.count()
+				})
+				.unwrap_or(0);
+			if dictionaries > 0 {
+				report.ok(format!(
+					"{} hunspell dictionaries found in {}",
+					dictionaries,
+					hunspell_directory.display()
+				));
+			} else {
+				report.error(
+					format!(
+						"No hunspell dictionaries found in {}.",
+						hunspell_directory.display()
+					),
+					"Add '<language>.dic' and '<language>.aff' files to the directory.",
+				);
+			}
+		},
+		BackendOptions::Composite { backends } => {
+			for backend in backends {
+				backend_setup(report, backend);
+			}
+		},
+	}
+}
+
+/// Start the backend and validate the languages and rules.
+async fn backend(report: &mut Report, options: &LanguageToolOptions) {
+	let (mut lt, warnings) = match LanguageTool::new_validated(options).await {
+		Ok(result) => {
+			report.ok("Backend started");
+			result
+		},
+		Err(err) => {
+			report.error(
+				format!("Backend failed to start: {}", err),
+				"Check the backend options, the enabled features and the rule ids in the disabled checks.",
+			);
+			return;
+		},
+	};
+	match lt.list_languages().await {
+		Ok(languages) => report.ok(format!("Backend reachable ({} languages)", languages.len())),
+		Err(err) => {
+			report.error(
+				format!("Backend not reachable: {}", err),
+				"Check that the server is running and the host and port are correct.",
+			);
+			return;
+		},
+	}
+	for warning in warnings {
+		report.warning(warning.message, warning.hint);
+	}
+}
+
+fn document(report: &mut Report, args: &Args) {
+	let root = args.lt.root.clone().unwrap_or(".".into());
+	if root.is_dir().not() {
+		report.error(
+			format!("Root {} is not a directory.", root.display()),
+			"Use the project folder with '--root'.",
+		);
+		return;
+	}
+	report.ok(format!("Root {}", root.display()));
+
+	let mains = match args.lt.main.as_ref().or(args.path.as_ref()) {
+		Some(main) => vec![main.clone()],
+		None => args.lt.mains.clone(),
+	};
+	if mains.is_empty() {
+		report.warning(
+			"No main file or path specified.",
+			"Use '--main' or '--path' to validate the document.",
+		);
+		return;
+	}
+	let paths = [
+		("Main", args.lt.main.as_ref()),
+		("Path", args.path.as_ref()),
+	]
+	.into_iter()
+	.chain(args.lt.mains.iter().map(|main| ("Main", Some(main))));
+	for (name, path) in paths {
+		if let Some(path) = path {
+			if path.is_file().not() {
+				report.error(
+					format!("{} {} does not exist.", name, path.display()),
+					"Use the path of a typst file.",
+				);
+				return;
+			}
+		}
+	}
+	for main in &mains {
+		if main_in_root(&root, main).not() {
+			report.error(
+				format!(
+					"Main {} is not inside the root {}.",
+					main.display(),
+					root.display()
+				),
+				"Use a root containing the main file.",
+			);
+			return;
+		}
+	}
+
+	let world = match crate::world(&args.lt) {
+		Ok(world) => world,
+		Err(err) => {
+			report.error(
+				err.to_string(),
+				"Use a date like '2024-05-01' with '--creation-date'.",
+			);
+			return;
+		},
+	};
+	if let Some(wrapper) = &args.lt.wrapper {
+		match std::fs::read_to_string(wrapper) {
+			Ok(_) if main_in_root(&root, wrapper).not() => {
+				report.error(
+					format!("Wrapper {} is not inside the root.", wrapper.display()),
+					"Use a wrapper inside the root.",
+				);
+				return;
+			},
+			Ok(text) if text.contains(WRAPPER_PLACEHOLDER) => {
+				report.ok(format!("Wrapper {}", wrapper.display()))
+			},
+			Ok(_) => report.warning(
+				format!(
+					"Wrapper {} does not contain '{}'.",
+					wrapper.display(),
+					WRAPPER_PLACEHOLDER
+				),
+				format!(
+					"Include the file with '#include \"{}\"'.",
+					WRAPPER_PLACEHOLDER
+				),
+			),
+			Err(err) => {
+				report.error(
+					format!("Wrapper {} can not be read: {}", wrapper.display(), err),
+					"Use the path of a typst file.",
+				);
+				return;
+			},
+		}
+	}
+	for main in &mains {
+		let running = match &args.lt.wrapper {
+			Some(wrapper) if crate::isolated(args, &world, main) => {
+				match world.with_wrapper(wrapper, main) {
+					Ok(running) => running,
+					Err(err) => {
+						report.error(
+							format!("Wrapper {} can not be read: {}", wrapper.display(), err),
+							"Use the path of a typst file.",
+						);
+						return;
+					},
+				}
+			},
+			_ => world.with_main(main.clone()),
+		};
+		match running.compile() {
+			Ok(doc) => report.ok(format!(
+				"Document {} compiled ({} pages)",
+				main.display(),
+				doc.pages.len()
+			)),
+			Err(errors) => {
+				let errors = errors
+					.iter()
+					.map(|error| error.message.as_str())
+					.collect::<Vec<_>>();
+				report.error(
+					format!(
+						"Document {} failed to compile: {}",
+						main.display(),
+						errors.join(", ")
+					),
+					"Fix the document with 'typst compile'.",
+				);
+			},
+		}
+	}
+}
+
+fn main_in_root(root: &Path, main: &Path) -> bool {
+	let (Ok(root), Ok(main)) = (root.canonicalize(), main.canonicalize()) else {
+		return false;
+	};
+	main.starts_with(root)
+}
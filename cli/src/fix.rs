@@ -0,0 +1,136 @@
+use std::{
+	fmt::Write,
+	ops::{Not, Range},
+};
+
+use typst_languagetool::Severity;
+
+/// Lines around a change in the diff.
+const CONTEXT: usize = 3;
+
+/// Replacement for a byte range of a source file.
+#[derive(Debug, Clone)]
+pub struct Edit {
+	pub range: Range<usize>,
+	pub replacement: String,
+	/// Severity of the fixed diagnostic
+	pub severity: Severity,
+}
+
+/// Apply the edits, edits overlapping a previous edit are skipped.
+/// Returns the new text, the applied edits and the skipped edits.
+pub fn apply(text: &str, mut edits: Vec<Edit>) -> (String, Vec<Edit>, Vec<Edit>) {
+	edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+	let mut applied = Vec::<Edit>::with_capacity(edits.len());
+	let mut skipped = Vec::new();
+	for edit in edits {
+		if let Some(last) = applied.last() {
+			if edit.range.start < last.range.end || edit.range.start == last.range.start {
+				skipped.push(edit);
+				continue;
+			}
+		}
+		applied.push(edit);
+	}
+
+	let mut result = String::with_capacity(text.len());
+	let mut position = 0;
+	for edit in &applied {
+		result.push_str(&text[position..edit.range.start]);
+		result.push_str(&edit.replacement);
+		position = edit.range.end;
+	}
+	result.push_str(&text[position..]);
+	(result, applied, skipped)
+}
+
+/// Unified diff for the applied edits.
+pub fn diff(path: &str, text: &str, applied: &[Edit]) -> String {
+	let lines = text.split_inclusive('\n').collect::<Vec<_>>();
+	if lines.is_empty() {
+		return String::new();
+	}
+	let mut line_starts = Vec::with_capacity(lines.len() + 1);
+	let mut offset = 0;
+	for line in &lines {
+		line_starts.push(offset);
+		offset += line.len();
+	}
+	line_starts.push(offset);
+	let line_of = |index: usize| line_starts.partition_point(|start| *start <= index) - 1;
+
+	// edits with the changed lines, merged if the context overlaps
+	let mut hunks = Vec::<(usize, usize, Vec<&Edit>)>::new();
+	for edit in applied {
+		let first = line_of(edit.range.start).min(lines.len().saturating_sub(1));
+		let last = line_of(edit.range.end).min(lines.len().saturating_sub(1));
+		match hunks.last_mut() {
+			Some((_, hunk_last, edits)) if first <= *hunk_last + 2 * CONTEXT => {
+				*hunk_last = last.max(*hunk_last);
+				edits.push(edit);
+			},
+			_ => hunks.push((first, last, vec![edit])),
+		}
+	}
+
+	let mut out = String::new();
+	if hunks.is_empty() {
+		return out;
+	}
+	_ = writeln!(out, "--- a/{}", path);
+	_ = writeln!(out, "+++ b/{}", path);
+	let mut delta = 0isize;
+	for (first, last, edits) in hunks {
+		let before = first.saturating_sub(CONTEXT);
+		let after = (last + CONTEXT).min(lines.len().saturating_sub(1));
+
+		let start = line_starts[first];
+		let end = line_starts[last + 1];
+		let (changed, ..) = apply(
+			&text[start..end],
+			edits
+				.into_iter()
+				.map(|edit| Edit {
+					range: (edit.range.start - start)..(edit.range.end - start),
+					replacement: edit.replacement.clone(),
+					severity: edit.severity,
+				})
+				.collect(),
+		);
+		let changed = changed.split_inclusive('\n').collect::<Vec<_>>();
+
+		let old_count = after + 1 - before;
+		let new_count = old_count - (last + 1 - first) + changed.len();
+		_ = writeln!(
+			out,
+			"@@ -{},{} +{},{} @@",
+			before + 1,
+			old_count,
+			(before as isize + 1 + delta),
+			new_count
+		);
+		delta += new_count as isize - old_count as isize;
+
+		for line in &lines[before..first] {
+			push_line(&mut out, ' ', line);
+		}
+		for line in &lines[first..=last] {
+			push_line(&mut out, '-', line);
+		}
+		for line in &changed {
+			push_line(&mut out, '+', line);
+		}
+		for line in &lines[(last + 1)..=after] {
+			push_line(&mut out, ' ', line);
+		}
+	}
+	out
+}
+
+fn push_line(out: &mut String, prefix: char, line: &str) {
+	out.push(prefix);
+	out.push_str(line);
+	if line.ends_with('\n').not() {
+		out.push_str("\n\\ No newline at end of file\n");
+	}
+}
@@ -0,0 +1,133 @@
+use std::{
+	collections::HashMap,
+	ops::{Not, Range},
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+/// Lines changed compared to a git revision.
+#[derive(Debug, Default)]
+pub struct ChangedLines {
+	/// 1-based line ranges for the canonical paths, `None` for untracked files
+	files: HashMap<PathBuf, Option<Vec<Range<usize>>>>,
+}
+
+impl ChangedLines {
+	/// Changes in the working tree compared to `base`, includes untracked files.
+	/// The merge base is used, so only the own changes of a branch are included.
+	pub fn new(directory: &Path, base: &str) -> anyhow::Result<Self> {
+		let top_level = git(directory, &["rev-parse", "--show-toplevel"])?;
+		let top_level = PathBuf::from(top_level.trim());
+		let merge_base = git(directory, &["merge-base", base, "HEAD"])?;
+
+		let mut files = HashMap::new();
+		let diff = git(
+			directory,
+			&[
+				"diff",
+				"--unified=0",
+				"--no-color",
+				"--no-ext-diff",
+				merge_base.trim(),
+			],
+		)?;
+		let mut current = None;
+		for line in diff.lines() {
+			if let Some(path) = line.strip_prefix("+++ ") {
+				current = path
+					.strip_prefix("b/")
+					.map(|path| canonical(&top_level.join(path)));
+				continue;
+			}
+			let (Some(path), Some(hunk)) = (&current, line.strip_prefix("@@ ")) else {
+				continue;
+			};
+			let Some(range) = parse_hunk(hunk) else {
+				continue;
+			};
+			files
+				.entry(path.clone())
+				.or_insert_with(|| Some(Vec::new()))
+				.get_or_insert_with(Vec::new)
+				.push(range);
+		}
+
+		let untracked = git(
+			directory,
+			&["ls-files", "--others", "--exclude-standard", "--full-name"],
+		)?;
+		for path in untracked.lines() {
+			files.insert(canonical(&top_level.join(path)), None);
+		}
+		Ok(Self { files })
+	}
+
+	/// Check if one of the 1-based lines changed.
+	pub fn contains(&self, path: &Path, lines: Range<usize>) -> bool {
+		match self.files.get(&canonical(path)) {
+			None => false,
+			Some(None) => true,
+			Some(Some(ranges)) => ranges
+				.iter()
+				.any(|range| range.start < lines.end && lines.start < range.end),
+		}
+	}
+}
+
+/// Added lines from `-a,b +c,d @@`, pure deletions are ignored.
+fn parse_hunk(hunk: &str) -> Option<Range<usize>> {
+	let new = hunk
+		.split_whitespace()
+		.find_map(|part| part.strip_prefix('+'))?;
+	let (start, count) = match new.split_once(',') {
+		Some((start, count)) => (start.parse::<usize>().ok()?, count.parse::<usize>().ok()?),
+		None => (new.parse::<usize>().ok()?, 1),
+	};
+	(count > 0).then_some(start..(start + count))
+}
+
+/// Paths ignored by `.gitignore`, nothing is ignored outside of a git repository.
+pub fn ignored(directory: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+	if paths.is_empty() {
+		return Vec::new();
+	}
+	let Ok(output) = Command::new("git")
+		.arg("-C")
+		.arg(directory)
+		.args(["-c", "core.quotepath=off", "check-ignore", "--"])
+		.args(paths)
+		.output()
+	else {
+		return Vec::new();
+	};
+	// exit code 1 if no path is ignored
+	match output.status.code() {
+		Some(0) => String::from_utf8_lossy(&output.stdout)
+			.lines()
+			.map(PathBuf::from)
+			.collect(),
+		_ => Vec::new(),
+	}
+}
+
+fn canonical(path: &Path) -> PathBuf {
+	path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+fn git(directory: &Path, args: &[&str]) -> anyhow::Result<String> {
+	let output = Command::new("git")
+		.arg("-C")
+		.arg(directory)
+		.args(["-c", "core.quotepath=off"])
+		.args(args)
+		.output()
+		.map_err(|err| anyhow::anyhow!("Failed to execute git: {}", err))?;
+	if output.status.success().not() {
+		Err(anyhow::anyhow!(
+			"git {} failed: {}",
+			args.join(" "),
+			String::from_utf8_lossy(&output.stderr).trim()
+		))?;
+	}
+	Ok(String::from_utf8(output.stdout)?)
+}
@@ -1,29 +1,108 @@
+mod baseline;
+mod doctor;
+mod fix;
+mod git;
+mod notification;
 mod output;
+mod serve;
+mod tui;
 
 use anyhow::Context;
+use baseline::Baseline;
 use clap::{Parser, ValueEnum};
 
-use colored::Colorize;
-use lt_world::LtWorld;
+use indicatif::{ProgressBar, ProgressStyle};
+use lt_world::{FontOptions, LtWorld, LtWorldRunning, PackageOptions};
 use notify::RecursiveMode;
 use notify_debouncer_mini::new_debouncer;
-use typst::World;
+use typst::{
+	diag::SourceResult,
+	syntax::{FileId, Source},
+	World,
+};
 use typst_languagetool::{
-	BackendOptions, LanguageTool, LanguageToolBackend, LanguageToolOptions, Suggestion,
+	convert::{Mapping, Selection},
+	BackendOptions, Cache, Cancellation, CheckMode, ChunkSize, DetachedText, Diagnostic,
+	DirectoryOptions, DocumentOptions, FileCollector, Headings, JniOptions, LanguageTool,
+	LanguageToolBackend, LanguageToolOptions, Profile, Severity, Suggestion,
+	DIRECTORY_OPTIONS_FILES,
 };
 
 use std::{
-	collections::HashMap,
-	fs::File,
-	ops::Not,
+	collections::{HashMap, HashSet},
+	io::{IsTerminal, Read},
+	net::SocketAddr,
+	ops::{Not, RangeInclusive},
 	path::{Path, PathBuf},
-	time::Duration,
+	process::ExitCode,
+	sync::mpsc::RecvTimeoutError,
+	time::{Duration, Instant},
 };
 
 #[derive(ValueEnum, Clone, Debug)]
 enum Task {
 	Check,
 	Watch,
+	/// List the languages supported by the backend.
+	Languages,
+	/// List the active rules for `language`.
+	Rules,
+	/// Validate the options, the backend and the document.
+	Doctor,
+	/// List the words flagged by the spelling rules sorted by frequency.
+	UnknownWords,
+	/// Serve a HTTP JSON API to check files with a running backend.
+	Serve,
+	/// Convert Hunspell and LTeX dictionaries from `--import` to dictionary file lines.
+	ImportDictionary,
+	/// Write the options file and the command line flags as one options file to `--output`,
+	/// the dictionaries from `--import` are added to `dictionary`, files with words for all languages to `dictionary_imports`.
+	/// The dictionary and font paths are written relative to the output file.
+	MigrateConfig,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+	/// Annotated source snippets.
+	Pretty,
+	/// One line per diagnostic for easy regex evaluation.
+	Plain,
+	/// `file:line:col: severity: message [RULE_ID]` like gcc, for editors and log parsers.
+	Short,
+	/// SARIF 2.1 log for code scanning integrations.
+	Sarif,
+	/// Code Climate JSON for the GitLab Code Quality widget.
+	Codeclimate,
+	/// JUnit XML with a failed test per diagnostic.
+	Junit,
+	/// CSV with a row per diagnostic, to triage in a spreadsheet.
+	Csv,
+	/// Newline-delimited JSON events, to follow `watch` live.
+	Ndjson,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum GroupBy {
+	/// Diagnostics of each file in the order of the text.
+	File,
+	/// Occurrences of each rule together, the most frequent rule first.
+	Rule,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorChoice {
+	/// Colors if stdout is a terminal and `NO_COLOR` is not set.
+	Auto,
+	Always,
+	Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum FixMode {
+	/// Apply the first replacement.
+	First,
+	/// Only apply the replacement if it is the only one.
+	Unambiguous,
 }
 
 #[derive(Parser, Debug)]
@@ -43,6 +122,23 @@ struct CliArgs {
 	#[clap(short, long, default_value = None)]
 	main: Option<PathBuf>,
 
+	/// Changed files matching the glob (relative to `root`) are not checked with `watch`, `.gitignore` is always respected.
+	#[clap(long)]
+	ignore_files: Vec<String>,
+
+	/// Address for `serve`.
+	#[clap(long, default_value = "127.0.0.1:8787")]
+	listen: SocketAddr,
+
+	/// Show the diagnostics of `watch` in a terminal interface, grouped by file and updated after each check.
+	/// The log is only written with `--log-file`.
+	#[clap(long, default_value_t = false)]
+	tui: bool,
+
+	/// Send a desktop notification with the new issues after each check of `watch`.
+	#[clap(long, default_value_t = false)]
+	notify: bool,
+
 	/// Delay for file changes.
 	#[clap(long, default_value_t = 0.1, id = "SECONDS")]
 	delay: f64,
@@ -51,10 +147,232 @@ struct CliArgs {
 	#[clap(long, default_value_t = 1000)]
 	chunk_size: usize,
 
-	/// Print results without annotations for easy regex evaluation.
+	/// Adjust the chunk size from the latency of the requests, starting with `--chunk-size`.
+	#[clap(long, default_value_t = false)]
+	auto_chunk_size: bool,
+
+	/// Chunks checked concurrently with the server backends.
+	#[clap(short, long, default_value_t = 1)]
+	jobs: usize,
+
+	/// Additional directory with fonts for the document.
+	#[clap(long = "font-path")]
+	font_paths: Vec<PathBuf>,
+
+	/// Only use the embedded fonts and `--font-path`, the system fonts are not searched.
+	#[clap(long, default_value_t = false)]
+	no_system_fonts: bool,
+
+	/// Fail for packages which are not downloaded instead of downloading them.
+	#[clap(long, default_value_t = false)]
+	offline: bool,
+
+	/// Directory with local packages, defaults to the typst data directory.
+	#[clap(long, default_value = None)]
+	package_path: Option<PathBuf>,
+
+	/// Directory for downloaded packages, defaults to the typst cache directory.
+	#[clap(long, default_value = None)]
+	package_cache_path: Option<PathBuf>,
+
+	/// Date for `datetime.today()` (`2024-05-01` or `2024-05-01T12:00:00Z`), defaults to `SOURCE_DATE_EPOCH` or the current date.
+	#[clap(long, default_value = None)]
+	creation_date: Option<String>,
+
+	/// Language for `rules` (`en-US`, `de-DE`, ...).
+	#[clap(long, default_value = None)]
+	language: Option<String>,
+
+	/// Files with allowed words, one per line and optionally prefixed with the language (`en-US:word`).
+	#[clap(long)]
+	dictionary: Vec<PathBuf>,
+
+	/// Append the unknown or imported words to the dictionary file (only `unknown-words` and `import-dictionary`).
+	#[clap(long, default_value = None)]
+	append_dictionary: Option<PathBuf>,
+
+	/// Hunspell `.dic` file or LTeX dictionary to convert (only `import-dictionary` and `migrate-config`).
+	#[clap(long)]
+	import: Vec<PathBuf>,
+
+	/// Options file written by `migrate-config` (JSON, TOML or YAML by the extension), defaults to JSON on stdout.
+	#[clap(long, default_value = None)]
+	output: Option<PathBuf>,
+
+	/// Only list unknown words found at least this often (only `unknown-words`).
+	#[clap(long, default_value_t = 1)]
+	min_count: usize,
+
+	/// Only check the pages (`3-10` or `5`).
+	#[clap(long, default_value = None, value_parser = parse_pages)]
+	pages: Option<RangeInclusive<usize>>,
+
+	/// Only check the section with the heading text or label.
+	#[clap(long, default_value = None)]
+	section: Option<String>,
+
+	/// Only check spelling or grammar (`all`, `spelling` or `grammar`).
+	#[clap(long, default_value = None)]
+	check_mode: Option<CheckMode>,
+
+	/// Maximum number of replacements for a diagnostic.
+	#[clap(long, default_value = None)]
+	max_suggestions: Option<usize>,
+
+	/// Drop replacements only differing in whitespace or case from a previous replacement.
+	#[clap(long, default_value_t = false)]
+	drop_similar_suggestions: bool,
+
+	/// Preset of disabled checks and rule options (`academic`, `technical` or `casual`).
+	#[clap(long, default_value = None)]
+	profile: Option<Profile>,
+
+	/// Use the LTeX magic comments (`// ltex: language=de-DE`) in the sources.
+	#[clap(long, default_value_t = false)]
+	ltex_comments: bool,
+
+	/// Check the headings as part of the text, as independent sentences or skip them (`text`, `sentence` or `skip`).
+	#[clap(long, default_value = None)]
+	headings: Option<Headings>,
+
+	/// Check, skip or attribute to the nearest text the text without a location in the sources (`check`, `skip` or `nearest`).
+	#[clap(long, default_value = None)]
+	detached_text: Option<DetachedText>,
+
+	/// Skip the spelling suggestions for words shorter than this number of chars.
+	#[clap(long, default_value = None)]
+	min_word_length: Option<usize>,
+
+	/// Skip the spelling suggestions for words containing digits (`5km`, `x86_64`).
+	#[clap(long, default_value_t = false)]
+	skip_numeric_words: bool,
+
+	/// Check the text of the comments in the sources.
+	#[clap(long, default_value_t = false)]
+	check_comments: bool,
+
+	/// Check the headers and footers of the pages, each distinct header or footer once.
+	#[clap(long, default_value_t = false)]
+	headers_footers: bool,
+
+	/// Check the entries of the outlines, which repeat the headings.
+	#[clap(long, default_value_t = false)]
+	outline: bool,
+
+	/// Join the list items with a space instead of a paragraph break, for lists which are part of a sentence.
+	#[clap(long, default_value_t = false)]
+	join_list_items: bool,
+
+	/// Long language code for text in English, the language of documents without `#set text(lang: ..)`.
+	#[clap(long, default_value = None)]
+	default_language: Option<String>,
+
+	/// Print results without annotations for easy regex evaluation, same as `--format=plain`.
 	#[clap(long, default_value_t = false)]
 	plain: bool,
 
+	/// Exit with an error if a diagnostic with this or a higher severity is found (`info`, `warning` or `error`).
+	#[clap(long, default_value = None)]
+	fail_on: Option<Severity>,
+
+	/// Stop at the first diagnostic or compile error and exit with an error, the remaining chunks are not checked (only `check`).
+	/// With `--fail-on` only the diagnostics with a failing severity stop the check.
+	#[clap(long, default_value_t = false)]
+	fast_fail: bool,
+
+	/// Exit with an error if more diagnostics are found.
+	#[clap(long, default_value = None)]
+	max_issues: Option<usize>,
+
+	/// Read the file content from stdin instead of `stdin-filepath` (only `check`).
+	#[clap(long, default_value_t = false)]
+	stdin: bool,
+
+	/// Path of the file read from stdin.
+	#[clap(long, default_value = None)]
+	stdin_filepath: Option<PathBuf>,
+
+	/// Only report diagnostics not in the baseline file.
+	#[clap(long, default_value = None)]
+	baseline: Option<PathBuf>,
+
+	/// Write the current diagnostics to the baseline file instead of printing them.
+	#[clap(long, default_value_t = false)]
+	write_baseline: bool,
+
+	/// Only report diagnostics in lines changed since the last commit, same as `--diff-base=HEAD`.
+	#[clap(long, default_value_t = false)]
+	changed: bool,
+
+	/// Only report diagnostics in lines changed compared to the merge base with the git revision.
+	#[clap(long, default_value = None)]
+	diff_base: Option<String>,
+
+	/// Apply the replacements to the source files instead of printing the diagnostics (only `check`).
+	#[clap(long, value_enum, num_args = 0..=1, default_missing_value = "first", default_value = None)]
+	fix: Option<FixMode>,
+
+	/// Print a unified diff for `--fix` instead of changing the files.
+	#[clap(long, default_value_t = false)]
+	dry_run: bool,
+
+	/// Output format.
+	#[clap(long, value_enum, default_value_t = Format::Pretty)]
+	format: Format,
+
+	/// Order of the diagnostics for the `pretty`, `plain` and `short` formats.
+	#[clap(long, value_enum, default_value_t = GroupBy::File)]
+	group_by: GroupBy,
+
+	/// Use colors for the pretty output.
+	#[clap(long, value_enum, default_value_t = ColorChoice::Auto)]
+	color: ColorChoice,
+
+	/// Print the duration of compile, convert, each backend request and mapping.
+	#[clap(long, default_value_t = false)]
+	timings: bool,
+
+	/// Only print a summary of the diagnostics, for scripts using the exit code.
+	#[clap(short, long, default_value_t = false, conflicts_with = "verbose")]
+	quiet: bool,
+
+	/// Print the timings and the cache statistics, twice (`-vv`) also the backend requests.
+	#[clap(short, long, action = clap::ArgAction::Count)]
+	verbose: u8,
+
+	/// Log level (`error`, `warn`, `info`, `debug` or `trace`), `TYPST_LANGUAGETOOL_LOG` takes precedence.
+	/// Defaults to `info`, `error` with `--quiet` and `debug` with `-vv`.
+	#[clap(long, default_value = None)]
+	log_level: Option<String>,
+
+	/// Write the log to the file instead of stderr.
+	#[clap(long, default_value = None)]
+	log_file: Option<PathBuf>,
+
+	/// Directory with the ngram data for the bundle and jar backends.
+	#[clap(long, default_value = None)]
+	ngram_directory: Option<PathBuf>,
+
+	/// Threads for the bundle and jar backends, defaults to the number of processors.
+	#[clap(long, default_value = None)]
+	threads: Option<usize>,
+
+	/// Cached sentences for the bundle and jar backends.
+	#[clap(long, default_value = None)]
+	cache_size: Option<u64>,
+
+	/// Maximum heap size for the JVM of the bundle and jar backends (`512m`, `2g`, ...).
+	#[clap(long, default_value = None)]
+	max_heap: Option<String>,
+
+	/// Additional option for the JVM of the bundle and jar backends.
+	#[clap(long = "jvm-option", allow_hyphen_values = true)]
+	jvm_options: Vec<String>,
+
+	/// Maximum number of loaded languages for the bundle and jar backends.
+	#[clap(long, default_value = None)]
+	max_languages: Option<usize>,
+
 	/// Use bundled languagetool jar.
 	#[clap(long, default_value_t = false)]
 	bundle: bool,
@@ -71,7 +389,27 @@ struct CliArgs {
 	#[clap(long, default_value = None)]
 	port: Option<String>,
 
-	/// Path to JSON with configuration.
+	/// Timeout in seconds for requests to the remote languagetool server.
+	#[clap(long, default_value = None)]
+	timeout: Option<f64>,
+
+	/// Retries for failed requests to the remote languagetool server.
+	#[clap(long, default_value_t = 0)]
+	retries: usize,
+
+	/// Skip chunks with failed requests instead of aborting the check.
+	#[clap(long, default_value_t = false)]
+	continue_on_error: bool,
+
+	/// Location of the languagetool-server.jar to start a local server.
+	#[clap(long, default_value = None)]
+	server_location: Option<String>,
+
+	/// Directory with hunspell dictionaries (`<lang>.aff` and `<lang>.dic`).
+	#[clap(long, default_value = None)]
+	hunspell_directory: Option<PathBuf>,
+
+	/// Path to JSON, TOML or YAML with configuration, defaults to `typst-languagetool.toml`, ... in the root.
 	#[clap(long, default_value = None)]
 	options: Option<PathBuf>,
 }
@@ -79,225 +417,1564 @@ struct CliArgs {
 struct Args {
 	task: Task,
 	path: Option<PathBuf>,
+	stdin: bool,
+	/// Baseline to filter the diagnostics, `None` while writing the baseline
+	baseline: Option<Baseline>,
+	write_baseline: Option<PathBuf>,
+	/// Git revision to compare against, only changed lines are reported
+	diff_base: Option<String>,
+	language: Option<String>,
+	append_dictionary: Option<PathBuf>,
+	import: Vec<PathBuf>,
+	output: Option<PathBuf>,
+	min_count: usize,
+	listen: SocketAddr,
 	delay: f64,
-	plain: bool,
+	tui: bool,
+	notify: bool,
+	ignore_files: Vec<glob::Pattern>,
+	/// Template and excluded files of the `typst.toml`, relative to the root
+	excluded: Vec<glob::Pattern>,
+	/// Options files in the directories of the root
+	directories: DirectoryOptions,
+	selection: Selection,
+	format: Format,
+	group_by: GroupBy,
+	fail_on: Option<Severity>,
+	max_issues: Option<usize>,
+	fast_fail: bool,
+	fix: Option<FixMode>,
+	dry_run: bool,
+	timings: bool,
+	quiet: bool,
+	verbose: u8,
+	/// Chunk size of the options, adjusted with `auto_chunk_size`
+	chunk_size: ChunkSize,
 	lt: LanguageToolOptions,
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
 	let cli_args = CliArgs::parse();
+	// the log would be written over the terminal interface
+	let log_level = match (cli_args.tui, &cli_args.log_file, &cli_args.log_level) {
+		(true, None, _) => "off",
+		(_, _, Some(level)) => level.as_str(),
+		_ if cli_args.quiet => "error",
+		_ if cli_args.verbose >= 2 => "debug",
+		_ => "info",
+	};
+	init_logging(log_level, cli_args.log_file.as_deref())?;
+
+	colored::control::set_override(match cli_args.color {
+		ColorChoice::Always => true,
+		ColorChoice::Never => false,
+		ColorChoice::Auto => {
+			std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+				&& std::io::stdout().is_terminal()
+		},
+	});
 
+	let jni = JniOptions {
+		ngram_directory: cli_args.ngram_directory,
+		threads: cli_args.threads,
+		cache_size: cli_args.cache_size,
+		max_heap: cli_args.max_heap,
+		jvm_options: cli_args.jvm_options,
+		max_languages: cli_args.max_languages,
+		..Default::default()
+	};
 	let backend = match (
 		cli_args.bundle,
 		cli_args.jar_location,
 		cli_args.host,
 		cli_args.port,
+		cli_args.hunspell_directory,
+		cli_args.server_location,
 	) {
-		(false, None, None, None) => None,
-		(true, None, None, None) => Some(BackendOptions::Bundle),
-		(false, Some(path), None, None) => Some(BackendOptions::Jar { jar_location: path }),
-		(false, None, Some(host), Some(port)) => Some(BackendOptions::Remote { host, port }),
+		(false, None, None, None, None, None) => None,
+		(true, None, None, None, None, None) => Some(BackendOptions::Bundle { jni }),
+		(false, Some(path), None, None, None, None) => {
+			Some(BackendOptions::Jar { jar_location: path, jni })
+		},
+		(false, None, Some(host), Some(port), None, None) => {
+			Some(BackendOptions::Remote {
+				host,
+				port,
+				timeout: cli_args.timeout,
+				retries: cli_args.retries,
+				continue_on_error: cli_args.continue_on_error,
+				username: None,
+				api_key: None,
+				ignore_case: false,
+				server_dictionary: None,
+			})
+		},
+		(false, None, None, None, Some(hunspell_directory), None) => {
+			Some(BackendOptions::Hunspell { hunspell_directory })
+		},
+		(false, None, None, port, None, Some(server_location)) => {
+			Some(BackendOptions::Managed {
+				server_location,
+				server_port: port.map(|port| port.parse()).transpose()?,
+				java: None,
+			})
+		},
 		_ => Err(anyhow::anyhow!(
-			"Exactly one of 'bundled', 'jar_location' or 'host and port' must be specified."
+			"Exactly one of 'bundled', 'jar_location', 'host and port', 'server_location' or 'hunspell_directory' must be specified."
+		))?,
+	};
+
+	let path = match (cli_args.stdin, cli_args.stdin_filepath) {
+		(false, _) => cli_args.path,
+		(true, Some(path)) => Some(path),
+		(true, None) => Err(anyhow::anyhow!("'stdin' requires 'stdin-filepath'."))?,
+	};
+
+	let (baseline, write_baseline) = match (cli_args.baseline, cli_args.write_baseline) {
+		(None, false) => (None, None),
+		(None, true) => Err(anyhow::anyhow!("'write-baseline' requires 'baseline'."))?,
+		(Some(path), false) => (Some(Baseline::load(&path)?), None),
+		(Some(path), true) => (None, Some(path)),
+	};
+
+	let diff_base = match (cli_args.changed, cli_args.diff_base) {
+		(false, diff_base) => diff_base,
+		(true, None) => Some("HEAD".into()),
+		(true, Some(_)) => Err(anyhow::anyhow!(
+			"Only one of 'changed' or 'diff-base' can be specified."
 		))?,
 	};
 
 	let mut args = Args {
 		task: cli_args.task,
-		path: cli_args.path,
+		path,
+		stdin: cli_args.stdin,
+		baseline,
+		write_baseline,
+		diff_base,
+		language: cli_args.language,
+		append_dictionary: cli_args.append_dictionary,
+		import: cli_args.import,
+		output: cli_args.output,
+		min_count: cli_args.min_count,
+		listen: cli_args.listen,
 		delay: cli_args.delay,
-		plain: cli_args.plain,
+		tui: cli_args.tui,
+		notify: cli_args.notify,
+		selection: Selection {
+			pages: cli_args.pages,
+			section: cli_args.section,
+			headers_footers: false,
+			outline: false,
+		},
+		ignore_files: cli_args
+			.ignore_files
+			.iter()
+			.map(|pattern| glob::Pattern::new(pattern))
+			.collect::<Result<_, _>>()?,
+		excluded: Vec::new(),
+		directories: DirectoryOptions::new(Path::new(".")),
+		fail_on: cli_args.fail_on,
+		max_issues: cli_args.max_issues,
+		fast_fail: cli_args.fast_fail,
+		fix: cli_args.fix,
+		dry_run: cli_args.dry_run,
+		timings: cli_args.timings || cli_args.verbose >= 1,
+		quiet: cli_args.quiet,
+		verbose: cli_args.verbose,
+		chunk_size: ChunkSize::new(cli_args.chunk_size, cli_args.auto_chunk_size),
+		group_by: cli_args.group_by,
+		format: if cli_args.plain {
+			Format::Plain
+		} else {
+			cli_args.format
+		},
 		lt: LanguageToolOptions {
 			root: cli_args.root,
 			main: cli_args.main,
 			chunk_size: cli_args.chunk_size,
+			auto_chunk_size: cli_args.auto_chunk_size,
+			jobs: cli_args.jobs,
+			font_paths: cli_args.font_paths,
+			include_system_fonts: cli_args.no_system_fonts.not(),
+			offline: cli_args.offline,
+			package_path: cli_args.package_path,
+			package_cache_path: cli_args.package_cache_path,
+			creation_date: cli_args.creation_date,
 			backend,
-			languages: HashMap::new(),
-			dictionary: HashMap::new(),
-			disabled_checks: HashMap::new(),
+			dictionary_files: cli_args.dictionary,
+			check_mode: cli_args.check_mode,
+			max_suggestions: cli_args.max_suggestions,
+			drop_similar_suggestions: cli_args.drop_similar_suggestions,
+			ltex_comments: cli_args.ltex_comments,
+			profile: cli_args.profile,
+			default_language: cli_args.default_language,
+			headings: cli_args.headings,
+			detached_text: cli_args.detached_text,
+			min_word_length: cli_args.min_word_length,
+			skip_numeric_words: cli_args.skip_numeric_words,
+			join_list_items: cli_args.join_list_items,
+			headers_footers: cli_args.headers_footers,
+			outline: cli_args.outline,
+			check_comments: cli_args.check_comments,
+			..Default::default()
 		},
 	};
 
-	if let Some(path) = cli_args.options {
-		let file = File::open(path)?;
-		let file_options = serde_json::from_reader::<_, LanguageToolOptions>(file)?;
+	let options_path = cli_args
+		.options
+		.or_else(|| LanguageToolOptions::find(args.lt.root.as_deref().unwrap_or(Path::new("."))));
+	if let Some(path) = &options_path {
+		let file_options = LanguageToolOptions::load(path)?;
 		args.lt = file_options.overwrite(args.lt);
 	}
+	// without the global options, the profile and the project
+	if matches!(args.task, Task::MigrateConfig) {
+		return migrate_config(&args);
+	}
+	if let Some(path) = LanguageToolOptions::find_global() {
+		let global_options = LanguageToolOptions::load(&path)?;
+		args.lt = global_options.overwrite(args.lt);
+		tracing::info!("Global options loaded from {}", path.display());
+	}
+	let project = args.lt.use_project().unwrap_or_else(|err| {
+		tracing::warn!("Project settings ignored: {}", err);
+		None
+	});
+	if let Some(project) = project {
+		tracing::info!(
+			"Package {} {} in {}",
+			project.name,
+			project.version,
+			project.root.display()
+		);
+		args.excluded = project
+			.exclude
+			.iter()
+			.map(|pattern| glob::Pattern::new(pattern))
+			.collect::<Result<_, _>>()?;
+	}
+	args.lt = args.lt.with_profile();
 
+	if matches!(args.task, Task::UnknownWords) {
+		args.lt.check_mode = Some(CheckMode::Spelling);
+	}
+	args.chunk_size = ChunkSize::new(args.lt.chunk_size, args.lt.auto_chunk_size)
+		.with_languages(args.lt.language_chunk_sizes.clone());
+	args.selection.headers_footers = args.lt.headers_footers;
+	args.selection.outline = args.lt.outline;
+	let root = args.lt.root.clone().unwrap_or(".".into());
+	args.directories = DirectoryOptions::new(&root.canonicalize()?);
 	let args = args;
 
-	let lt = LanguageTool::new(&args.lt).await?;
+	if args.fix.is_some() && matches!(args.task, Task::Check).not() {
+		Err(anyhow::anyhow!("'fix' is only supported for 'check'."))?;
+	}
+	if args.append_dictionary.is_some()
+		&& matches!(args.task, Task::UnknownWords | Task::ImportDictionary).not()
+	{
+		Err(anyhow::anyhow!(
+			"'append-dictionary' is only supported for 'unknown-words' and 'import-dictionary'."
+		))?;
+	}
+	if args.import.is_empty().not() && matches!(args.task, Task::ImportDictionary).not() {
+		Err(anyhow::anyhow!(
+			"'import' is only supported for 'import-dictionary' and 'migrate-config'."
+		))?;
+	}
+	if args.output.is_some() {
+		Err(anyhow::anyhow!(
+			"'output' is only supported for 'migrate-config'."
+		))?;
+	}
+	if args.import.is_empty() && matches!(args.task, Task::ImportDictionary) {
+		Err(anyhow::anyhow!("'import-dictionary' requires 'import'."))?;
+	}
+	if args.tui && matches!(args.task, Task::Watch).not() {
+		Err(anyhow::anyhow!("'tui' is only supported for 'watch'."))?;
+	}
+	if args.notify && matches!(args.task, Task::Watch).not() {
+		Err(anyhow::anyhow!("'notify' is only supported for 'watch'."))?;
+	}
+	if args.tui && args.format != Format::Pretty {
+		Err(anyhow::anyhow!("'tui' can not be used with 'format'."))?;
+	}
+	if args.group_by == GroupBy::Rule
+		&& (args.tui || matches!(args.format, Format::Pretty | Format::Plain | Format::Short).not())
+	{
+		Err(anyhow::anyhow!(
+			"'group-by' is only supported for the 'pretty', 'plain' and 'short' formats."
+		))?;
+	}
+	if args.stdin && matches!(args.task, Task::Check).not() {
+		Err(anyhow::anyhow!("'stdin' is only supported for 'check'."))?;
+	}
+	if args.stdin && args.fix.is_some() && args.dry_run.not() {
+		Err(anyhow::anyhow!("'fix' with 'stdin' requires 'dry-run'."))?;
+	}
+	if args.fast_fail && matches!(args.task, Task::Check).not() {
+		Err(anyhow::anyhow!(
+			"'fast-fail' is only supported for 'check'."
+		))?;
+	}
+	if args.fast_fail && (args.fix.is_some() || args.write_baseline.is_some()) {
+		Err(anyhow::anyhow!(
+			"'fast-fail' can not be used with 'fix' or 'write-baseline'."
+		))?;
+	}
 
-	let world = lt_world::LtWorld::new(args.lt.root.clone().unwrap_or(".".into()));
+	if matches!(args.task, Task::Doctor) {
+		return doctor::run(&args, options_path.as_deref()).await;
+	}
+	if matches!(args.task, Task::ImportDictionary) {
+		return import_dictionary(&args);
+	}
 
-	match args.task {
-		Task::Check => check(args, lt, world).await?,
-		Task::Watch => watch(args, lt, world).await?,
+	let (mut lt, warnings) = LanguageTool::new_validated(&args.lt).await?;
+	for warning in warnings {
+		tracing::warn!("{}", warning);
 	}
+	fallback_switch(&mut lt);
 
-	Ok(())
+	let mut world = world(&args.lt)?;
+	if let (true, Some(path)) = (args.stdin, &args.path) {
+		let mut text = String::new();
+		std::io::stdin().read_to_string(&mut text)?;
+		// the unsaved content replaces the file on disk
+		world.use_shadow_file(path, text);
+	}
+
+	// stop the running check on Ctrl-C, so the backends are shut down
+	let cancellation = Cancellation::new();
+	tokio::spawn({
+		let cancellation = cancellation.clone();
+		async move {
+			if tokio::signal::ctrl_c().await.is_ok() {
+				cancellation.cancel();
+			}
+		}
+	});
+
+	let exit_code = match args.task {
+		Task::Check => check(args, lt, world, &cancellation).await?,
+		Task::Watch => {
+			watch(args, lt, world, &cancellation).await?;
+			ExitCode::SUCCESS
+		},
+		Task::Languages => {
+			languages(lt).await?;
+			ExitCode::SUCCESS
+		},
+		Task::Rules => {
+			rules(args, lt).await?;
+			ExitCode::SUCCESS
+		},
+		Task::UnknownWords => unknown_words(args, lt, world, &cancellation).await?,
+		Task::Serve => {
+			serve::run(args, lt, world, &cancellation).await?;
+			ExitCode::SUCCESS
+		},
+		Task::Doctor | Task::ImportDictionary | Task::MigrateConfig => unreachable!(),
+	};
+
+	Ok(exit_code)
 }
 
-async fn check(args: Args, mut lt: LanguageTool, mut world: LtWorld) -> anyhow::Result<()> {
-	handle_file(
-		args.path
-			.as_ref()
-			.or_else(|| args.lt.main.as_ref())
-			.context("No path or main specified")?,
+async fn check(
+	args: Args,
+	mut lt: LanguageTool,
+	world: LtWorld,
+	cancellation: &Cancellation,
+) -> anyhow::Result<ExitCode> {
+	// each of the mains is checked without a path or main
+	let paths = match args.path.as_ref().or(args.lt.main.as_ref()) {
+		Some(path) => vec![path.clone()],
+		None if args.lt.mains.is_empty().not() => args.lt.mains.clone(),
+		None => Err(anyhow::anyhow!("No path, main or mains specified"))?,
+	};
+	let Checked { severities, stopped, .. } = handle_files(
+		&paths,
 		&mut lt,
 		&args,
-		&mut world,
-		args.lt.chunk_size,
+		&world,
 		&mut Cache::new(),
 		args.path.is_none(),
+		cancellation,
 	)
 	.await?;
-	Ok(())
+
+	let failed = args
+		.fail_on
+		.is_some_and(|fail_on| severities.iter().any(|severity| *severity >= fail_on));
+	let exceeded = args
+		.max_issues
+		.is_some_and(|max_issues| severities.len() > max_issues);
+	if exceeded {
+		tracing::error!(
+			"Found {} issues, at most {} are allowed.",
+			severities.len(),
+			args.max_issues.unwrap_or_default()
+		);
+	}
+	if stopped || failed || exceeded {
+		Ok(ExitCode::FAILURE)
+	} else {
+		Ok(ExitCode::SUCCESS)
+	}
 }
 
-async fn watch(args: Args, mut lt: LanguageTool, mut world: LtWorld) -> anyhow::Result<()> {
+async fn watch(
+	args: Args,
+	mut lt: LanguageTool,
+	world: LtWorld,
+	cancellation: &Cancellation,
+) -> anyhow::Result<()> {
 	let (tx, rx) = std::sync::mpsc::channel();
 	let mut watcher = new_debouncer(Duration::from_secs_f64(args.delay), tx)?;
 	let mut cache = Cache::new();
+	// files read for the document of each checked file
+	let mut dependencies = HashMap::<PathBuf, Vec<PathBuf>>::new();
 	watcher
 		.watcher()
 		.watch(world.root(), RecursiveMode::Recursive)?;
 
-	for events in rx {
-		for event in events.unwrap() {
-			match event.path.extension() {
-				Some(ext) if ext == "typ" => {},
-				_ => continue,
+	let mut tui = args.tui.then(tui::Tui::new).transpose()?;
+	// the terminal interface starts with the path or the main files,
+	// absolute like the paths of the file events
+	let mut recheck = match (&tui, &args.path) {
+		(None, _) => Vec::new(),
+		(Some(_), Some(path)) if path.is_file() => vec![path.canonicalize()?],
+		(Some(_), _) => args
+			.lt
+			.main
+			.iter()
+			.chain(&args.lt.mains)
+			.map(|path| path.canonicalize())
+			.collect::<Result<_, _>>()?,
+	};
+	let mut jump = None;
+	// issues of the last check of each file, to notify about the new issues
+	let mut known = HashMap::new();
+	loop {
+		if let Some(tui) = &mut tui {
+			tui.draw()?;
+			match tui.input(Duration::from_millis(50))? {
+				tui::Action::None => {},
+				tui::Action::Recheck => {
+					cache = Cache::new();
+					recheck = dependencies.keys().cloned().collect();
+					recheck.sort();
+				},
+				tui::Action::Quit => break,
+				tui::Action::Jump(location) => {
+					jump = Some(location);
+					break;
+				},
 			}
-
-			handle_file(
-				&event.path,
-				&mut lt,
-				&args,
-				&mut world,
-				args.lt.chunk_size,
-				&mut cache,
-				false,
-			)
-			.await?;
+		}
+		let paths = if recheck.is_empty() {
+			// the terminal interface already waited for the input
+			let timeout = Duration::from_millis(if tui.is_some() { 0 } else { 100 });
+			let events = match rx.recv_timeout(timeout) {
+				Ok(events) => events.unwrap(),
+				Err(RecvTimeoutError::Timeout) if cancellation.is_cancelled() => break,
+				Err(RecvTimeoutError::Timeout) => continue,
+				Err(RecvTimeoutError::Disconnected) => break,
+			};
+			let changed = events
+				.into_iter()
+				.map(|event| event.path)
+				.collect::<Vec<_>>();
+			changed_paths(&args, &world, &dependencies, changed)
+		} else {
+			std::mem::take(&mut recheck)
+		};
+		if paths.is_empty() {
+			continue;
+		}
+		if let Some(tui) = &mut tui {
+			tui.checking(&paths)?;
+		}
+		let checked = handle_files(
+			&paths,
+			&mut lt,
+			&args,
+			&world,
+			&mut cache,
+			false,
+			cancellation,
+		)
+		.await?;
+		dependencies.extend(checked.dependencies);
+		if args.notify {
+			notification::new_issues(&mut known, &checked.reports);
+		}
+		if let Some(tui) = &mut tui {
+			tui.update(checked.reports);
 		}
 	}
+	// the terminal is restored before the location is printed
+	drop(tui);
+	if let Some(location) = jump {
+		println!("{}", location);
+	}
 	Ok(())
 }
 
-async fn handle_file(
-	path: &Path,
-	lt: &mut LanguageTool,
+/// Typst files to check for the changed files, with the files using them.
+/// The changed files are invalidated in the world.
+fn changed_paths(
 	args: &Args,
 	world: &LtWorld,
-	chunk_size: usize,
-	cache: &mut Cache,
-	include_all: bool,
-) -> anyhow::Result<()> {
-	let world = world.with_main(args.lt.main.clone().unwrap_or(path.to_owned()));
-	let doc = match world.compile() {
-		Ok(doc) => doc,
+	dependencies: &HashMap<PathBuf, Vec<PathBuf>>,
+	changed: Vec<PathBuf>,
+) -> Vec<PathBuf> {
+	let changed = changed
+		.into_iter()
+		.filter(|path| {
+			let relative = path.strip_prefix(world.root()).unwrap_or(path);
+			args.ignore_files
+				.iter()
+				.chain(&args.excluded)
+				.all(|pattern| pattern.matches_path(relative).not())
+		})
+		.collect::<Vec<_>>();
+	let ignored = git::ignored(world.root(), &changed);
+	let changed = changed
+		.into_iter()
+		.filter(|path| ignored.contains(path).not())
+		.collect::<Vec<_>>();
+	for path in &changed {
+		world.invalidate(path);
+		let name = path.file_name().and_then(|name| name.to_str());
+		if name.is_some_and(|name| DIRECTORY_OPTIONS_FILES.contains(&name)) {
+			args.directories.clear();
+		}
+	}
+	let mut paths = Vec::new();
+	for path in &changed {
+		match path.extension() {
+			Some(ext) if ext == "typ" => {},
+			_ => continue,
+		}
+		if paths.contains(path).not() {
+			paths.push(path.clone());
+		}
+	}
+	// files using a changed file (`#include`, `#import`, data, ...) are checked again
+	let mut dependents = dependencies
+		.iter()
+		.filter(|(path, files)| {
+			paths.contains(path).not() && files.iter().any(|file| changed.contains(file))
+		})
+		.map(|(path, _)| path.clone())
+		.collect::<Vec<_>>();
+	dependents.sort();
+	paths.extend(dependents);
+	paths
+}
+
+async fn languages(mut lt: LanguageTool) -> anyhow::Result<()> {
+	for language in lt.list_languages().await? {
+		println!("{}\t{}", language.long_code, language.name);
+	}
+	Ok(())
+}
+
+async fn rules(args: Args, mut lt: LanguageTool) -> anyhow::Result<()> {
+	let lang = args.language.context("No language specified")?;
+	for rule in lt.list_rules(lang).await? {
+		println!("{}\t{}\t{}", rule.id, rule.category, rule.description);
+	}
+	Ok(())
+}
+
+/// Count the words flagged by the spelling rules in the document.
+async fn unknown_words(
+	args: Args,
+	mut lt: LanguageTool,
+	world: LtWorld,
+	cancellation: &Cancellation,
+) -> anyhow::Result<ExitCode> {
+	let path = args
+		.path
+		.as_ref()
+		.or(args.lt.main.as_ref())
+		.context("No path or main specified")?;
+	let main = main_for(&args, &world, path);
+	let include_all = args.path.is_none();
+	let prepared = prepare(&main, &[path], &args, &world, include_all)?;
+	prepared.print_timings(&args, &main);
+	let files = match prepared.files {
+		Ok(files) => files,
 		Err(err) => {
-			if args.plain {
-				println!("Failed to compile document!");
-			} else {
-				println!("{}", "Failed to compile document!\n".red().bold());
+			output::compile_error(args.format, &err);
+			return Ok(ExitCode::FAILURE);
+		},
+	};
+	let texts = files
+		.iter()
+		.flat_map(|(_, paragraphs)| paragraphs)
+		.map(|(text, mapping)| (language(&args, mapping), text.clone()))
+		.collect::<Vec<_>>();
+	let checked = check_texts(&mut lt, &texts, &args, &ProgressBar::hidden(), cancellation).await?;
+
+	let mut counts = HashMap::<String, usize>::new();
+	let mappings = files
+		.iter()
+		.flat_map(|(_, paragraphs)| paragraphs)
+		.map(|(_, mapping)| mapping);
+	for (((lang, text), mut suggestions), mapping) in texts.iter().zip(checked).zip(mappings) {
+		args.directories
+			.retain(text, lang, mapping, &mut suggestions);
+		prepared.metadata.retain(text, lang, &mut suggestions);
+		for suggestion in suggestions {
+			if suggestion.is_spelling().not() || mapping.skips(&suggestion) {
+				continue;
 			}
-			for dia in err {
-				println!("\t{:?}", dia);
+			let Some(range) = suggestion.byte_range(text) else {
+				continue;
+			};
+			*counts.entry(text[range].to_owned()).or_default() += 1;
+		}
+	}
+	let mut words = counts
+		.into_iter()
+		.filter(|(_, count)| *count >= args.min_count)
+		.collect::<Vec<_>>();
+	words.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+	for (word, count) in &words {
+		println!("{}\t{}", count, word);
+	}
+
+	if let Some(path) = &args.append_dictionary {
+		let words = words.iter().map(|(word, _)| word.as_str());
+		append_dictionary(path, words)?;
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+/// Print the imported words as dictionary file lines or append them to the dictionary file.
+fn import_dictionary(args: &Args) -> anyhow::Result<ExitCode> {
+	let (dictionary, all) = typst_languagetool::dictionary::import_files(&args.import)?;
+	let mut languages = dictionary.into_iter().collect::<Vec<_>>();
+	languages.sort_by(|(a, _), (b, _)| a.cmp(b));
+	let lines = all
+		.into_iter()
+		.chain(languages.into_iter().flat_map(|(lang, words)| {
+			words
+				.into_iter()
+				.map(move |word| format!("{}:{}", lang, word))
+		}))
+		.collect::<Vec<_>>();
+	match &args.append_dictionary {
+		Some(path) => append_dictionary(path, lines.iter().map(String::as_str))?,
+		None => {
+			for line in &lines {
+				println!("{}", line);
 			}
-			return Ok(());
 		},
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+/// Write the options changed from the defaults, the words of the imported dictionaries are added to `dictionary`.
+fn migrate_config(args: &Args) -> anyhow::Result<ExitCode> {
+	let mut options = args.lt.clone();
+	// the loaded paths are relative to the current directory
+	for path in options
+		.dictionary_files
+		.iter_mut()
+		.chain(&mut options.dictionary_imports)
+		.chain(&mut options.font_paths)
+	{
+		*path = relative_to_output(args, path)?;
+	}
+	for file in &args.import {
+		let (dictionary, all) =
+			typst_languagetool::dictionary::import_files(std::slice::from_ref(file))?;
+		for (lang, words) in dictionary {
+			let existing = options.dictionary.entry(lang).or_default();
+			for word in words {
+				if existing.contains(&word).not() {
+					existing.push(word);
+				}
+			}
+		}
+		// the dictionary has no entry for all languages, the file is imported instead
+		if all.is_empty().not() {
+			let path = relative_to_output(args, file)?;
+			tracing::info!(
+				"Added {} to 'dictionary_imports' for {} words for all languages",
+				path.display(),
+				all.len()
+			);
+			if options.dictionary_imports.contains(&path).not() {
+				options.dictionary_imports.push(path);
+			}
+		}
+	}
+
+	let defaults = serde_json::to_value(LanguageToolOptions::default())?;
+	let mut value = serde_json::to_value(&options)?;
+	if let (serde_json::Value::Object(value), serde_json::Value::Object(defaults)) =
+		(&mut value, &defaults)
+	{
+		// unset options of the backend are null, which TOML can not represent
+		value.retain(|key, value| value.is_null().not() && defaults.get(key) != Some(value));
+	}
+	let extension = args
+		.output
+		.as_ref()
+		.and_then(|path| path.extension())
+		.and_then(|extension| extension.to_str());
+	let content = match extension {
+		Some("toml") => toml::to_string_pretty(&value)?,
+		Some("yaml" | "yml") => serde_yaml::to_string(&value)?,
+		_ => serde_json::to_string_pretty(&value)? + "\n",
+	};
+	match &args.output {
+		Some(path) => {
+			std::fs::write(path, content)?;
+			tracing::info!("Wrote the options to {}", path.display());
+		},
+		None => print!("{}", content),
+	}
+	Ok(ExitCode::SUCCESS)
+}
+
+/// Path relative to the directory of `--output`, absolute if it is outside of the directory.
+fn relative_to_output(args: &Args, path: &Path) -> anyhow::Result<PathBuf> {
+	let path = absolute(path)?;
+	let directory = match &args.output {
+		Some(output) => absolute(output)?
+			.parent()
+			.map(Path::to_owned)
+			.unwrap_or_default(),
+		None => std::env::current_dir()?,
 	};
+	Ok(path
+		.strip_prefix(&directory)
+		.map(Path::to_owned)
+		.unwrap_or(path))
+}
 
-	let file_id = world.file_id(path).unwrap();
-	let file_id_opt = include_all.not().then_some(file_id);
+/// Absolute path without `.` and `..`.
+fn absolute(path: &Path) -> anyhow::Result<PathBuf> {
+	let mut absolute = PathBuf::new();
+	for component in std::path::absolute(path)?.components() {
+		match component {
+			std::path::Component::CurDir => {},
+			std::path::Component::ParentDir => {
+				absolute.pop();
+			},
+			component => absolute.push(component),
+		}
+	}
+	Ok(absolute)
+}
 
-	let paragraphs = typst_languagetool::convert::document(&doc, chunk_size, file_id_opt);
-	let mut collector = typst_languagetool::FileCollector::new(file_id_opt, &world);
-	let mut next_cache = Cache::new();
-	for (text, mapping) in paragraphs {
-		let lang = mapping.long_language();
-		let suggestions = if let Some(suggestions) = cache.get(&text, &lang) {
-			suggestions
-		} else {
-			lt.check_text(lang.clone(), &text).await?
-		};
+/// Append the lines missing in the dictionary file.
+fn append_dictionary<'a>(
+	path: &Path,
+	lines: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<()> {
+	let existing = match std::fs::read_to_string(path) {
+		Ok(existing) => existing,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+		Err(err) => Err(err)?,
+	};
+	let mut known = existing.lines().map(str::trim).collect::<HashSet<_>>();
+	let mut appended = existing.clone();
+	if appended.is_empty().not() && appended.ends_with('\n').not() {
+		appended.push('\n');
+	}
+	let mut added = 0;
+	for line in lines {
+		if known.insert(line).not() {
+			continue;
+		}
+		appended.push_str(line);
+		appended.push('\n');
+		added += 1;
+	}
+	std::fs::write(path, appended)?;
+	tracing::info!("Added {} words to {}", added, path.display());
+	Ok(())
+}
+
+/// Compiled document with the paragraphs of the checked files.
+struct Prepared<'a> {
+	world: LtWorldRunning<'a>,
+	files: SourceResult<Vec<(Option<FileId>, Paragraphs)>>,
+	/// Options from the metadata of the document
+	metadata: DocumentOptions,
+	compile: Duration,
+	convert: Duration,
+}
+
+type Paragraphs = Vec<(String, Mapping)>;
+
+/// World for the root with the font, package and date options.
+fn world(options: &LanguageToolOptions) -> anyhow::Result<LtWorld> {
+	let fonts = FontOptions {
+		font_paths: options.font_paths.clone(),
+		include_system_fonts: options.include_system_fonts,
+	};
+	let packages = PackageOptions {
+		offline: options.offline,
+		package_path: options.package_path.clone(),
+		package_cache_path: options.package_cache_path.clone(),
+	};
+	let creation_date =
+		lt_world::creation_date(options.creation_date.as_deref()).map_err(anyhow::Error::msg)?;
+	let world = LtWorld::with_options(options.root.clone().unwrap_or(".".into()), fonts, packages)
+		.with_creation_date(creation_date);
+	Ok(world)
+}
+
+/// Main file for the path: the `main`, the first of the `mains` using the path or the path itself.
+fn main_for(args: &Args, world: &LtWorld, path: &Path) -> PathBuf {
+	args.lt
+		.main
+		.clone()
+		.or_else(|| world.main_for(&args.lt.mains, path))
+		.unwrap_or_else(|| path.to_owned())
+}
+
+/// File checked as its own main file, which is not specified as a main or the wrapper.
+fn isolated(args: &Args, world: &LtWorld, main: &Path) -> bool {
+	let id = world.file_id(main);
+	args.lt.main.is_none()
+		&& args.lt.mains.iter().all(|other| world.file_id(other) != id)
+		&& args
+			.lt
+			.wrapper
+			.iter()
+			.all(|wrapper| world.file_id(wrapper) != id)
+}
 
-		collector.add(&world, &suggestions, &mapping);
-		next_cache.insert(text, lang, suggestions);
+/// Compile the main file once for all files of the document.
+fn prepare<'a>(
+	main: &Path,
+	paths: &[&Path],
+	args: &Args,
+	world: &'a LtWorld,
+	include_all: bool,
+) -> anyhow::Result<Prepared<'a>> {
+	let world = match &args.lt.wrapper {
+		Some(wrapper) if include_all.not() && isolated(args, world, main) => {
+			world.with_wrapper(wrapper, main).map_err(|err| {
+				anyhow::anyhow!("Failed to read wrapper {}: {}", wrapper.display(), err)
+			})?
+		},
+		_ => world.with_main(main.to_owned()),
+	};
+	let start = Instant::now();
+	let doc = world.compile();
+	let compile = start.elapsed();
+	let start = Instant::now();
+	let metadata = doc.as_ref().map(DocumentOptions::new).unwrap_or_default();
+	let files = match doc {
+		Ok(doc) => Ok(paths
+			.iter()
+			.map(|path| {
+				let file_id = include_all.not().then(|| world.file_id(path).unwrap());
+				let mut paragraphs = typst_languagetool::convert::document_selection(
+					&doc,
+					&args.chunk_size,
+					file_id,
+					&args.selection,
+				)?;
+				if args.lt.check_comments {
+					let comments = typst_languagetool::source_comments(
+						&world,
+						&paragraphs,
+						file_id,
+						&args.chunk_size,
+					);
+					paragraphs.extend(comments);
+				}
+				let paragraphs = if args.lt.ltex_comments {
+					typst_languagetool::ltex_comments(&world, paragraphs)
+				} else {
+					paragraphs
+				};
+				let paragraphs = typst_languagetool::bibliography(
+					&world,
+					&doc,
+					paragraphs,
+					&args.lt.bibliography_fields,
+				);
+				let paragraphs =
+					typst_languagetool::list_items(&world, paragraphs, args.lt.join_list_items);
+				let paragraphs = typst_languagetool::detached_text(
+					paragraphs,
+					file_id,
+					args.lt.detached_text.unwrap_or_default(),
+				);
+				let paragraphs = typst_languagetool::headings(
+					&doc,
+					paragraphs,
+					args.lt.headings.unwrap_or_default(),
+					&args.lt.heading_disabled_checks,
+				);
+				// after the passes which change the text, the skipped words are ranges in the text
+				let paragraphs = typst_languagetool::skip_words(
+					paragraphs,
+					args.lt.min_word_length.unwrap_or_default(),
+					args.lt.skip_numeric_words,
+				);
+				Ok((file_id, paragraphs))
+			})
+			.collect::<anyhow::Result<_>>()?),
+		Err(err) => Err(err),
+	};
+	Ok(Prepared {
+		world,
+		files,
+		metadata,
+		compile,
+		convert: start.elapsed(),
+	})
+}
+
+impl Prepared<'_> {
+	fn print_timings(&self, args: &Args, main: &Path) {
+		timing(args, "compile", self.compile, main.display());
+		if self.files.is_ok() {
+			timing(args, "convert", self.convert, main.display());
+		}
 	}
-	*cache = next_cache;
+}
 
-	let diagnostics = collector.finish();
+/// Print the duration of a stage with `--timings`.
+fn timing(args: &Args, stage: &str, duration: Duration, detail: impl std::fmt::Display) {
+	if args.timings {
+		tracing::info!("{:<8} {:>12.2?}  {}", stage, duration, detail);
+	}
+}
 
-	if include_all {
-		if args.plain {
-			plain_start();
-			for diagnostic in diagnostics {
-				let id = diagnostic.locations[0].0;
-				let source = world.source(id).unwrap();
-				let path = id.vpath().as_rootless_path();
-				output::plain(&path, &source, diagnostic);
-			}
-			plain_end();
+/// Log the switch to the fallback backend, for `--tui` only with `--log-file`.
+fn fallback_switch(lt: &mut LanguageTool) {
+	if let Some(message) = lt.take_fallback_switch() {
+		tracing::warn!("{}", message);
+	}
+}
+
+/// Check the texts, with `--timings` or `--auto-chunk-size` in batches to time each request.
+/// The progress bar advances with each checked text.
+async fn check_texts(
+	lt: &mut LanguageTool,
+	texts: &[(String, String)],
+	args: &Args,
+	progress: &ProgressBar,
+	cancellation: &Cancellation,
+) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+	if args.verbose >= 2 {
+		tracing::debug!(
+			"Backend request: {} chunks, {} chars",
+			texts.len(),
+			texts
+				.iter()
+				.map(|(_, text)| text.chars().count())
+				.sum::<usize>()
+		);
+	}
+	if args.timings.not() && args.chunk_size.is_auto().not() {
+		let checked = check_with_progress(lt, texts, args.lt.jobs, progress, cancellation).await;
+		fallback_switch(lt);
+		return checked;
+	}
+	let mut checked = Vec::with_capacity(texts.len());
+	for batch in texts.chunks(args.lt.jobs.max(1)) {
+		let start = Instant::now();
+		let batch_checked =
+			check_with_progress(lt, batch, args.lt.jobs, progress, cancellation).await;
+		fallback_switch(lt);
+		checked.extend(batch_checked?);
+		let chars = batch
+			.iter()
+			.map(|(_, text)| text.chars().count())
+			.sum::<usize>();
+		args.chunk_size
+			.observe(chars / batch.len(), start.elapsed());
+		timing(
+			args,
+			"check",
+			start.elapsed(),
+			format!("{} chunks, {} chars", batch.len(), chars),
+		);
+	}
+	Ok(checked)
+}
+
+/// Check the texts and update the progress bar with the count of the backend while checking.
+async fn check_with_progress(
+	lt: &mut LanguageTool,
+	texts: &[(String, String)],
+	jobs: usize,
+	progress: &ProgressBar,
+	cancellation: &Cancellation,
+) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+	let check = lt.check_texts(texts, jobs, cancellation);
+	if progress.is_hidden() {
+		return check.await;
+	}
+	let (start, position) = (cancellation.checked(), progress.position());
+	tokio::pin!(check);
+	let mut interval = tokio::time::interval(Duration::from_millis(100));
+	loop {
+		tokio::select! {
+			checked = &mut check => {
+				progress.set_position(position + texts.len() as u64);
+				return checked;
+			},
+			_ = interval.tick() => {
+				let checked = cancellation.checked().saturating_sub(start).min(texts.len());
+				progress.set_position(position + checked as u64);
+			},
+		}
+	}
+}
+
+/// Result of checking files.
+struct Checked {
+	severities: Vec<Severity>,
+	/// Files read for the document of each checked file
+	dependencies: Vec<(PathBuf, Vec<PathBuf>)>,
+	/// Results of the checked files with `--tui`, `--notify`, `--group-by=rule` or `--write-baseline`,
+	/// only written for `--group-by=rule`
+	reports: Vec<(PathBuf, tui::Report)>,
+	/// Stopped at the first diagnostic or compile error with `--fast-fail`
+	stopped: bool,
+}
+
+/// Compiles each document once and in parallel, the paragraphs of all files are checked together.
+/// The results are written in the order of the documents.
+async fn handle_files(
+	paths: &[PathBuf],
+	lt: &mut LanguageTool,
+	args: &Args,
+	world: &LtWorld,
+	cache: &mut Cache,
+	include_all: bool,
+	cancellation: &Cancellation,
+) -> anyhow::Result<Checked> {
+	// files with the same main file share the compilation
+	let mut documents = Vec::<(PathBuf, Vec<&Path>)>::new();
+	for path in paths {
+		let main = main_for(args, world, path);
+		match documents.iter_mut().find(|(existing, _)| *existing == main) {
+			Some((_, paths)) => paths.push(path),
+			None => documents.push((main, vec![path])),
+		}
+	}
+	for (_, paths) in &documents {
+		for path in paths {
+			output::file_started(args.format, path)?;
+		}
+	}
+	let prepared = std::thread::scope(|scope| {
+		documents
+			.iter()
+			.map(|(main, paths)| scope.spawn(|| prepare(main, paths, args, world, include_all)))
+			.collect::<Vec<_>>()
+			.into_iter()
+			.map(|handle| handle.join().unwrap())
+			.collect::<anyhow::Result<Vec<_>>>()
+	})?;
+	for ((main, _), document) in documents.iter().zip(&prepared) {
+		document.print_timings(args, main);
+	}
+
+	let paragraphs = || {
+		prepared
+			.iter()
+			.flat_map(|document| &document.files)
+			.flatten()
+			.flat_map(|(_, paragraphs)| paragraphs)
+	};
+	let cached = paragraphs()
+		.map(|(text, mapping)| cache.get(text, &language(args, mapping)))
+		.collect::<Vec<_>>();
+	let missing = paragraphs()
+		.zip(&cached)
+		.filter(|(_, suggestions)| suggestions.is_none())
+		.map(|((text, mapping), _)| (language(args, mapping), text.clone()))
+		.collect::<Vec<_>>();
+	if args.verbose >= 1 {
+		tracing::info!(
+			"Cache: {} hits, {} misses",
+			cached.len() - missing.len(),
+			missing.len()
+		);
+	}
+	// streamed formats write the diagnostics after each batch
+	let stream = streamed(args);
+	// with the filters of the file the diagnostics are only known after all chunks of the file
+	let fail_early = args.fast_fail && args.baseline.is_none() && args.diff_base.is_none();
+	let progress = progress(args, cached.len());
+	let mut pending = Pending::new(
+		&missing,
+		if stream || fail_early {
+			args.lt.jobs.max(1)
 		} else {
-			pretty_start();
-			for diagnostic in diagnostics {
-				let id = diagnostic.locations[0].0;
-				let source = world.source(id).unwrap();
-				let path = id.vpath().as_rootless_path();
-				output::pretty(&path, &source, diagnostic);
-			}
+			missing.len()
+		},
+	);
+
+	let mut cached = cached.into_iter();
+	let mut severities = Vec::new();
+	let mut dependencies = Vec::new();
+	let mut reports = Vec::new();
+	let mut stopped = false;
+	'documents: for ((_, paths), document) in documents.iter().zip(prepared) {
+		let files = document.world.dependencies();
+		for path in paths {
+			dependencies.push((path.to_path_buf(), files.clone()));
 		}
-	} else {
-		let source = world.source(file_id).unwrap();
-		if args.plain {
-			plain_start();
-			for diagnostic in diagnostics {
-				output::plain(&path, &source, diagnostic);
+		let files = match document.files {
+			Ok(files) => files,
+			Err(err) if args.tui => {
+				let errors = err
+					.iter()
+					.map(|error| error.message.to_string())
+					.collect::<Vec<_>>();
+				for path in paths {
+					reports.push((path.to_path_buf(), Err(errors.clone())));
+				}
+				continue;
+			},
+			Err(err) => {
+				progress.suspend(|| output::compile_error(args.format, &err));
+				for path in paths {
+					output::file_finished(args.format, path, 0)?;
+				}
+				if args.fast_fail {
+					stopped = true;
+					break;
+				}
+				continue;
+			},
+		};
+		for (path, (file_id, paragraphs)) in paths.iter().zip(files) {
+			let mut mapping_duration = Duration::ZERO;
+			let mut collector = FileCollector::new(file_id, &document.world);
+			let mut streamed = 0;
+			if stream {
+				progress.suspend(|| output::begin(args.format));
 			}
-			plain_end();
-		} else {
-			pretty_start();
-			println!("{}", "\n\nChecking Document\n".green().bold());
-			for diagnostic in diagnostics {
-				output::pretty(&path, &source, diagnostic);
+			for (text, mapping) in paragraphs {
+				let lang = language(args, &mapping);
+				// the checked texts advance the progress while checking
+				let suggestions = match cached.next().flatten() {
+					Some(suggestions) => {
+						progress.inc(1);
+						suggestions
+					},
+					None => pending.next(lt, args, &progress, cancellation).await?,
+				};
+				// the cache keeps the suggestions of the backend
+				let mut kept = suggestions.clone();
+				args.directories.retain(&text, &lang, &mapping, &mut kept);
+				args.lt.retain_file_checks(&mapping, &mut kept);
+				document.metadata.retain(&text, &lang, &mut kept);
+
+				let start = Instant::now();
+				if stream {
+					let added = collector.add(&document.world, &kept, &mapping).to_vec();
+					for mut diagnostic in added {
+						if include_all && excluded(args, &diagnostic) {
+							continue;
+						}
+						diagnostic.filter_replacements(
+							args.lt.max_suggestions,
+							args.lt.drop_similar_suggestions,
+						);
+						diagnostic.apply_rule_options(
+							&args.lt.rule_severity,
+							args.lt.comment_severity,
+							&args.lt.unnecessary_rules,
+						);
+						severities.push(diagnostic.severity);
+						streamed += 1;
+						progress.suspend(|| {
+							output::diagnostic(
+								args.format,
+								locate(path, &document.world, diagnostic, include_all),
+							)
+						})?;
+					}
+				} else {
+					collector.add(&document.world, &kept, &mapping);
+				}
+				mapping_duration += start.elapsed();
+				cache.insert(&text, &lang, suggestions);
+				// the remaining chunks are skipped after the first failing diagnostic
+				let found = collector.diagnostics().iter().any(|diagnostic| {
+					(include_all && excluded(args, diagnostic)).not()
+						&& failing(
+							args,
+							diagnostic
+								.rule_severity(&args.lt.rule_severity, args.lt.comment_severity),
+						)
+				});
+				if fail_early && found {
+					stopped = true;
+					break;
+				}
+			}
+			if stream {
+				timing(args, "mapping", mapping_duration, path.display());
+				progress.suspend(|| output::end(args.format));
+				output::file_finished(args.format, path, streamed)?;
+				if stopped {
+					break 'documents;
+				}
+				continue;
+			}
+			let start = Instant::now();
+			let mut diagnostics = collector.finish();
+			if include_all {
+				diagnostics.retain(|diagnostic| excluded(args, diagnostic).not());
+			}
+			for diagnostic in &mut diagnostics {
+				diagnostic
+					.filter_replacements(args.lt.max_suggestions, args.lt.drop_similar_suggestions);
+				diagnostic.apply_rule_options(
+					&args.lt.rule_severity,
+					args.lt.comment_severity,
+					&args.lt.unnecessary_rules,
+				);
+			}
+			timing(
+				args,
+				"mapping",
+				mapping_duration + start.elapsed(),
+				path.display(),
+			);
+			severities.extend(progress.suspend(|| {
+				finish_file(
+					path,
+					&document.world,
+					diagnostics,
+					args,
+					include_all,
+					&mut reports,
+				)
+			})?);
+			if args.fast_fail && severities.iter().any(|severity| failing(args, *severity)) {
+				stopped = true;
+				break 'documents;
 			}
 		}
 	}
-	Ok(())
+	progress.finish_and_clear();
+	if let Some(path) = &args.write_baseline {
+		let diagnostics = reports
+			.iter()
+			.filter_map(|(_, report)| report.as_ref().ok())
+			.flatten()
+			.cloned()
+			.collect::<Vec<_>>();
+		Baseline::write(path, &diagnostics)?;
+		tracing::info!("Wrote {} issues to {}", diagnostics.len(), path.display());
+	} else if args.quiet {
+		output::summary(&severities);
+	} else if args.group_by == GroupBy::Rule {
+		let diagnostics = reports
+			.iter()
+			.filter_map(|(_, report)| report.as_ref().ok())
+			.flatten()
+			.cloned()
+			.collect();
+		output::by_rule(args.format, diagnostics);
+	}
+	Ok(Checked {
+		severities,
+		dependencies,
+		reports,
+		stopped,
+	})
 }
 
-fn plain_start() {
-	println!("START");
+/// Progress of the checked paragraphs on stderr for `check`,
+/// hidden without a terminal, for the machine readable formats and while logging the timings.
+fn progress(args: &Args, paragraphs: usize) -> ProgressBar {
+	let shown = matches!(args.task, Task::Check)
+		&& matches!(args.format, Format::Pretty | Format::Short)
+		&& args.quiet.not()
+		&& args.timings.not()
+		&& std::io::stderr().is_terminal();
+	if shown.not() {
+		return ProgressBar::hidden();
+	}
+	ProgressBar::new(paragraphs as u64).with_style(
+		ProgressStyle::with_template("{bar:40} {pos}/{len} paragraphs, {eta} left").unwrap(),
+	)
 }
 
-fn plain_end() {
-	println!("END");
+/// Language for the chunk, the options files of the directories take precedence.
+fn language(args: &Args, mapping: &Mapping) -> String {
+	if let Some(lang) = mapping.comment_language() {
+		return lang.to_string();
+	}
+	args.directories
+		.language(mapping)
+		.or_else(|| args.lt.language(mapping))
+		.unwrap_or_else(|| mapping.long_language())
 }
 
-fn pretty_start() {
-	println!("{}", "\n\nChecking Document\n".green().bold());
+/// Diagnostic in a file excluded by the `typst.toml`.
+fn excluded(args: &Args, diagnostic: &Diagnostic) -> bool {
+	let path = diagnostic.locations[0].0.vpath().as_rootless_path();
+	args.excluded
+		.iter()
+		.any(|pattern| pattern.matches_path(path))
 }
 
-#[derive(Debug)]
-struct Cache {
-	cache: HashMap<String, (String, Vec<Suggestion>)>,
+/// Diagnostics can be written while checking, if they are written one after another
+/// and no option needs all diagnostics of the file.
+/// Diagnostic with the severity stops the check with `--fast-fail`, with `--fail-on` only the failing severities.
+fn failing(args: &Args, severity: Severity) -> bool {
+	args.fail_on.is_none_or(|fail_on| severity >= fail_on)
 }
 
-impl Cache {
-	pub fn new() -> Self {
-		Self { cache: HashMap::new() }
+fn streamed(args: &Args) -> bool {
+	output::streamable(args.format)
+		&& args.tui.not()
+		&& args.notify.not()
+		&& args.group_by == GroupBy::File
+		&& args.quiet.not()
+		&& args.fix.is_none()
+		&& args.baseline.is_none()
+		&& args.write_baseline.is_none()
+		&& args.diff_base.is_none()
+}
+
+/// Texts checked in batches when needed, so the results can be written before all texts are checked.
+struct Pending<'a> {
+	texts: &'a [(String, String)],
+	batch: usize,
+	position: usize,
+	checked: std::vec::IntoIter<Vec<Suggestion>>,
+}
+
+impl<'a> Pending<'a> {
+	fn new(texts: &'a [(String, String)], batch: usize) -> Self {
+		Self {
+			texts,
+			batch: batch.max(1),
+			position: 0,
+			checked: Vec::new().into_iter(),
+		}
 	}
 
-	pub fn get(&mut self, text: &str, lang: &str) -> Option<Vec<Suggestion>> {
-		let entry = self.cache.remove(text)?;
-		(lang == entry.0).then_some(entry.1)
+	/// Suggestions for the next text, the next batch is checked if necessary.
+	async fn next(
+		&mut self,
+		lt: &mut LanguageTool,
+		args: &Args,
+		progress: &ProgressBar,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		if let Some(suggestions) = self.checked.next() {
+			return Ok(suggestions);
+		}
+		let end = (self.position + self.batch).min(self.texts.len());
+		let batch = &self.texts[self.position..end];
+		self.checked = check_texts(lt, batch, args, progress, cancellation)
+			.await?
+			.into_iter();
+		self.position = end;
+		Ok(self.checked.next().unwrap_or_default())
 	}
+}
+
+fn finish_file(
+	path: &Path,
+	world: &LtWorldRunning,
+	diagnostics: Vec<Diagnostic>,
+	args: &Args,
+	include_all: bool,
+	reports: &mut Vec<(PathBuf, tui::Report)>,
+) -> anyhow::Result<Vec<Severity>> {
+	let diagnostics = diagnostics
+		.into_iter()
+		.map(|diagnostic| locate(path, world, diagnostic, include_all))
+		.collect::<Vec<_>>();
 
-	pub fn insert(&mut self, text: String, lang: String, suggestions: Vec<Suggestion>) {
-		self.cache.insert(text, (lang, suggestions));
+	// the baseline is written once for the diagnostics of all files
+	if args.write_baseline.is_some() {
+		reports.push((path.to_owned(), Ok(diagnostics)));
+		return Ok(Vec::new());
+	}
+	let diagnostics = match &args.baseline {
+		Some(baseline) => baseline.filter(diagnostics),
+		None => diagnostics,
+	};
+	let diagnostics = match &args.diff_base {
+		Some(base) => {
+			let changed = git::ChangedLines::new(world.root(), base)?;
+			diagnostics
+				.into_iter()
+				.filter(|(_, _, diagnostic)| {
+					diagnostic.locations.iter().any(|(id, range)| {
+						let (Some(path), Ok(source)) =
+							(id.vpath().resolve(world.root()), world.source(*id))
+						else {
+							return false;
+						};
+						let start = source.byte_to_line(range.start).unwrap_or(0) + 1;
+						let end = source.byte_to_line(range.end).unwrap_or(start) + 1;
+						changed.contains(&path, start..(end + 1))
+					})
+				})
+				.collect()
+		},
+		None => diagnostics,
+	};
+
+	if let Some(mode) = args.fix {
+		return apply_fixes(mode, args.dry_run, world, diagnostics);
+	}
+
+	let severities: Vec<_> = diagnostics
+		.iter()
+		.map(|(_, _, diagnostic)| diagnostic.severity)
+		.collect();
+	// the diagnostics grouped by rule are written after all files
+	if args.tui || args.group_by == GroupBy::Rule {
+		reports.push((path.to_owned(), Ok(diagnostics)));
+		return Ok(severities);
+	}
+	if args.notify {
+		reports.push((path.to_owned(), Ok(diagnostics.clone())));
+	}
+	if args.quiet {
+		return Ok(severities);
+	}
+	output::write(args.format, diagnostics)?;
+	output::file_finished(args.format, path, severities.len())?;
+	Ok(severities)
+}
+
+/// Path and source for the main location of the diagnostic.
+fn locate(
+	path: &Path,
+	world: &LtWorldRunning,
+	diagnostic: Diagnostic,
+	include_all: bool,
+) -> (PathBuf, Source, Diagnostic) {
+	let id = diagnostic.locations[0].0;
+	let source = world.source(id).unwrap();
+	let path = if include_all {
+		id.vpath().as_rootless_path().to_owned()
+	} else {
+		path.to_owned()
+	};
+	(path, source, diagnostic)
+}
+
+/// Returns the severities of the diagnostics without a fix.
+fn apply_fixes(
+	mode: FixMode,
+	dry_run: bool,
+	world: &LtWorld,
+	diagnostics: Vec<(PathBuf, Source, Diagnostic)>,
+) -> anyhow::Result<Vec<Severity>> {
+	let mut remaining = Vec::new();
+	let mut files = Vec::<(FileId, PathBuf, Source, Vec<fix::Edit>)>::new();
+	for (path, source, diagnostic) in diagnostics {
+		let replacement = match (mode, &*diagnostic.replacements) {
+			(FixMode::First, [first, ..]) => Some(first),
+			(FixMode::Unambiguous, [only]) => Some(only),
+			_ => None,
+		};
+		// diagnostics spanning multiple source ranges can not be fixed with one replacement
+		let (Some(replacement), [(id, range)]) = (replacement, diagnostic.locations.as_slice())
+		else {
+			remaining.push(diagnostic.severity);
+			continue;
+		};
+		let edit = fix::Edit {
+			range: range.clone(),
+			replacement: replacement.clone(),
+			severity: diagnostic.severity,
+		};
+		match files.iter_mut().find(|(file, ..)| file == id) {
+			Some((.., edits)) => edits.push(edit),
+			None => files.push((*id, path, source, vec![edit])),
+		}
+	}
+
+	for (id, path, source, edits) in files {
+		let (text, applied, skipped) = fix::apply(source.text(), edits);
+		remaining.extend(skipped.iter().map(|edit| edit.severity));
+		if dry_run {
+			print!(
+				"{}",
+				fix::diff(&path.to_string_lossy(), source.text(), &applied)
+			);
+			continue;
+		}
+		let Some(file) = id
+			.package()
+			.is_none()
+			.then(|| id.vpath().resolve(world.root()))
+			.flatten()
+		else {
+			tracing::warn!("Skipping fixes for {}", path.display());
+			continue;
+		};
+		std::fs::write(&file, text)?;
+		tracing::info!("Fixed {} issues in {}", applied.len(), path.display());
+	}
+	Ok(remaining)
+}
+
+/// Environment variable for the log filter (`debug`, `typst_languagetool=trace`, ...).
+const LOG_ENV: &str = "TYPST_LANGUAGETOOL_LOG";
+
+/// Log to stderr or the file, span durations are logged with `debug`.
+fn init_logging(level: &str, file: Option<&Path>) -> anyhow::Result<()> {
+	let filter = tracing_subscriber::EnvFilter::try_from_env(LOG_ENV)
+		.or_else(|_| tracing_subscriber::EnvFilter::try_new(level))?;
+	let builder = tracing_subscriber::fmt()
+		.with_env_filter(filter)
+		.with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+		.without_time()
+		.with_target(false);
+	match file {
+		Some(path) => builder
+			.with_ansi(false)
+			.with_writer(std::sync::Mutex::new(std::fs::File::create(path)?))
+			.init(),
+		None => builder
+			.with_ansi(std::io::stderr().is_terminal())
+			.with_writer(std::io::stderr)
+			.init(),
+	}
+	Ok(())
+}
+
+fn parse_pages(value: &str) -> Result<RangeInclusive<usize>, String> {
+	let parse = |page: &str| {
+		page.trim()
+			.parse::<usize>()
+			.map_err(|err| format!("Invalid page '{}': {}", page, err))
+	};
+	let (start, end) = match value.split_once('-') {
+		Some((start, end)) => (parse(start)?, parse(end)?),
+		None => (parse(value)?, parse(value)?),
+	};
+	if start > end {
+		return Err(format!(
+			"Invalid page range '{}': the start is after the end",
+			value
+		));
 	}
+	Ok(start..=end)
 }
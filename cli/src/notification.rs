@@ -0,0 +1,66 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{baseline::Baseline, output::byte_to_position, tui::Report};
+
+/// Listed issues in the notification.
+const MAX_LISTED: usize = 5;
+
+/// Send a desktop notification for the issues not found in the last check of the files,
+/// the files which failed to compile keep their last issues.
+pub fn new_issues(known: &mut HashMap<PathBuf, Baseline>, reports: &[(PathBuf, Report)]) {
+	let mut issues = Vec::new();
+	let mut files = 0;
+	for (path, report) in reports {
+		let Ok(diagnostics) = report else {
+			continue;
+		};
+		let new = match known.get(path) {
+			Some(baseline) => baseline.filter(diagnostics.clone()),
+			None => diagnostics.clone(),
+		};
+		known.insert(path.clone(), Baseline::new(diagnostics));
+		if new.is_empty() {
+			continue;
+		}
+		files += 1;
+		issues.extend(new);
+	}
+	if issues.is_empty() {
+		return;
+	}
+
+	let summary = match (issues.len(), files) {
+		(1, _) => String::from("1 new issue"),
+		(count, 1) => format!("{} new issues", count),
+		(count, files) => format!("{} new issues in {} files", count, files),
+	};
+	let mut body = issues
+		.iter()
+		.take(MAX_LISTED)
+		.map(|(path, source, diagnostic)| {
+			let (line, column) = byte_to_position(source, diagnostic.locations[0].1.start);
+			let name = path
+				.file_name()
+				.unwrap_or(path.as_os_str())
+				.to_string_lossy();
+			format!(
+				"{}:{}:{} {}",
+				name,
+				line + 1,
+				column + 1,
+				diagnostic.message
+			)
+		})
+		.collect::<Vec<_>>();
+	if issues.len() > MAX_LISTED {
+		body.push(format!("and {} more", issues.len() - MAX_LISTED));
+	}
+	let result = notify_rust::Notification::new()
+		.appname("typst-languagetool")
+		.summary(&summary)
+		.body(&body.join("\n"))
+		.show();
+	if let Err(err) = result {
+		tracing::warn!("Failed to send the notification: {}", err);
+	}
+}
@@ -1,31 +1,273 @@
-use std::{io::stdout, io::Write, ops::Not, path::Path};
+use std::{
+	collections::HashMap,
+	io::stdout,
+	io::Write,
+	ops::Not,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
 
 use annotate_snippets::{Level, Renderer, Snippet};
-use typst::syntax::Source;
-use typst_languagetool::Diagnostic;
+use colored::Colorize;
+use typst::{diag::SourceDiagnostic, syntax::Source};
+use typst_languagetool::{Diagnostic, Severity};
+
+use crate::Format;
 
 const MAX_SUGGESTIONS: usize = 20;
 
-pub fn plain(file: &Path, source: &Source, diagnostic: Diagnostic) {
+pub fn write(
+	format: Format,
+	diagnostics: Vec<(PathBuf, Source, Diagnostic)>,
+) -> anyhow::Result<()> {
+	match format {
+		Format::Sarif => sarif(diagnostics)?,
+		Format::Codeclimate => codeclimate(diagnostics)?,
+		Format::Junit => junit(diagnostics)?,
+		Format::Csv => csv(diagnostics)?,
+		Format::Pretty | Format::Plain | Format::Short | Format::Ndjson => {
+			begin(format);
+			for diagnostic in diagnostics {
+				self::diagnostic(format, diagnostic)?;
+			}
+			end(format);
+		},
+	}
+	Ok(())
+}
+
+/// Formats writing the diagnostics one after another, so they can be written while checking.
+pub fn streamable(format: Format) -> bool {
+	matches!(
+		format,
+		Format::Pretty | Format::Plain | Format::Short | Format::Ndjson
+	)
+}
+
+/// Diagnostics of all files with the occurrences of each rule together, the most frequent rule first.
+/// The pretty format lists the occurrences below the rule, the other formats only change the order.
+pub fn by_rule(format: Format, diagnostics: Vec<(PathBuf, Source, Diagnostic)>) {
+	let mut rules = Vec::<(Arc<str>, Vec<(PathBuf, Source, Diagnostic)>)>::new();
+	for diagnostic in diagnostics {
+		match rules
+			.iter_mut()
+			.find(|(rule, _)| *rule == diagnostic.2.rule_id)
+		{
+			Some((_, occurrences)) => occurrences.push(diagnostic),
+			None => rules.push((diagnostic.2.rule_id.clone(), vec![diagnostic])),
+		}
+	}
+	rules.sort_by(|(a, a_occurrences), (b, b_occurrences)| {
+		b_occurrences
+			.len()
+			.cmp(&a_occurrences.len())
+			.then_with(|| a.cmp(b))
+	});
+
+	begin(format);
+	for (rule, occurrences) in rules {
+		if format != Format::Pretty {
+			for occurrence in occurrences {
+				match format {
+					Format::Plain => plain(&occurrence.0, &occurrence.1, occurrence.2),
+					_ => short(&occurrence.0, &occurrence.1, occurrence.2),
+				}
+			}
+			continue;
+		}
+		let description = occurrences[0].2.rule_description.clone();
+		println!(
+			"{} {} {}",
+			rule.bold(),
+			description,
+			format!("({})", occurrences.len()).dimmed()
+		);
+		for (path, source, diagnostic) in occurrences {
+			let range = diagnostic.locations[0].1.clone();
+			let (line, column) = byte_to_position(&source, range.start);
+			let text = source.get(range).unwrap_or_default();
+			let replacement = diagnostic
+				.replacements
+				.first()
+				.map(|replacement| format!(" -> {}", replacement.green()))
+				.unwrap_or_default();
+			println!(
+				"  {}:{}:{} {}{} {}",
+				path.display(),
+				line + 1,
+				column + 1,
+				text.trim().yellow(),
+				replacement,
+				diagnostic.message.dimmed()
+			);
+		}
+		println!();
+	}
+	end(format);
+}
+
+/// Number of diagnostics for each severity, for `--quiet`.
+pub fn summary(severities: &[Severity]) {
+	if severities.is_empty() {
+		println!("No issues");
+		return;
+	}
+	let count = |severity| {
+		severities
+			.iter()
+			.filter(|other| **other == severity)
+			.count()
+	};
+	println!(
+		"{} issues ({} errors, {} warnings, {} info)",
+		severities.len(),
+		count(Severity::Error),
+		count(Severity::Warning),
+		count(Severity::Info),
+	);
+}
+
+/// Start of the diagnostics for a file in a streamable format.
+pub fn begin(format: Format) {
+	match format {
+		Format::Pretty => println!("{}", "\n\nChecking Document\n".green().bold()),
+		Format::Plain => println!("START"),
+		_ => {},
+	}
+}
+
+/// End of the diagnostics for a file in a streamable format.
+pub fn end(format: Format) {
+	if format == Format::Plain {
+		println!("END");
+	}
+}
+
+/// Single diagnostic in a streamable format.
+pub fn diagnostic(
+	format: Format,
+	(path, source, diagnostic): (PathBuf, Source, Diagnostic),
+) -> anyhow::Result<()> {
+	match format {
+		Format::Pretty => pretty(&path, &source, diagnostic),
+		Format::Plain => plain(&path, &source, diagnostic),
+		Format::Short => short(&path, &source, diagnostic),
+		Format::Ndjson => ndjson_diagnostic(&path, &source, diagnostic)?,
+		_ => unreachable!("{:?} is not streamable", format),
+	}
+	Ok(())
+}
+
+/// Event before the file is checked, only for NDJSON.
+pub fn file_started(format: Format, path: &Path) -> anyhow::Result<()> {
+	if format == Format::Ndjson {
+		event(serde_json::json!({
+			"event": "file-started",
+			"path": uri(path),
+		}))?;
+	}
+	Ok(())
+}
+
+/// Event after the diagnostics for the file, only for NDJSON.
+pub fn file_finished(format: Format, path: &Path, diagnostics: usize) -> anyhow::Result<()> {
+	if format == Format::Ndjson {
+		event(serde_json::json!({
+			"event": "file-finished",
+			"path": uri(path),
+			"diagnostics": diagnostics,
+		}))?;
+	}
+	Ok(())
+}
+
+pub fn compile_error(format: Format, errors: &[SourceDiagnostic]) {
+	match format {
+		Format::Pretty => println!("{}", "Failed to compile document!\n".red().bold()),
+		Format::Plain => println!("Failed to compile document!"),
+		Format::Short => {
+			for error in errors {
+				println!("error: {}", error.message);
+			}
+			return;
+		},
+		Format::Ndjson => {
+			let errors = errors
+				.iter()
+				.map(|error| error.message.as_str())
+				.collect::<Vec<_>>();
+			_ = event(serde_json::json!({
+				"event": "compile-error",
+				"errors": errors,
+			}));
+			return;
+		},
+		// keep stdout valid for the structured formats
+		Format::Sarif | Format::Codeclimate | Format::Junit | Format::Csv => {
+			tracing::error!("Failed to compile document!");
+			for error in errors {
+				tracing::error!("\t{:?}", error);
+			}
+			return;
+		},
+	}
+	for error in errors {
+		println!("\t{:?}", error);
+	}
+}
+
+fn ndjson_diagnostic(path: &Path, source: &Source, diagnostic: Diagnostic) -> anyhow::Result<()> {
+	let mut value = json(path, source, diagnostic);
+	value["event"] = "diagnostic".into();
+	event(value)
+}
+
+/// JSON object for the diagnostic, lines and columns start at 1.
+pub fn json(path: &Path, source: &Source, diagnostic: Diagnostic) -> serde_json::Value {
+	let (start_line, start_column) = byte_to_position(source, diagnostic.locations[0].1.start);
+	let (end_line, end_column) = byte_to_position(source, diagnostic.locations[0].1.end);
+	serde_json::json!({
+		"path": uri(path),
+		"start": { "line": start_line + 1, "column": start_column + 1 },
+		"end": { "line": end_line + 1, "column": end_column + 1 },
+		"severity": diagnostic.severity,
+		"message": diagnostic.message,
+		"rule": diagnostic.rule_id,
+		"category": diagnostic.category,
+		"replacements": diagnostic.replacements,
+	})
+}
+
+/// One JSON object per line, flushed so the stream can be followed live.
+fn event(value: serde_json::Value) -> anyhow::Result<()> {
+	let mut out = stdout().lock();
+	serde_json::to_writer(&mut out, &value)?;
+	writeln!(out)?;
+	out.flush()?;
+	Ok(())
+}
+
+fn plain(file: &Path, source: &Source, diagnostic: Diagnostic) {
 	let mut out = stdout().lock();
 
 	let (start_line, start_column) = byte_to_position(source, diagnostic.locations[0].1.start);
 	let (end_line, end_column) = byte_to_position(source, diagnostic.locations[0].1.end);
 	write!(
 		out,
-		"{} {}:{}-{}:{} info {}",
+		"{} {}:{}-{}:{} {} {}",
 		file.display(),
 		start_line + 1,
 		start_column + 1,
 		end_line + 1,
 		end_column + 1,
+		diagnostic.severity,
 		diagnostic.message,
 	)
 	.unwrap();
 
 	let mut suggestions = diagnostic
 		.replacements
-		.into_iter()
+		.iter()
 		.filter(|suggestion| suggestion.trim().is_empty().not())
 		.take(MAX_SUGGESTIONS);
 	if let Some(first) = suggestions.next() {
@@ -39,7 +281,21 @@ pub fn plain(file: &Path, source: &Source, diagnostic: Diagnostic) {
 	}
 }
 
-pub fn pretty(file: &Path, source: &Source, diagnostic: Diagnostic) {
+/// `file:line:col: severity: message [RULE_ID]` like gcc and clippy.
+fn short(file: &Path, source: &Source, diagnostic: Diagnostic) {
+	let (line, column) = byte_to_position(source, diagnostic.locations[0].1.start);
+	println!(
+		"{}:{}:{}: {}: {} [{}]",
+		file.display(),
+		line + 1,
+		column + 1,
+		diagnostic.severity,
+		diagnostic.message,
+		diagnostic.rule_id,
+	);
+}
+
+fn pretty(file: &Path, source: &Source, diagnostic: Diagnostic) {
 	let file_name = format!("{}", file.display());
 
 	let (start_line, _) = byte_to_position(source, diagnostic.locations[0].1.start);
@@ -61,26 +317,314 @@ pub fn pretty(file: &Path, source: &Source, diagnostic: Diagnostic) {
 	let start = diagnostic.locations[0].1.start - context.start;
 	let end = diagnostic.locations[0].1.end - context.start;
 
-	snippet = snippet.annotation(Level::Info.span(start..end).label(&diagnostic.message));
+	let level = match diagnostic.severity {
+		Severity::Info => Level::Info,
+		Severity::Warning => Level::Warning,
+		Severity::Error => Level::Error,
+	};
+	snippet = snippet.annotation(level.span(start..end).label(&diagnostic.message));
+	let message = level
+		.title(&diagnostic.rule_description)
+		.id(&diagnostic.rule_id)
+		.snippet(snippet);
 
-	for replacement in diagnostic
+	let renderer = if colored::control::SHOULD_COLORIZE.should_colorize() {
+		Renderer::styled()
+	} else {
+		Renderer::plain()
+	};
+	println!("{}", renderer.render(message));
+
+	// the top replacement as a patch of the lines, diagnostics spanning multiple source ranges only list the replacements
+	let width = (end_line + 1).to_string().len();
+	let patched = match (
+		diagnostic.replacements.first(),
+		diagnostic.locations.as_slice(),
+	) {
+		(Some(first), [(_, range)]) => {
+			let patch = format!(
+				"{}{}{}",
+				&text[context.start..range.start],
+				first,
+				&text[range.end..context.end]
+			);
+			for line in text[context.clone()].lines() {
+				println!("{:width$} {} {}", "", "-".red(), line.red());
+			}
+			for line in patch.lines() {
+				println!("{:width$} {} {}", "", "+".green(), line.green());
+			}
+			true
+		},
+		_ => false,
+	};
+	let others = diagnostic
 		.replacements
 		.iter()
+		.skip(usize::from(patched))
 		.filter(|replacement| replacement.trim().is_empty().not())
-		.take(MAX_SUGGESTIONS)
-	{
-		snippet = snippet.annotation(Level::Help.span(end..end).label(&replacement));
+		.collect::<Vec<_>>();
+	if others.is_empty().not() {
+		let mut listed = others
+			.iter()
+			.take(MAX_SUGGESTIONS)
+			.map(|replacement| replacement.as_str())
+			.collect::<Vec<_>>()
+			.join(", ");
+		if others.len() > MAX_SUGGESTIONS {
+			listed += &format!(" and {} more", others.len() - MAX_SUGGESTIONS);
+		}
+		let label = if patched {
+			"other replacements"
+		} else {
+			"replacements"
+		};
+		println!(
+			"{:width$} {} {}: {}",
+			"",
+			"=".blue().bold(),
+			label.bold(),
+			listed
+		);
 	}
-	let message = Level::Info
-		.title(&diagnostic.rule_description)
-		.id(&diagnostic.rule_id)
-		.snippet(snippet);
+}
 
-	let renderer = Renderer::styled();
-	println!("{}", renderer.render(message));
+/// SARIF 2.1.0 log with one run, columns are counted in unicode code points.
+fn sarif(diagnostics: Vec<(PathBuf, Source, Diagnostic)>) -> anyhow::Result<()> {
+	let mut rules = Vec::new();
+	let mut rule_indices = HashMap::<Arc<str>, usize>::new();
+	let mut results = Vec::with_capacity(diagnostics.len());
+	for (path, source, diagnostic) in diagnostics {
+		let rule_index = *rule_indices
+			.entry(diagnostic.rule_id.clone())
+			.or_insert_with(|| {
+				rules.push(serde_json::json!({
+					"id": diagnostic.rule_id,
+					"shortDescription": { "text": diagnostic.rule_description },
+				}));
+				rules.len() - 1
+			});
+		let (start_line, start_column) = byte_to_position(&source, diagnostic.locations[0].1.start);
+		let (end_line, end_column) = byte_to_position(&source, diagnostic.locations[0].1.end);
+		results.push(serde_json::json!({
+			"ruleId": diagnostic.rule_id,
+			"ruleIndex": rule_index,
+			"level": match diagnostic.severity {
+				Severity::Info => "note",
+				Severity::Warning => "warning",
+				Severity::Error => "error",
+			},
+			"message": { "text": diagnostic.message },
+			"locations": [{
+				"physicalLocation": {
+					"artifactLocation": { "uri": uri(&path) },
+					"region": {
+						"startLine": start_line + 1,
+						"startColumn": start_column + 1,
+						"endLine": end_line + 1,
+						"endColumn": end_column + 1,
+					},
+				},
+			}],
+		}));
+	}
+	let log = serde_json::json!({
+		"$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+		"version": "2.1.0",
+		"runs": [{
+			"tool": {
+				"driver": {
+					"name": "typst-languagetool",
+					"informationUri": "https://github.com/antonWetzel/typst-languagetool",
+					"rules": rules,
+				},
+			},
+			"columnKind": "unicodeCodePoints",
+			"results": results,
+		}],
+	});
+	let mut out = stdout().lock();
+	serde_json::to_writer_pretty(&mut out, &log)?;
+	writeln!(out)?;
+	Ok(())
+}
+
+/// Code Climate issues as used by GitLab Code Quality.
+fn codeclimate(diagnostics: Vec<(PathBuf, Source, Diagnostic)>) -> anyhow::Result<()> {
+	let mut occurrences = HashMap::<(String, Arc<str>, String), usize>::new();
+	let mut issues = Vec::with_capacity(diagnostics.len());
+	for (path, source, diagnostic) in diagnostics {
+		let path = uri(&path);
+		let range = diagnostic.locations[0].1.clone();
+		let (start_line, start_column) = byte_to_position(&source, range.start);
+		let (end_line, end_column) = byte_to_position(&source, range.end);
+
+		// independent of the position, so unrelated edits keep the fingerprint
+		let text = source.get(range).unwrap_or_default().to_owned();
+		let occurrence = occurrences
+			.entry((path.clone(), diagnostic.rule_id.clone(), text.clone()))
+			.or_default();
+		let fingerprint =
+			fingerprint(&[&path, &diagnostic.rule_id, &text, &occurrence.to_string()]);
+		*occurrence += 1;
+
+		issues.push(serde_json::json!({
+			"type": "issue",
+			"check_name": diagnostic.rule_id,
+			"description": diagnostic.message,
+			"categories": ["Style"],
+			"fingerprint": fingerprint,
+			"severity": match diagnostic.severity {
+				Severity::Info => "info",
+				Severity::Warning => "minor",
+				Severity::Error => "major",
+			},
+			"location": {
+				"path": path,
+				"positions": {
+					"begin": { "line": start_line + 1, "column": start_column + 1 },
+					"end": { "line": end_line + 1, "column": end_column + 1 },
+				},
+			},
+		}));
+	}
+	let mut out = stdout().lock();
+	serde_json::to_writer_pretty(&mut out, &issues)?;
+	writeln!(out)?;
+	Ok(())
+}
+
+/// JUnit XML with a test suite per file and a failed test case per diagnostic.
+fn junit(diagnostics: Vec<(PathBuf, Source, Diagnostic)>) -> anyhow::Result<()> {
+	let mut files = Vec::<(String, Vec<String>)>::new();
+	for (path, source, diagnostic) in diagnostics {
+		let path = uri(&path);
+		let (line, column) = byte_to_position(&source, diagnostic.locations[0].1.start);
+		let replacements = diagnostic
+			.replacements
+			.iter()
+			.filter(|replacement| replacement.trim().is_empty().not())
+			.take(MAX_SUGGESTIONS)
+			.map(String::as_str)
+			.collect::<Vec<_>>()
+			.join(", ");
+		let case = format!(
+			"\t\t<testcase name=\"{}:{}:{} {}\" classname=\"{}\">\n\t\t\t<failure message=\"{}\" type=\"{}\">{}\nSuggestions: {}</failure>\n\t\t</testcase>",
+			xml_escape(&path),
+			line + 1,
+			column + 1,
+			xml_escape(&diagnostic.rule_id),
+			xml_escape(&path),
+			xml_escape(&diagnostic.message),
+			xml_escape(&diagnostic.rule_id),
+			xml_escape(&diagnostic.rule_description),
+			xml_escape(&replacements),
+		);
+		match files.iter_mut().find(|(file, _)| *file == path) {
+			Some((_, cases)) => cases.push(case),
+			None => files.push((path, vec![case])),
+		}
+	}
+
+	let total = files.iter().map(|(_, cases)| cases.len()).sum::<usize>();
+	let mut out = stdout().lock();
+	writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+	writeln!(
+		out,
+		r#"<testsuites name="typst-languagetool" tests="{}" failures="{}">"#,
+		total, total
+	)?;
+	for (path, cases) in files {
+		writeln!(
+			out,
+			"\t<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+			xml_escape(&path),
+			cases.len(),
+			cases.len()
+		)?;
+		for case in cases {
+			writeln!(out, "{}", case)?;
+		}
+		writeln!(out, "\t</testsuite>")?;
+	}
+	writeln!(out, "</testsuites>")?;
+	Ok(())
+}
+
+/// CSV with a header row, columns are counted in unicode code points.
+fn csv(diagnostics: Vec<(PathBuf, Source, Diagnostic)>) -> anyhow::Result<()> {
+	let mut out = stdout().lock();
+	writeln!(
+		out,
+		"file,start line,start column,end line,end column,rule id,category,message,suggestion"
+	)?;
+	for (path, source, diagnostic) in diagnostics {
+		let (start_line, start_column) = byte_to_position(&source, diagnostic.locations[0].1.start);
+		let (end_line, end_column) = byte_to_position(&source, diagnostic.locations[0].1.end);
+		let suggestion = diagnostic
+			.replacements
+			.iter()
+			.find(|replacement| replacement.trim().is_empty().not())
+			.map_or("", String::as_str);
+		writeln!(
+			out,
+			"{},{},{},{},{},{},{},{},{}",
+			csv_escape(&uri(&path)),
+			start_line + 1,
+			start_column + 1,
+			end_line + 1,
+			end_column + 1,
+			csv_escape(&diagnostic.rule_id),
+			csv_escape(&diagnostic.category),
+			csv_escape(&diagnostic.message),
+			csv_escape(suggestion),
+		)?;
+	}
+	Ok(())
+}
+
+/// Quote fields with separators, quotes or line breaks (RFC 4180).
+fn csv_escape(text: &str) -> String {
+	if text.contains([',', '"', '\n', '\r']) {
+		format!("\"{}\"", text.replace('"', "\"\""))
+	} else {
+		text.to_owned()
+	}
+}
+
+fn xml_escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&apos;"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+/// FNV-1a hash, stable across runs and versions.
+pub fn fingerprint(parts: &[&str]) -> String {
+	let mut hash = 0xcbf29ce484222325_u64;
+	for part in parts {
+		for byte in part.bytes().chain([0]) {
+			hash ^= byte as u64;
+			hash = hash.wrapping_mul(0x100000001b3);
+		}
+	}
+	format!("{:016x}", hash)
+}
+
+/// URI with forward slashes.
+pub fn uri(path: &Path) -> String {
+	path.to_string_lossy().replace('\\', "/")
 }
 
-fn byte_to_position(source: &Source, index: usize) -> (usize, usize) {
+pub fn byte_to_position(source: &Source, index: usize) -> (usize, usize) {
 	let line = source.byte_to_line(index).unwrap();
 	let start = source.line_to_byte(line).unwrap();
 	let head = source.get(start..index).unwrap();
@@ -0,0 +1,234 @@
+use std::{
+	convert::Infallible,
+	ops::Not,
+	path::{Path, PathBuf},
+};
+
+use hyper::{
+	service::{make_service_fn, service_fn},
+	Body, Method, Request, Response, Server, StatusCode,
+};
+use indicatif::ProgressBar;
+use lt_world::LtWorld;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+use typst::World;
+use typst_languagetool::{Cache, Cancellation, FileCollector, LanguageTool};
+
+use crate::{output, Args};
+
+/// File for a request with only the source, relative to the root.
+const SOURCE_PATH: &str = "input.typ";
+
+/// Body for `POST /check`.
+#[derive(serde::Deserialize, Debug)]
+struct CheckRequest {
+	/// File to check, relative to the root
+	path: Option<PathBuf>,
+	/// Content used instead of the file on disk
+	source: Option<String>,
+}
+
+type Job = (CheckRequest, oneshot::Sender<(StatusCode, Value)>);
+
+/// Serve `GET /health` and `POST /check` until cancelled.
+/// The backend and the world are kept between requests, the requests are checked one after another.
+pub async fn run(
+	args: Args,
+	mut lt: LanguageTool,
+	mut world: LtWorld,
+	cancellation: &Cancellation,
+) -> anyhow::Result<()> {
+	let (sender, mut receiver) = mpsc::channel::<Job>(16);
+	let make_service = make_service_fn(move |_| {
+		let sender = sender.clone();
+		async move { Ok::<_, Infallible>(service_fn(move |request| handle(request, sender.clone()))) }
+	});
+	let server = Server::try_bind(&args.listen)?.serve(make_service);
+	tracing::info!("Listening on http://{}", args.listen);
+	tokio::spawn(async move {
+		if let Err(err) = server.await {
+			tracing::error!("Server failed: {}", err);
+		}
+	});
+
+	let mut cache = Cache::new();
+	loop {
+		let (request, reply) = tokio::select! {
+			job = receiver.recv() => match job {
+				Some(job) => job,
+				None => break,
+			},
+			_ = cancellation.cancelled() => break,
+		};
+		let response = check(
+			request,
+			&args,
+			&mut lt,
+			&mut world,
+			&mut cache,
+			cancellation,
+		)
+		.await
+		.unwrap_or_else(|err| error(StatusCode::INTERNAL_SERVER_ERROR, err));
+		_ = reply.send(response);
+	}
+	Ok(())
+}
+
+async fn handle(
+	request: Request<Body>,
+	sender: mpsc::Sender<Job>,
+) -> Result<Response<Body>, Infallible> {
+	let (status, body) = match (request.method(), request.uri().path()) {
+		(&Method::GET, "/health") => (StatusCode::OK, json!({ "status": "ok" })),
+		(&Method::POST, "/check") => check_request(request, sender).await,
+		_ => error(StatusCode::NOT_FOUND, "Use 'GET /health' or 'POST /check'."),
+	};
+	let response = Response::builder()
+		.status(status)
+		.header("content-type", "application/json")
+		.body(Body::from(body.to_string()))
+		.unwrap();
+	Ok(response)
+}
+
+async fn check_request(request: Request<Body>, sender: mpsc::Sender<Job>) -> (StatusCode, Value) {
+	let body = match hyper::body::to_bytes(request.into_body()).await {
+		Ok(body) => body,
+		Err(err) => return error(StatusCode::BAD_REQUEST, err),
+	};
+	let request = match serde_json::from_slice::<CheckRequest>(&body) {
+		Ok(request) => request,
+		Err(err) => return error(StatusCode::BAD_REQUEST, err),
+	};
+	let (reply, response) = oneshot::channel();
+	if sender.send((request, reply)).await.is_err() {
+		return error(StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down.");
+	}
+	response
+		.await
+		.unwrap_or_else(|_| error(StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down."))
+}
+
+async fn check(
+	request: CheckRequest,
+	args: &Args,
+	lt: &mut LanguageTool,
+	world: &mut LtWorld,
+	cache: &mut Cache,
+	cancellation: &Cancellation,
+) -> anyhow::Result<(StatusCode, Value)> {
+	let (path, main) = match (&request.path, &request.source) {
+		(Some(path), _) => {
+			let path = world.root().join(path);
+			let main = crate::main_for(args, world, &path);
+			(path, main)
+		},
+		(None, Some(_)) => {
+			let path = world.root().join(SOURCE_PATH);
+			(path.clone(), path)
+		},
+		(None, None) => {
+			return Ok(error(
+				StatusCode::BAD_REQUEST,
+				"Specify 'path' or 'source'.",
+			))
+		},
+	};
+	if world.file_id(&path).is_none() || world.file_id(&main).is_none() {
+		return Ok(error(
+			StatusCode::BAD_REQUEST,
+			format!("{} is not inside the root.", path.display()),
+		));
+	}
+	let Some(source) = request.source else {
+		if path.is_file().not() {
+			return Ok(error(
+				StatusCode::NOT_FOUND,
+				format!("{} does not exist.", path.display()),
+			));
+		}
+		return diagnostics(&path, &main, args, lt, world, cache, cancellation).await;
+	};
+	world.use_shadow_file(&path, source);
+	let result = diagnostics(&path, &main, args, lt, world, cache, cancellation).await;
+	world.use_original_file(&path);
+	result
+}
+
+async fn diagnostics(
+	path: &Path,
+	main: &Path,
+	args: &Args,
+	lt: &mut LanguageTool,
+	world: &LtWorld,
+	cache: &mut Cache,
+	cancellation: &Cancellation,
+) -> anyhow::Result<(StatusCode, Value)> {
+	let prepared = crate::prepare(main, &[path], args, world, false)?;
+	let (file_id, paragraphs) = match prepared.files {
+		Ok(mut files) => files.remove(0),
+		Err(errors) => {
+			let errors = errors
+				.iter()
+				.map(|error| error.message.as_str())
+				.collect::<Vec<_>>();
+			return Ok((
+				StatusCode::UNPROCESSABLE_ENTITY,
+				json!({ "errors": errors }),
+			));
+		},
+	};
+
+	let cached = paragraphs
+		.iter()
+		.map(|(text, mapping)| cache.get(text, &crate::language(args, mapping)))
+		.collect::<Vec<_>>();
+	let missing = paragraphs
+		.iter()
+		.zip(&cached)
+		.filter(|(_, suggestions)| suggestions.is_none())
+		.map(|((text, mapping), _)| (crate::language(args, mapping), text.clone()))
+		.collect::<Vec<_>>();
+	let mut checked = crate::check_texts(lt, &missing, args, &ProgressBar::hidden(), cancellation)
+		.await?
+		.into_iter();
+
+	let mut collector = FileCollector::new(file_id, &prepared.world);
+	for ((text, mapping), cached) in paragraphs.into_iter().zip(cached) {
+		let suggestions = match cached {
+			Some(suggestions) => suggestions,
+			None => checked.next().unwrap_or_default(),
+		};
+		let lang = crate::language(args, &mapping);
+		let mut kept = suggestions.clone();
+		args.directories.retain(&text, &lang, &mapping, &mut kept);
+		args.lt.retain_file_checks(&mapping, &mut kept);
+		prepared.metadata.retain(&text, &lang, &mut kept);
+		collector.add(&prepared.world, &kept, &mapping);
+		cache.insert(&text, &lang, suggestions);
+	}
+
+	let relative = path.strip_prefix(world.root()).unwrap_or(path);
+	let diagnostics = collector
+		.finish()
+		.into_iter()
+		.map(|mut diagnostic| {
+			diagnostic
+				.filter_replacements(args.lt.max_suggestions, args.lt.drop_similar_suggestions);
+			diagnostic.apply_rule_options(
+				&args.lt.rule_severity,
+				args.lt.comment_severity,
+				&args.lt.unnecessary_rules,
+			);
+			let source = prepared.world.source(diagnostic.locations[0].0).unwrap();
+			output::json(relative, &source, diagnostic)
+		})
+		.collect::<Vec<_>>();
+	Ok((StatusCode::OK, json!({ "diagnostics": diagnostics })))
+}
+
+fn error(status: StatusCode, message: impl ToString) -> (StatusCode, Value) {
+	(status, json!({ "error": message.to_string() }))
+}
@@ -0,0 +1,258 @@
+use std::{collections::BTreeMap, ops::Not, path::PathBuf, time::Duration};
+
+use ratatui::{
+	crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+	layout::{Constraint, Layout},
+	style::{Color, Modifier, Style, Stylize},
+	text::{Line, Span},
+	widgets::{List, ListState, Paragraph},
+	DefaultTerminal,
+};
+use typst::syntax::Source;
+use typst_languagetool::{Diagnostic, Severity};
+
+use crate::output::byte_to_position;
+
+/// Diagnostics of a checked file, or the compile errors of its document.
+pub type Report = Result<Vec<(PathBuf, Source, Diagnostic)>, Vec<String>>;
+
+/// Input of the user for the watch loop.
+pub enum Action {
+	None,
+	/// Check the files again without the cache
+	Recheck,
+	Quit,
+	/// Quit and print the location (`file:line:col`)
+	Jump(String),
+}
+
+/// Diagnostic with the position for the list.
+struct Entry {
+	line: usize,
+	column: usize,
+	severity: Severity,
+	message: String,
+	rule_id: String,
+}
+
+/// Row of the list, a file or one of its diagnostics.
+struct Row {
+	line: Line<'static>,
+	location: String,
+	rule_id: Option<String>,
+}
+
+/// Live list of the diagnostics grouped by file for `watch --tui`, the terminal is restored on drop.
+pub struct Tui {
+	terminal: DefaultTerminal,
+	/// Diagnostics or compile errors of the files with issues, sorted by path
+	files: BTreeMap<PathBuf, Result<Vec<Entry>, Vec<String>>>,
+	/// Only show the diagnostics of the rule
+	rule: Option<String>,
+	state: ListState,
+	status: String,
+}
+
+impl Tui {
+	pub fn new() -> anyhow::Result<Self> {
+		Ok(Self {
+			terminal: ratatui::try_init()?,
+			files: BTreeMap::new(),
+			rule: None,
+			state: ListState::default().with_selected(Some(0)),
+			status: String::from("Waiting for changes"),
+		})
+	}
+
+	/// Show the files in the status while they are checked.
+	pub fn checking(&mut self, paths: &[PathBuf]) -> anyhow::Result<()> {
+		self.status = match paths {
+			[path] => format!("Checking {}", path.display()),
+			_ => format!("Checking {} files", paths.len()),
+		};
+		self.draw()
+	}
+
+	/// Replace the diagnostics of the checked files.
+	pub fn update(&mut self, reports: Vec<(PathBuf, Report)>) {
+		let checked = reports.len();
+		for (path, report) in reports {
+			let entries = report.map(|diagnostics| {
+				diagnostics
+					.into_iter()
+					.map(|(_, source, diagnostic)| {
+						let (line, column) =
+							byte_to_position(&source, diagnostic.locations[0].1.start);
+						Entry {
+							line: line + 1,
+							column: column + 1,
+							severity: diagnostic.severity,
+							message: diagnostic.message.to_string(),
+							rule_id: diagnostic.rule_id.to_string(),
+						}
+					})
+					.collect::<Vec<_>>()
+			});
+			match entries {
+				Ok(entries) if entries.is_empty() => {
+					self.files.remove(&path);
+				},
+				entries => {
+					self.files.insert(path, entries);
+				},
+			}
+		}
+		let diagnostics = self
+			.files
+			.values()
+			.map(|entries| entries.as_ref().map_or(0, Vec::len))
+			.sum::<usize>();
+		self.status = format!(
+			"Checked {} files, {} diagnostics in {} files",
+			checked,
+			diagnostics,
+			self.files.len()
+		);
+	}
+
+	pub fn draw(&mut self) -> anyhow::Result<()> {
+		let rows = self.rows();
+		match self.state.selected() {
+			Some(selected) if selected >= rows.len() => {
+				self.state.select(rows.len().checked_sub(1))
+			},
+			None if rows.is_empty().not() => self.state.select(Some(0)),
+			_ => {},
+		}
+		let list = List::new(rows.into_iter().map(|row| row.line))
+			.highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+		let mut footer = vec![Span::raw(self.status.clone())];
+		if let Some(rule) = &self.rule {
+			footer.push(Span::raw(" | rule ").dim());
+			footer.push(Span::raw(rule.clone()).yellow());
+		}
+		footer.push(Span::raw(" | ↑↓ select, enter jump, f filter rule, r re-check, q quit").dim());
+		let state = &mut self.state;
+		self.terminal.draw(|frame| {
+			let [main, status] =
+				Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+			frame.render_stateful_widget(list, main, state);
+			frame.render_widget(Paragraph::new(Line::from(footer)), status);
+		})?;
+		Ok(())
+	}
+
+	/// Wait up to the timeout for the input of the user.
+	pub fn input(&mut self, timeout: Duration) -> anyhow::Result<Action> {
+		if event::poll(timeout)?.not() {
+			return Ok(Action::None);
+		}
+		let Event::Key(key) = event::read()? else {
+			return Ok(Action::None);
+		};
+		if key.kind != KeyEventKind::Press {
+			return Ok(Action::None);
+		}
+		match key.code {
+			KeyCode::Char('q') | KeyCode::Esc => return Ok(Action::Quit),
+			// the raw mode of the terminal catches Ctrl-C
+			KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+				return Ok(Action::Quit)
+			},
+			KeyCode::Char('r') => return Ok(Action::Recheck),
+			KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+			KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+			KeyCode::Home | KeyCode::Char('g') => self.state.select_first(),
+			KeyCode::End | KeyCode::Char('G') => self.state.select_last(),
+			KeyCode::Enter => {
+				let rows = self.rows();
+				if let Some(row) = self.state.selected().and_then(|index| rows.get(index)) {
+					return Ok(Action::Jump(row.location.clone()));
+				}
+			},
+			KeyCode::Char('f') => {
+				let rows = self.rows();
+				let selected = self.state.selected().and_then(|index| rows.get(index));
+				self.rule = match (&self.rule, selected) {
+					(Some(_), _) => None,
+					(None, Some(row)) => row.rule_id.clone(),
+					(None, None) => None,
+				};
+				self.state.select_first();
+			},
+			_ => {},
+		}
+		Ok(Action::None)
+	}
+
+	/// Rows for the files with their diagnostics, only the files with diagnostics of the filtered rule are shown.
+	fn rows(&self) -> Vec<Row> {
+		let mut rows = Vec::new();
+		for (path, entries) in &self.files {
+			let file = path.display().to_string();
+			match entries {
+				Ok(entries) => {
+					let entries = entries
+						.iter()
+						.filter(|entry| {
+							self.rule.as_ref().is_none_or(|rule| *rule == entry.rule_id)
+						})
+						.collect::<Vec<_>>();
+					if entries.is_empty() {
+						continue;
+					}
+					rows.push(Row {
+						line: Line::from(vec![
+							Span::raw(file.clone()).bold(),
+							Span::raw(format!(" ({})", entries.len())).dim(),
+						]),
+						location: file.clone(),
+						rule_id: None,
+					});
+					for entry in entries {
+						let color = match entry.severity {
+							Severity::Info => Color::Blue,
+							Severity::Warning => Color::Yellow,
+							Severity::Error => Color::Red,
+						};
+						rows.push(Row {
+							line: Line::from(vec![
+								Span::raw(format!("  {}:{} ", entry.line, entry.column)).dim(),
+								Span::styled(entry.severity.to_string(), color),
+								Span::raw(format!(" {} ", entry.message)),
+								Span::raw(format!("[{}]", entry.rule_id)).dim(),
+							]),
+							location: format!("{}:{}:{}", file, entry.line, entry.column),
+							rule_id: Some(entry.rule_id.clone()),
+						});
+					}
+				},
+				Err(errors) if self.rule.is_none() => {
+					rows.push(Row {
+						line: Line::from(Span::raw(file.clone()).bold()),
+						location: file.clone(),
+						rule_id: None,
+					});
+					for error in errors {
+						rows.push(Row {
+							line: Line::from(vec![
+								Span::raw("  error: ").red(),
+								Span::raw(error.clone()),
+							]),
+							location: file.clone(),
+							rule_id: None,
+						});
+					}
+				},
+				Err(_) => {},
+			}
+		}
+		rows
+	}
+}
+
+impl Drop for Tui {
+	fn drop(&mut self) {
+		ratatui::restore();
+	}
+}
@@ -0,0 +1,10 @@
+// The body of a short page is inlined into the page, the large figure is not the body
+// and the heading and the paragraphs are checked (`typst-languagetool check --main example/short-page.typ`).
+
+= Introducion
+
+A paragraph with a speling mistake.
+
+#figure(rect(width: 100%, height: 18cm))
+
+Another paragraph with a mistak.
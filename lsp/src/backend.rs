@@ -0,0 +1,110 @@
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use lsp_server::{Message, Notification};
+use lsp_types::{notification::ShowMessage, MessageType, ShowMessageParams};
+use tokio::{sync::oneshot, task::JoinHandle};
+use typst_languagetool::{Cancellation, LanguageTool, LanguageToolBackend, LanguageToolOptions};
+
+/// Checked once for each language, so the backend loads the languages before the first check.
+const PREWARM_TEXT: &str = "Typst";
+
+/// Backend started in the background or on the first check, the JVM or server start does not block the initialization.
+pub enum Backend {
+	/// Started on the first check, the warnings for the options are shown with the client
+	Lazy(Box<LanguageToolOptions>, Sender<Message>),
+	Starting(JoinHandle<anyhow::Result<LanguageTool>>),
+	Ready(Box<LanguageTool>),
+	Failed(anyhow::Error),
+}
+
+impl Backend {
+	/// Start the backend and load the languages received with `languages`.
+	pub fn start(
+		options: LanguageToolOptions,
+		client: Sender<Message>,
+		languages: oneshot::Receiver<Vec<String>>,
+	) -> Self {
+		Self::Starting(tokio::spawn(async move {
+			let start = Instant::now();
+			let mut lt = new(&options, &client).await?;
+			tracing::info!("Backend started in {:.2?}", start.elapsed());
+
+			let start = Instant::now();
+			let languages = languages.await.unwrap_or_default();
+			for lang in &languages {
+				let result = lt
+					.check_text(lang.clone(), PREWARM_TEXT, &Cancellation::new())
+					.await;
+				if let Err(err) = result {
+					tracing::warn!("Failed to load language {}: {}", lang, err);
+				}
+			}
+			tracing::debug!(
+				"Loaded {} languages in {:.2?}",
+				languages.len(),
+				start.elapsed()
+			);
+			Ok(lt)
+		}))
+	}
+
+	/// Wait until the backend is started.
+	pub async fn get(&mut self) -> anyhow::Result<&mut LanguageTool> {
+		if let Self::Lazy(options, client) = self {
+			let start = Instant::now();
+			*self = match new(options, client).await {
+				Ok(lt) => {
+					tracing::info!("Backend started in {:.2?}", start.elapsed());
+					Self::Ready(Box::new(lt))
+				},
+				Err(err) => Self::Failed(err),
+			};
+		}
+		if let Self::Starting(handle) = self {
+			*self = match handle.await {
+				Ok(Ok(lt)) => Self::Ready(Box::new(lt)),
+				Ok(Err(err)) => Self::Failed(err),
+				Err(err) => Self::Failed(err.into()),
+			};
+		}
+		match self {
+			Self::Ready(lt) => Ok(lt),
+			Self::Failed(err) => Err(anyhow::anyhow!("Backend failed to start: {}", err)),
+			Self::Lazy(..) | Self::Starting(_) => unreachable!(),
+		}
+	}
+}
+
+/// Start the backend and show the warnings for the options.
+async fn new(
+	options: &LanguageToolOptions,
+	client: &Sender<Message>,
+) -> anyhow::Result<LanguageTool> {
+	let (mut lt, warnings) = LanguageTool::new_validated(options).await?;
+	for warning in warnings {
+		tracing::warn!("{}", warning);
+		show_warning(client, warning.to_string())?;
+	}
+	fallback_switch(&mut lt, client)?;
+	Ok(lt)
+}
+
+/// Show the switch to the fallback backend with the client.
+pub fn fallback_switch(lt: &mut LanguageTool, client: &Sender<Message>) -> anyhow::Result<()> {
+	if let Some(message) = lt.take_fallback_switch() {
+		tracing::warn!("{}", message);
+		show_warning(client, message)?;
+	}
+	Ok(())
+}
+
+fn show_warning(client: &Sender<Message>, message: String) -> anyhow::Result<()> {
+	let params = ShowMessageParams { typ: MessageType::WARNING, message };
+	let notification = Notification::new(
+		<ShowMessage as lsp_types::notification::Notification>::METHOD.into(),
+		params,
+	);
+	client.send(Message::Notification(notification))?;
+	Ok(())
+}
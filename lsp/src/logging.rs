@@ -0,0 +1,127 @@
+use std::{
+	fmt::Write as _,
+	fs::File,
+	io::Write,
+	path::Path,
+	sync::{Arc, Mutex},
+};
+
+use crossbeam_channel::Sender;
+use lsp_server::{Message, Notification};
+use lsp_types::{notification::LogMessage, LogMessageParams, MessageType};
+use tracing::{field::Field, Event, Level, Subscriber};
+use tracing_subscriber::{
+	fmt::format::FmtSpan, layer::Context, layer::SubscriberExt, reload, util::SubscriberInitExt,
+	EnvFilter, Layer, Registry,
+};
+
+/// Environment variable for the log filter, takes precedence over the `log_level` option.
+const LOG_ENV: &str = "TYPST_LANGUAGETOOL_LOG";
+
+/// Default level until the options are known.
+const DEFAULT_LEVEL: &str = "info";
+
+/// Log output, the level and the file can be changed with the options.
+pub struct Logging {
+	filter: reload::Handle<EnvFilter, Registry>,
+	file: Arc<Mutex<Option<File>>>,
+	client: Arc<Mutex<Option<Sender<Message>>>>,
+}
+
+impl Logging {
+	/// Log to stderr, span durations are logged with `debug`.
+	/// Events with `info` or above are also forwarded to the client after `forward`.
+	pub fn init() -> Self {
+		let (filter, handle) = reload::Layer::new(
+			EnvFilter::try_from_env(LOG_ENV).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LEVEL)),
+		);
+		let file = Arc::new(Mutex::new(None));
+		let writer = {
+			let file = file.clone();
+			move || LogWriter(file.clone())
+		};
+		let client = Arc::new(Mutex::new(None));
+		tracing_subscriber::registry()
+			.with(filter)
+			.with(
+				tracing_subscriber::fmt::layer()
+					.with_ansi(false)
+					.with_target(false)
+					.with_span_events(FmtSpan::CLOSE)
+					.with_writer(writer),
+			)
+			.with(ClientLayer(client.clone()))
+			.init();
+		Self { filter: handle, file, client }
+	}
+
+	/// Send the log as `window/logMessage` to the client.
+	pub fn forward(&self, sender: Sender<Message>) {
+		*self.client.lock().unwrap() = Some(sender);
+	}
+
+	/// Use the level and log to the file, or stderr if no file is specified.
+	pub fn apply(&self, level: Option<&str>, file: Option<&Path>) -> anyhow::Result<()> {
+		if std::env::var_os(LOG_ENV).is_none() {
+			let filter = EnvFilter::try_new(level.unwrap_or(DEFAULT_LEVEL))?;
+			self.filter.reload(filter)?;
+		}
+		let file = match file {
+			Some(path) => Some(File::options().create(true).append(true).open(path)?),
+			None => None,
+		};
+		*self.file.lock().unwrap() = file;
+		Ok(())
+	}
+}
+
+struct LogWriter(Arc<Mutex<Option<File>>>);
+
+impl Write for LogWriter {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		match self.0.lock().unwrap().as_mut() {
+			Some(file) => file.write(buf),
+			None => std::io::stderr().write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		match self.0.lock().unwrap().as_mut() {
+			Some(file) => file.flush(),
+			None => std::io::stderr().flush(),
+		}
+	}
+}
+
+/// Forward events to the client with the matching message type.
+struct ClientLayer(Arc<Mutex<Option<Sender<Message>>>>);
+
+impl<S: Subscriber> Layer<S> for ClientLayer {
+	fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+		let typ = match *event.metadata().level() {
+			Level::ERROR => MessageType::ERROR,
+			Level::WARN => MessageType::WARNING,
+			Level::INFO => MessageType::INFO,
+			_ => return,
+		};
+		let client = self.0.lock().unwrap();
+		let Some(sender) = client.as_ref() else {
+			return;
+		};
+		let mut message = String::new();
+		event.record(&mut |field: &Field, value: &dyn std::fmt::Debug| {
+			if field.name() == "message" {
+				_ = write!(message, "{:?}", value);
+			} else {
+				_ = write!(message, " {}={:?}", field.name(), value);
+			}
+		});
+		let params = LogMessageParams { typ, message };
+		let notification = Notification::new(
+			<LogMessage as lsp_types::notification::Notification>::METHOD.into(),
+			params,
+		);
+		// the client is gone after the shutdown
+		_ = sender.send(Message::Notification(notification));
+	}
+}
@@ -1,18 +1,37 @@
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{HashMap, VecDeque};
+use std::ops::Not;
 use std::path::{Path, PathBuf};
 
+mod backend;
+mod logging;
+
 use anyhow::Context;
-use crossbeam_channel::RecvTimeoutError;
+use crossbeam_channel::{RecvTimeoutError, TryRecvError};
 use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
 use lsp_types::notification::*;
 use lsp_types::request::*;
 use lsp_types::*;
-use lt_world::LtWorld;
+use lt_world::{FontOptions, LtWorld, LtWorldRunning, PackageOptions};
 use serde_json::Value;
-use typst::syntax::Source;
+use tokio::sync::oneshot;
+use typst::model::Document;
+use typst::syntax::{FileId, Source};
 use typst::World;
-use typst_languagetool::{LanguageTool, LanguageToolBackend, LanguageToolOptions, Suggestion};
+use typst_languagetool::{
+	convert::{Mapping, Selection},
+	Cache, Cancellation, Cancelled, ChunkSize, DetachedText, DirectoryOptions, DocumentOptions,
+	Headings, Incremental, LanguageToolBackend, LanguageToolOptions, Severity, Suggestion,
+};
+
+use crate::{backend::Backend, logging::Logging};
+
+const STALE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Minimum duration between partial diagnostics while a file is checked.
+const PARTIAL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Command to list the languages supported by the backend.
+const LANGUAGES_COMMAND: &str = "typst-languagetool.languages";
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
 #[serde(default)]
@@ -22,7 +41,23 @@ struct InitOptions {
 	#[serde(with = "humantime_serde")]
 	on_change: Option<std::time::Duration>,
 
-	/// Path to JSON with configuration.
+	/// Maximum duration for a check, leave empty for no limit
+	#[serde(with = "humantime_serde")]
+	check_timeout: Option<std::time::Duration>,
+
+	/// Log the duration of compile, convert, each backend request and mapping
+	timings: bool,
+
+	/// Start the backend on the first check instead of the initialization
+	lazy_backend: bool,
+
+	/// Log level (`error`, `warn`, `info`, `debug` or `trace`), `TYPST_LANGUAGETOOL_LOG` takes precedence
+	log_level: Option<String>,
+
+	/// Append the log to the file instead of stderr
+	log_file: Option<PathBuf>,
+
+	/// Path to JSON, TOML or YAML with configuration, defaults to `typst-languagetool.toml`, ... in the root.
 	options: Option<PathBuf>,
 
 	#[serde(flatten)]
@@ -41,17 +76,48 @@ impl InitOptions {
 		}
 		let cwd = std::env::current_dir().unwrap();
 		make_absolute(&cwd, &mut self.lt.main);
+		make_absolute(&cwd, &mut self.lt.wrapper);
+		for path in &mut self.lt.mains {
+			*path = cwd.join(&path);
+		}
 		make_absolute(&cwd, &mut self.lt.root);
+		make_absolute(&cwd, &mut self.log_file);
+		make_absolute(&cwd, &mut self.lt.package_path);
+		make_absolute(&cwd, &mut self.lt.package_cache_path);
+		for path in &mut self.lt.font_paths {
+			*path = cwd.join(&path);
+		}
 	}
 }
 
+/// World for the root with the font, package and date options.
+fn world(options: &LanguageToolOptions, root: PathBuf) -> LtWorld {
+	let fonts = FontOptions {
+		font_paths: options.font_paths.clone(),
+		include_system_fonts: options.include_system_fonts,
+	};
+	let packages = PackageOptions {
+		offline: options.offline,
+		package_path: options.package_path.clone(),
+		package_cache_path: options.package_cache_path.clone(),
+	};
+	let creation_date =
+		lt_world::creation_date(options.creation_date.as_deref()).unwrap_or_else(|err| {
+			tracing::error!("{}", err);
+			None
+		});
+	LtWorld::with_options(root, fonts, packages).with_creation_date(creation_date)
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-	eprintln!("Starting LSP server");
+	let logging = Logging::init();
+	tracing::info!("Starting LSP server");
 
 	let (connection, io_threads) = Connection::stdio();
 
 	let capabilities = ServerCapabilities {
+		position_encoding: Some(PositionEncodingKind::UTF16),
 		text_document_sync: Some(TextDocumentSyncCapability::Options(
 			TextDocumentSyncOptions {
 				open_close: Some(true),
@@ -64,6 +130,10 @@ async fn main() -> anyhow::Result<()> {
 		)),
 
 		code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+		execute_command_provider: Some(ExecuteCommandOptions {
+			commands: vec![String::from(LANGUAGES_COMMAND)],
+			..Default::default()
+		}),
 		..Default::default()
 	};
 
@@ -77,28 +147,69 @@ async fn main() -> anyhow::Result<()> {
 			return Err(e.into());
 		},
 	};
-	let state = State::new(connection, initialization_params).await?;
+	logging.forward(connection.sender.clone());
+	let state = State::new(connection, initialization_params, logging).await?;
 	state.main_loop().await?;
 	io_threads.join()?;
 
-	eprintln!("Shutting down server");
+	tracing::info!("Shutting down server");
 	Ok(())
 }
 
 struct Options {
-	chunk_size: usize,
+	chunk_size: ChunkSize,
+	jobs: usize,
 	on_change: Option<std::time::Duration>,
-	language_codes: HashMap<String, String>,
+	check_timeout: Option<std::time::Duration>,
+	timings: bool,
+	max_suggestions: Option<usize>,
+	drop_similar_suggestions: bool,
+	rule_severity: HashMap<String, Severity>,
+	comment_severity: Option<Severity>,
+	unnecessary_rules: Vec<String>,
+	/// `languages`, `default_language`, `file_languages` and `file_disabled_checks` of the options
+	languages: LanguageToolOptions,
 	main: Option<PathBuf>,
+	mains: Vec<PathBuf>,
+	wrapper: Option<PathBuf>,
+	directories: DirectoryOptions,
+	ltex_comments: bool,
+	headings: Headings,
+	heading_disabled_checks: Vec<String>,
+	join_list_items: bool,
+	bibliography_fields: HashMap<String, Vec<String>>,
+	detached_text: DetachedText,
+	min_word_length: usize,
+	skip_numeric_words: bool,
+	headers_footers: bool,
+	outline: bool,
+	check_comments: bool,
+}
+
+impl Options {
+	fn language(&self, mapping: &Mapping) -> String {
+		if let Some(lang) = mapping.comment_language() {
+			return lang.to_string();
+		}
+		self.directories
+			.language(mapping)
+			.or_else(|| self.languages.language(mapping))
+			.unwrap_or(mapping.long_language())
+	}
 }
 
 struct State {
 	world: LtWorld,
 	cache: Cache,
-	lt: LanguageTool,
+	/// Paragraphs of the last check for each file
+	checked: HashMap<PathBuf, Incremental>,
+	lt: Backend,
 	connection: Connection,
+	/// Messages received while checking, handled before the new messages
+	pending: VecDeque<Message>,
 	check: Option<CheckData>,
 	options: Options,
+	logging: Logging,
 }
 
 struct CheckData {
@@ -107,54 +218,158 @@ struct CheckData {
 	path: PathBuf,
 }
 
+/// Replacement with the edits for the locations of the diagnostic, stored in the data of the diagnostic.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Replacement {
+	value: String,
+	edits: Vec<TextEdit>,
+}
+
 enum Action {
 	Message(Message),
 	Check(CheckData),
 }
 
 impl State {
-	pub async fn new(connection: Connection, params: Value) -> anyhow::Result<Self> {
+	pub async fn new(
+		connection: Connection,
+		params: Value,
+		logging: Logging,
+	) -> anyhow::Result<Self> {
 		let params = serde_json::from_value::<InitializeParams>(params)?;
 		let options = params.initialization_options.context("No init options")?;
 
 		let mut options = serde_ignored::deserialize::<_, _, InitOptions>(options, |path| {
-			eprintln!("Unknown option: {}", path);
+			tracing::warn!("Unknown option: {}", path);
 		})?;
 
-		if let Some(path) = &options.options {
-			let file = File::open(path)?;
-			let file_options = serde_json::from_reader::<_, LanguageToolOptions>(file)?;
+		let options_path = options.options.clone().or_else(|| {
+			LanguageToolOptions::find(options.lt.root.as_deref().unwrap_or(Path::new(".")))
+		});
+		if let Some(path) = options_path {
+			let file_options = LanguageToolOptions::load(&path)?;
 			options.lt = file_options.overwrite(options.lt);
+			tracing::info!("Options loaded from {}", path.display());
+		}
+		if let Some(path) = LanguageToolOptions::find_global() {
+			let global_options = LanguageToolOptions::load(&path)?;
+			options.lt = global_options.overwrite(options.lt);
+			tracing::info!("Global options loaded from {}", path.display());
 		}
+		match options.lt.use_project() {
+			Ok(Some(project)) => tracing::info!(
+				"Package {} {} in {}",
+				project.name,
+				project.version,
+				project.root.display()
+			),
+			Ok(None) => {},
+			Err(err) => tracing::warn!("Project settings ignored: {}", err),
+		}
+		options.lt = options.lt.with_profile();
 
 		let cache = Cache::new();
 
 		options.make_absolute();
-		eprintln!("Options: {:#?}", options);
-		let lt = LanguageTool::new(&options.lt).await?;
-
-		let world = lt_world::LtWorld::new(options.lt.root.clone().unwrap_or_else(|| ".".into()));
+		logging.apply(options.log_level.as_deref(), options.log_file.as_deref())?;
+		tracing::debug!("Options: {:#?}", options);
+		let lt = Backend::Lazy(Box::new(options.lt.clone()), connection.sender.clone());
+		if let Some(backend) = &options.lt.backend {
+			tracing::info!("Backend {:?}", backend);
+		}
 
-		eprintln!("Compiling document");
+		let world = world(
+			&options.lt,
+			options.lt.root.clone().unwrap_or_else(|| ".".into()),
+		);
 
-		Ok(Self {
+		let directories = DirectoryOptions::new(world.root());
+		let mut state = Self {
 			world,
 			cache,
+			checked: HashMap::new(),
 			lt,
 			connection,
+			pending: VecDeque::new(),
 			check: None,
+			logging,
 
 			options: Options {
 				on_change: options.on_change,
-				chunk_size: options.lt.chunk_size,
-				language_codes: options.lt.languages,
+				check_timeout: options.check_timeout,
+				timings: options.timings,
+				chunk_size: ChunkSize::new(options.lt.chunk_size, options.lt.auto_chunk_size)
+					.with_languages(options.lt.language_chunk_sizes),
+				jobs: options.lt.jobs,
+				max_suggestions: options.lt.max_suggestions,
+				drop_similar_suggestions: options.lt.drop_similar_suggestions,
+				rule_severity: options.lt.rule_severity,
+				comment_severity: options.lt.comment_severity,
+				unnecessary_rules: options.lt.unnecessary_rules,
+				languages: LanguageToolOptions {
+					languages: options.lt.languages,
+					default_language: options.lt.default_language,
+					file_languages: options.lt.file_languages,
+					file_disabled_checks: options.lt.file_disabled_checks,
+					..Default::default()
+				},
 				main: options.lt.main,
+				mains: options.lt.mains,
+				wrapper: options.lt.wrapper,
+				directories,
+				ltex_comments: options.lt.ltex_comments,
+				headings: options.lt.headings.unwrap_or_default(),
+				heading_disabled_checks: options.lt.heading_disabled_checks,
+				join_list_items: options.lt.join_list_items,
+				bibliography_fields: options.lt.bibliography_fields,
+				detached_text: options.lt.detached_text.unwrap_or_default(),
+				min_word_length: options.lt.min_word_length.unwrap_or_default(),
+				skip_numeric_words: options.lt.skip_numeric_words,
+				headers_footers: options.lt.headers_footers,
+				outline: options.lt.outline,
+				check_comments: options.lt.check_comments,
 			},
-		})
+		};
+		if options.lazy_backend.not() {
+			state.prewarm();
+		}
+		Ok(state)
+	}
+
+	/// Start the backend in the background and compile the main document meanwhile.
+	fn prewarm(&mut self) {
+		let Backend::Lazy(options, client) = &self.lt else {
+			return;
+		};
+		let (languages, receiver) = oneshot::channel();
+		self.lt = Backend::start(options.as_ref().clone(), client.clone(), receiver);
+		_ = languages.send(self.main_languages());
+	}
+
+	/// Languages of the main documents, later checks reuse the compilation.
+	fn main_languages(&self) -> Vec<String> {
+		let mut languages = Vec::new();
+		for main in self.options.main.iter().chain(&self.options.mains) {
+			let start = std::time::Instant::now();
+			let world = self.world.with_main(main.clone());
+			let doc = world.compile();
+			self.timing("compile", start);
+			let Ok(doc) = doc else {
+				continue;
+			};
+			languages.extend(
+				self.paragraphs(&world, &doc, None)
+					.iter()
+					.map(|(_, mapping)| self.options.language(mapping)),
+			);
+		}
+		languages.sort();
+		languages.dedup();
+		languages
 	}
 
 	pub async fn main_loop(mut self) -> anyhow::Result<()> {
-		eprintln!("Waiting for events");
+		tracing::debug!("Waiting for events");
 		loop {
 			match self.next_action()? {
 				Action::Message(msg) => self.message(msg).await?,
@@ -164,6 +379,9 @@ impl State {
 	}
 
 	fn next_action(&mut self) -> anyhow::Result<Action> {
+		if let Some(msg) = self.pending.pop_front() {
+			return Ok(Action::Message(msg));
+		}
 		if let Some(last_change) = &self.check {
 			let msg = self
 				.connection
@@ -189,7 +407,7 @@ impl State {
 				self.request(req).await
 			},
 			Message::Response(resp) => {
-				eprintln!("Unknown response: {:?}", resp);
+				tracing::warn!("Unknown response: {:?}", resp);
 				Ok(())
 			},
 			Message::Notification(not) => self.notification(not).await,
@@ -206,10 +424,35 @@ impl State {
 			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
 			Err(ExtractError::MethodMismatch(req)) => req,
 		};
-		eprintln!("Unknown request: {:?}", req);
+		let req = match cast_request::<ExecuteCommand>(req) {
+			Ok((id, params)) => {
+				let result = self.execute_command(params).await?;
+				send_response::<ExecuteCommand>(&self.connection, id, result)?;
+				return Ok(());
+			},
+			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
+			Err(ExtractError::MethodMismatch(req)) => req,
+		};
+		tracing::warn!("Unknown request: {:?}", req);
 		Ok(())
 	}
 
+	async fn execute_command(
+		&mut self,
+		params: ExecuteCommandParams,
+	) -> anyhow::Result<Option<Value>> {
+		match params.command.as_str() {
+			LANGUAGES_COMMAND => {
+				let languages = self.lt.get().await?.list_languages().await?;
+				Ok(Some(serde_json::to_value(languages)?))
+			},
+			command => {
+				tracing::warn!("Unknown command: {}", command);
+				Ok(None)
+			},
+		}
+	}
+
 	async fn code_action(
 		&self,
 		params: CodeActionParams,
@@ -223,18 +466,17 @@ impl State {
 			return Ok(None);
 		};
 
-		let replacements = match serde_json::from_value::<Vec<String>>(data.clone()) {
+		let replacements = match serde_json::from_value::<Vec<Replacement>>(data.clone()) {
 			Ok(r) => r,
 			Err(err) => {
-				eprintln!("{}", err);
+				tracing::warn!("Invalid code action data: {}", err);
 				return Ok(None);
 			},
 		};
 
-		for (i, value) in replacements.into_iter().enumerate() {
-			let title = format!("Replace with \"{}\"", value);
-			let replace = TextEdit { range: diagnostic.range, new_text: value };
-			let edit = [(params.text_document.uri.clone(), vec![replace])]
+		for (i, replacement) in replacements.into_iter().enumerate() {
+			let title = format!("Replace with \"{}\"", replacement.value);
+			let edit = [(params.text_document.uri.clone(), replacement.edits)]
 				.into_iter()
 				.collect();
 
@@ -294,13 +536,16 @@ impl State {
 			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
 			Err(ExtractError::MethodMismatch(not)) => not,
 		};
-		eprintln!("Unknown notification: {:?}", not);
+		tracing::warn!("Unknown notification: {:?}", not);
 		Ok(())
 	}
 
 	async fn file_save(&mut self, params: DidSaveTextDocumentParams) -> anyhow::Result<()> {
 		let path = params.text_document.uri.to_file_path().unwrap();
-		eprintln!("Save {}", path.display());
+		tracing::debug!("Save {}", path.display());
+		self.world.invalidate(&path);
+		// the saved file may be an options file of a directory
+		self.options.directories.clear();
 		self.check = Some(CheckData {
 			check_time: std::time::Instant::now(),
 			url: params.text_document.uri,
@@ -311,7 +556,7 @@ impl State {
 
 	async fn file_open(&mut self, params: DidOpenTextDocumentParams) -> anyhow::Result<()> {
 		let path = params.text_document.uri.to_file_path().unwrap();
-		eprintln!("Open {}", path.display());
+		tracing::debug!("Open {}", path.display());
 		self.world.use_shadow_file(&path, params.text_document.text);
 		self.check = Some(CheckData {
 			check_time: std::time::Instant::now(),
@@ -323,24 +568,22 @@ impl State {
 
 	async fn file_close(&mut self, params: DidCloseTextDocumentParams) -> anyhow::Result<()> {
 		let path = &params.text_document.uri.to_file_path().unwrap();
-		eprintln!("Close {}", path.display());
-		self.world.use_original_file(&path);
+		tracing::debug!("Close {}", path.display());
+		self.world.use_original_file(path);
+		self.world.invalidate(path);
+		self.checked.remove(path);
 		Ok(())
 	}
 
 	async fn file_change(&mut self, params: DidChangeTextDocumentParams) -> anyhow::Result<()> {
 		let path = params.text_document.uri.to_file_path().unwrap();
-		eprintln!("Change {}", path.display());
+		tracing::debug!("Change {}", path.display());
 		let source = self.world.shadow_file(&path).unwrap();
 
 		for change in &params.content_changes {
 			if let Some(range) = change.range {
-				let start = source
-					.line_column_to_byte(range.start.line as usize, range.start.character as usize)
-					.unwrap();
-				let end = source
-					.line_column_to_byte(range.end.line as usize, range.end.character as usize)
-					.unwrap();
+				let start = position_to_byte(source, range.start);
+				let end = position_to_byte(source, range.end);
 				source.edit(start..end, &change.text);
 			} else {
 				source.replace(&change.text);
@@ -358,146 +601,445 @@ impl State {
 		Ok(())
 	}
 
+	#[tracing::instrument(level = "debug", skip_all, fields(path = %path.display()))]
 	async fn check_change(&mut self, path: &Path, url: Url) -> anyhow::Result<()> {
-		eprintln!("Checking: {}", path.display());
+		tracing::debug!("Checking: {}", path.display());
+		let start = std::time::Instant::now();
+
+		let cancellation = match self.options.check_timeout {
+			Some(timeout) => Cancellation::with_deadline(std::time::Instant::now() + timeout),
+			None => Cancellation::new(),
+		};
+		// changes of the document make the running check stale, the other messages are kept for later
+		let finished = Cancellation::new();
+		let stale = tokio::spawn({
+			let receiver = self.connection.receiver.clone();
+			let cancellation = cancellation.clone();
+			let finished = finished.clone();
+			let url = url.clone();
+			async move {
+				let mut received = Vec::new();
+				let mut changed = false;
+				while cancellation.is_cancelled().not() && finished.is_cancelled().not() {
+					match receiver.try_recv() {
+						Ok(msg) => {
+							if changes_document(&msg, &url) {
+								changed = true;
+								cancellation.cancel();
+							}
+							received.push(msg);
+						},
+						Err(TryRecvError::Empty) => tokio::time::sleep(STALE_INTERVAL).await,
+						Err(TryRecvError::Disconnected) => break,
+					}
+				}
+				(received, changed)
+			}
+		});
+		let diagnostics = self.get_diagnostics(path, &url, &cancellation).await;
+		finished.cancel();
+		let (received, changed) = stale.await?;
+		self.pending.extend(received);
 
-		let diagnostics = match self.get_diagnostics(path).await {
+		let diagnostics = match diagnostics {
 			Ok(d) => d,
+			Err(err) if err.is::<Cancelled>() && changed => {
+				tracing::debug!("Check cancelled, document changed");
+				// check again after the messages, unless a new check is scheduled
+				self.check = Some(CheckData {
+					check_time: std::time::Instant::now(),
+					url,
+					path: path.to_owned(),
+				});
+				return Ok(());
+			},
 			Err(err) => {
-				eprintln!("{:?}", err);
+				tracing::error!("Check failed: {:?}", err);
 				return Ok(());
 			},
 		};
 		let l = diagnostics.len();
 		let params = PublishDiagnosticsParams { uri: url, version: None, diagnostics };
 		send_notification::<PublishDiagnostics>(&self.connection, params)?;
-		eprintln!("{} Diagnostics send", l);
+		tracing::info!(
+			"Checked {} in {:.2?}, {} diagnostics",
+			path.display(),
+			start.elapsed(),
+			l
+		);
 		Ok(())
 	}
 
 	async fn config_change(&mut self, params: DidChangeConfigurationParams) -> anyhow::Result<()> {
 		let mut options =
 			match serde_ignored::deserialize::<_, _, InitOptions>(params.settings, |path| {
-				eprintln!("Unknown option {}", path);
+				tracing::warn!("Unknown option: {}", path);
 			}) {
 				Ok(o) => o,
 				Err(err) => {
-					eprintln!("{}", err);
+					tracing::error!("Invalid options: {}", err);
 					return Ok(());
 				},
 			};
 
-		if let Some(path) = &options.options {
-			let file = File::open(path)?;
-			let file_options = serde_json::from_reader::<_, LanguageToolOptions>(file)?;
+		let options_path = options.options.clone().or_else(|| {
+			LanguageToolOptions::find(options.lt.root.as_deref().unwrap_or(Path::new(".")))
+		});
+		if let Some(path) = options_path {
+			let file_options = LanguageToolOptions::load(&path)?;
 			options.lt = file_options.overwrite(options.lt);
+			tracing::info!("Options loaded from {}", path.display());
+		}
+		if let Some(path) = LanguageToolOptions::find_global() {
+			let global_options = LanguageToolOptions::load(&path)?;
+			options.lt = global_options.overwrite(options.lt);
+			tracing::info!("Global options loaded from {}", path.display());
+		}
+		match options.lt.use_project() {
+			Ok(Some(project)) => tracing::info!(
+				"Package {} {} in {}",
+				project.name,
+				project.version,
+				project.root.display()
+			),
+			Ok(None) => {},
+			Err(err) => tracing::warn!("Project settings ignored: {}", err),
 		}
+		options.lt = options.lt.with_profile();
 
 		options.make_absolute();
-		eprintln!("Options: {:#?}", options);
+		if let Err(err) = self
+			.logging
+			.apply(options.log_level.as_deref(), options.log_file.as_deref())
+		{
+			tracing::error!("Invalid log options: {}", err);
+		}
+		tracing::debug!("Options: {:#?}", options);
 
-		self.lt = match LanguageTool::new(&options.lt).await {
-			Ok(lt) => lt,
-			Err(err) => {
-				eprintln!("{}", err);
-				return Ok(());
-			},
-		};
+		self.lt = Backend::Lazy(Box::new(options.lt.clone()), self.connection.sender.clone());
+		if let Some(backend) = &options.lt.backend {
+			tracing::info!("Backend {:?}", backend);
+		}
 
-		if let Some(root) = options.lt.root {
-			self.world = LtWorld::new(root);
+		if let Some(root) = options.lt.root.clone() {
+			// the fonts are reused if the font options are unchanged
+			self.world = world(&options.lt, root);
 		}
+		// suggestions of the previous backend or options
+		self.cache = Cache::new();
+		self.checked.clear();
 
 		self.options = Options {
 			on_change: options.on_change,
-			chunk_size: options.lt.chunk_size,
-			language_codes: options.lt.languages,
+			check_timeout: options.check_timeout,
+			timings: options.timings,
+			chunk_size: ChunkSize::new(options.lt.chunk_size, options.lt.auto_chunk_size)
+				.with_languages(options.lt.language_chunk_sizes),
+			jobs: options.lt.jobs,
+			max_suggestions: options.lt.max_suggestions,
+			drop_similar_suggestions: options.lt.drop_similar_suggestions,
+			rule_severity: options.lt.rule_severity,
+			comment_severity: options.lt.comment_severity,
+			unnecessary_rules: options.lt.unnecessary_rules,
+			languages: LanguageToolOptions {
+				languages: options.lt.languages,
+				default_language: options.lt.default_language,
+				file_languages: options.lt.file_languages,
+				file_disabled_checks: options.lt.file_disabled_checks,
+				..Default::default()
+			},
 			main: options.lt.main,
+			mains: options.lt.mains,
+			wrapper: options.lt.wrapper,
+			directories: DirectoryOptions::new(self.world.root()),
+			ltex_comments: options.lt.ltex_comments,
+			headings: options.lt.headings.unwrap_or_default(),
+			heading_disabled_checks: options.lt.heading_disabled_checks,
+			join_list_items: options.lt.join_list_items,
+			bibliography_fields: options.lt.bibliography_fields,
+			detached_text: options.lt.detached_text.unwrap_or_default(),
+			min_word_length: options.lt.min_word_length.unwrap_or_default(),
+			skip_numeric_words: options.lt.skip_numeric_words,
+			headers_footers: options.lt.headers_footers,
+			outline: options.lt.outline,
+			check_comments: options.lt.check_comments,
 		};
+		if options.lazy_backend.not() {
+			self.prewarm();
+		}
 
 		Ok(())
 	}
 
-	async fn get_diagnostics(&mut self, path: &Path) -> anyhow::Result<Vec<Diagnostic>> {
-		let world = self
-			.world
-			.with_main(self.options.main.clone().unwrap_or_else(|| path.to_owned()));
-		eprintln!("Compiling");
-		let doc = match world.compile() {
+	/// Text chunks of the document, with the LTeX magic comments if enabled.
+	fn paragraphs(
+		&self,
+		world: &LtWorldRunning,
+		doc: &Document,
+		file_id: Option<FileId>,
+	) -> Vec<(String, Mapping)> {
+		let selection = Selection {
+			headers_footers: self.options.headers_footers,
+			outline: self.options.outline,
+			..Default::default()
+		};
+		// without a section there is no error
+		let mut paragraphs = typst_languagetool::convert::document_selection(
+			doc,
+			&self.options.chunk_size,
+			file_id,
+			&selection,
+		)
+		.unwrap_or_default();
+		if self.options.check_comments {
+			let comments = typst_languagetool::source_comments(
+				world,
+				&paragraphs,
+				file_id,
+				&self.options.chunk_size,
+			);
+			paragraphs.extend(comments);
+		}
+		let paragraphs = if self.options.ltex_comments {
+			typst_languagetool::ltex_comments(world, paragraphs)
+		} else {
+			paragraphs
+		};
+		let paragraphs = typst_languagetool::bibliography(
+			world,
+			doc,
+			paragraphs,
+			&self.options.bibliography_fields,
+		);
+		let paragraphs =
+			typst_languagetool::list_items(world, paragraphs, self.options.join_list_items);
+		let paragraphs =
+			typst_languagetool::detached_text(paragraphs, file_id, self.options.detached_text);
+		let paragraphs = typst_languagetool::headings(
+			doc,
+			paragraphs,
+			self.options.headings,
+			&self.options.heading_disabled_checks,
+		);
+		// after the passes which change the text, the skipped words are ranges in the text
+		typst_languagetool::skip_words(
+			paragraphs,
+			self.options.min_word_length,
+			self.options.skip_numeric_words,
+		)
+	}
+
+	fn timing(&self, stage: &str, start: std::time::Instant) {
+		if self.options.timings {
+			tracing::info!("Timing {}: {:.2?}", stage, start.elapsed());
+		}
+	}
+
+	async fn get_diagnostics(
+		&mut self,
+		path: &Path,
+		url: &Url,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Diagnostic>> {
+		let main = self
+			.options
+			.main
+			.clone()
+			.or_else(|| self.world.main_for(&self.options.mains, path));
+		let world = match (main, &self.options.wrapper) {
+			(Some(main), _) => self.world.with_main(main),
+			// the wrapper itself is checked as its own main file
+			(None, Some(wrapper)) if self.world.file_id(wrapper) != self.world.file_id(path) => {
+				self.world.with_wrapper(wrapper, path).map_err(|err| {
+					anyhow::anyhow!("Failed to read wrapper {}: {}", wrapper.display(), err)
+				})?
+			},
+			(None, _) => self.world.with_main(path.to_owned()),
+		};
+		tracing::debug!("Compiling");
+		let start = std::time::Instant::now();
+		let doc = tracing::debug_span!("compile").in_scope(|| world.compile());
+		self.timing("compile", start);
+		let doc = match doc {
 			Ok(doc) => doc,
 			Err(err) => {
-				eprintln!("Failed to compile document");
+				tracing::warn!("Failed to compile document");
 				for dia in err {
-					eprintln!("\t{:?}", dia);
+					tracing::warn!("\t{:?}", dia);
 				}
 				return Ok(Vec::new());
 			},
 		};
+		let metadata = DocumentOptions::new(&doc);
 
 		let Some(file_id) = self.world.file_id(path) else {
 			return Ok(Vec::new());
 		};
-		eprintln!("Converting");
-		let paragraphs =
-			typst_languagetool::convert::document(&doc, self.options.chunk_size, Some(file_id));
-		let mut collector = typst_languagetool::FileCollector::new(Some(file_id), &world);
-		let mut next_cache = Cache::new();
+		tracing::debug!("Converting");
+		let start = std::time::Instant::now();
+		let paragraphs = tracing::debug_span!("convert")
+			.in_scope(|| self.paragraphs(&world, &doc, Some(file_id)));
+		self.timing("convert", start);
 		let l = paragraphs.len();
-		eprintln!("Checking {} paragraphs", l);
-		for (idx, (text, mapping)) in paragraphs.into_iter().enumerate() {
-			let lang = self
-				.options
-				.language_codes
-				.get(mapping.short_language())
-				.map(|x| x.clone())
-				.unwrap_or(mapping.long_language());
-			let suggestions = if let Some(suggestions) = self.cache.get(&text, &lang) {
-				suggestions
-			} else {
-				eprintln!("Checking {}/{}", idx + 1, l);
-				self.lt.check_text(lang.clone(), &text).await?
-			};
-			collector.add(&world, &suggestions, &mapping);
-			next_cache.insert(text, lang, suggestions);
+		let mut previous = self.checked.remove(path).unwrap_or_default();
+		let mut paragraphs = paragraphs
+			.into_iter()
+			.map(|(text, mapping)| {
+				let lang = self.options.language(&mapping);
+				// changed paragraphs may be known from other files or older versions
+				let suggestions = previous
+					.get(&text, &lang)
+					.or_else(|| self.cache.get(&text, &lang));
+				(text, lang, mapping, suggestions)
+			})
+			.collect::<Vec<_>>();
+
+		// check in batches, so a cancelled check keeps the finished batches
+		let missing = paragraphs
+			.iter()
+			.enumerate()
+			.filter(|(_, (_, _, _, suggestions))| suggestions.is_none())
+			.map(|(idx, _)| idx)
+			.collect::<Vec<_>>();
+		tracing::debug!("Checking {} of {} paragraphs", missing.len(), l);
+		let batches = missing.chunks(self.options.jobs.max(1)).collect::<Vec<_>>();
+		let mut published = std::time::Instant::now();
+		for (batch_idx, batch) in batches.iter().enumerate() {
+			tracing::debug!("Checking {}/{}", batch[0] + 1, l);
+			let start = std::time::Instant::now();
+			let texts = batch
+				.iter()
+				.map(|&idx| (paragraphs[idx].1.clone(), paragraphs[idx].0.clone()))
+				.collect::<Vec<_>>();
+			let jobs = self.options.jobs;
+			let lt = self.lt.get().await?;
+			let result = lt.check_texts(&texts, jobs, cancellation).await;
+			backend::fallback_switch(lt, &self.connection.sender)?;
+			match result {
+				Ok(results) => {
+					self.timing("check", start);
+					let chars = texts
+						.iter()
+						.map(|(_, text)| text.chars().count())
+						.sum::<usize>();
+					self.options
+						.chunk_size
+						.observe(chars / texts.len(), start.elapsed());
+					for (&idx, suggestions) in batch.iter().zip(results) {
+						let (text, lang, _, _) = &paragraphs[idx];
+						self.cache.insert(text, lang, suggestions.clone());
+						previous.insert(text, lang, suggestions.clone());
+						paragraphs[idx].3 = Some(suggestions);
+					}
+				},
+				Err(err) => {
+					self.checked.insert(path.to_owned(), previous);
+					return Err(err);
+				},
+			}
+			// show the finished paragraphs while the remaining ones are checked
+			if batch_idx + 1 < batches.len() && published.elapsed() >= PARTIAL_INTERVAL {
+				let diagnostics = self.diagnostics(&world, &metadata, file_id, &paragraphs);
+				tracing::debug!("Publishing {} partial diagnostics", diagnostics.len());
+				let params = PublishDiagnosticsParams {
+					uri: url.clone(),
+					version: None,
+					diagnostics,
+				};
+				send_notification::<PublishDiagnostics>(&self.connection, params)?;
+				published = std::time::Instant::now();
+			}
+		}
+		let mut checked = Incremental::new();
+		for (text, lang, _, suggestions) in &paragraphs {
+			if let Some(suggestions) = suggestions {
+				checked.insert(text, lang, suggestions.clone());
+			}
 		}
-		self.cache = next_cache;
-		eprintln!("Generating diagnostics");
+		self.checked.insert(path.to_owned(), checked);
+		let start = std::time::Instant::now();
+		let diagnostics = self.diagnostics(&world, &metadata, file_id, &paragraphs);
+		self.timing("mapping", start);
+		Ok(diagnostics)
+	}
 
-		let diagnostics = collector.finish();
+	/// Diagnostics for the checked paragraphs, unchecked paragraphs are skipped.
+	fn diagnostics(
+		&self,
+		world: &LtWorldRunning,
+		metadata: &DocumentOptions,
+		file_id: FileId,
+		paragraphs: &[(String, String, Mapping, Option<Vec<Suggestion>>)],
+	) -> Vec<Diagnostic> {
+		let mut collector = typst_languagetool::FileCollector::new(Some(file_id), world);
+		for (text, lang, mapping, suggestions) in paragraphs {
+			if let Some(suggestions) = suggestions {
+				let mut kept = suggestions.clone();
+				self.options
+					.directories
+					.retain(text, lang, mapping, &mut kept);
+				self.options
+					.languages
+					.retain_file_checks(mapping, &mut kept);
+				metadata.retain(text, lang, &mut kept);
+				collector.add(world, &kept, mapping);
+			}
+		}
+		tracing::debug!("Generating diagnostics");
+
+		let mut diagnostics = collector.finish();
+		for diagnostic in &mut diagnostics {
+			diagnostic.filter_replacements(
+				self.options.max_suggestions,
+				self.options.drop_similar_suggestions,
+			);
+			diagnostic.apply_rule_options(
+				&self.options.rule_severity,
+				self.options.comment_severity,
+				&self.options.unnecessary_rules,
+			);
+		}
 		let source = world.source(file_id).unwrap();
 
-		let diagnostics = diagnostics
+		diagnostics
 			.into_iter()
 			.map(|diagnostic| {
-				let (start_line, start_column) =
-					byte_to_position(&source, diagnostic.locations[0].1.start);
-				let (end_line, end_column) =
-					byte_to_position(&source, diagnostic.locations[0].1.end);
+				// the edits are split for diagnostics spanning markup
+				let replacements = diagnostic
+					.replacements
+					.iter()
+					.filter_map(|value| {
+						let edits = diagnostic
+							.edits(value, world)?
+							.into_iter()
+							.map(|(_, range, new_text)| TextEdit {
+								range: byte_range_to_range(&source, range),
+								new_text,
+							})
+							.collect();
+						Some(Replacement { value: value.clone(), edits })
+					})
+					.collect::<Vec<_>>();
 
 				Diagnostic {
-					range: Range {
-						start: lsp_types::Position {
-							line: start_line as u32,
-							character: start_column as u32,
-						},
-						end: lsp_types::Position {
-							line: end_line as u32,
-							character: end_column as u32,
-						},
-					},
-					severity: Some(DiagnosticSeverity::INFORMATION),
-					code: Some(NumberOrString::String(diagnostic.rule_id)),
+					range: byte_range_to_range(&source, diagnostic.locations[0].1.clone()),
+					severity: Some(match diagnostic.severity {
+						Severity::Info => DiagnosticSeverity::INFORMATION,
+						Severity::Warning => DiagnosticSeverity::WARNING,
+						Severity::Error => DiagnosticSeverity::ERROR,
+					}),
+					code: Some(NumberOrString::String(diagnostic.rule_id.to_string())),
 					code_description: None,
 					source: None,
-					message: diagnostic.message,
+					message: diagnostic.message.to_string(),
 					related_information: None,
-					tags: None,
-					data: serde_json::to_value(diagnostic.replacements).ok(),
+					tags: diagnostic
+						.unnecessary
+						.then(|| vec![DiagnosticTag::UNNECESSARY]),
+					data: serde_json::to_value(replacements).ok(),
 				}
 			})
-			.collect();
-
-		Ok(diagnostics)
+			.collect()
 	}
 }
 
@@ -528,6 +1070,26 @@ where
 	Ok(())
 }
 
+/// Change or save of the document, which makes a running check of the document stale.
+fn changes_document(msg: &Message, url: &Url) -> bool {
+	let Message::Notification(notification) = msg else {
+		return false;
+	};
+	let changes = [
+		<DidChangeTextDocument as lsp_types::notification::Notification>::METHOD,
+		<DidSaveTextDocument as lsp_types::notification::Notification>::METHOD,
+	];
+	if changes.contains(&notification.method.as_str()).not() {
+		return false;
+	}
+	notification
+		.params
+		.get("textDocument")
+		.and_then(|document| document.get("uri"))
+		.and_then(Value::as_str)
+		== Some(url.as_str())
+}
+
 fn send_response<R>(connection: &Connection, id: RequestId, result: R::Result) -> anyhow::Result<()>
 where
 	R: lsp_types::request::Request,
@@ -546,30 +1108,36 @@ where
 	Ok(())
 }
 
-#[derive(Debug)]
-struct Cache {
-	cache: HashMap<String, (String, Vec<Suggestion>)>,
+/// Line and column in UTF-16 code units, the default position encoding of the protocol.
+fn byte_to_position(source: &Source, index: usize) -> (usize, usize) {
+	let line = source.byte_to_line(index).unwrap();
+	let start = source.line_to_byte(line).unwrap();
+	let column = source.byte_to_utf16(index).unwrap() - source.byte_to_utf16(start).unwrap();
+	(line, column)
 }
 
-impl Cache {
-	pub fn new() -> Self {
-		Self { cache: HashMap::new() }
-	}
-
-	pub fn get(&mut self, text: &str, lang: &str) -> Option<Vec<Suggestion>> {
-		let entry = self.cache.remove(text)?;
-		(lang == entry.0).then_some(entry.1)
-	}
-
-	pub fn insert(&mut self, text: String, lang: String, suggestions: Vec<Suggestion>) {
-		self.cache.insert(text, (lang, suggestions));
+fn byte_range_to_range(source: &Source, range: std::ops::Range<usize>) -> Range {
+	let (start_line, start_column) = byte_to_position(source, range.start);
+	let (end_line, end_column) = byte_to_position(source, range.end);
+	Range {
+		start: Position {
+			line: start_line as u32,
+			character: start_column as u32,
+		},
+		end: Position {
+			line: end_line as u32,
+			character: end_column as u32,
+		},
 	}
 }
 
-fn byte_to_position(source: &Source, index: usize) -> (usize, usize) {
-	let line = source.byte_to_line(index).unwrap();
-	let start = source.line_to_byte(line).unwrap();
-	let head = source.get(start..index).unwrap();
-	let column = head.chars().count();
-	(line, column)
+/// Byte index for the position with the column in UTF-16 code units.
+fn position_to_byte(source: &Source, position: Position) -> usize {
+	let Some(start) = source.line_to_byte(position.line as usize) else {
+		return source.len_bytes();
+	};
+	let start = source.byte_to_utf16(start).unwrap();
+	source
+		.utf16_to_byte(start + position.character as usize)
+		.unwrap_or(source.len_bytes())
 }
@@ -1,21 +1,23 @@
 use std::{
-	collections::HashMap,
-	ops::Deref,
+	collections::{HashMap, HashSet},
+	ops::{Deref, Not},
 	path::{Path, PathBuf},
+	sync::{Arc, Mutex, OnceLock},
+	time::SystemTime,
 };
 
-use chrono::{DateTime, Datelike, FixedOffset, Local, Utc};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, Utc};
 use typst::{
-	diag::{FileError, FileResult, SourceResult},
-	foundations::{Dict, Value},
+	diag::{FileError, FileResult, PackageError, PackageResult, SourceResult},
+	foundations::{Bytes, Dict, Value},
 	model::Document,
-	syntax::{FileId, Source, VirtualPath},
-	text::Font,
+	syntax::{package::PackageSpec, FileId, Source, VirtualPath},
+	text::{Font, FontBook},
 	utils::LazyHash,
 	Library, World,
 };
 use typst_kit::{
-	download::Downloader,
+	download::{DownloadState, Downloader},
 	fonts::{FontSlot, Fonts},
 	package::PackageStorage,
 };
@@ -26,48 +28,140 @@ pub struct LtWorld {
 	now: DateTime<Utc>,
 
 	packages: PackageStorage,
+	/// Only use downloaded packages
+	offline: bool,
 
-	fonts: Vec<FontSlot>,
-	font_book: LazyHash<typst::text::FontBook>,
+	fonts: Arc<FontData>,
 	shadow_files: HashMap<FileId, Source>,
+	/// Sources read from disk with the modification time, reused while unchanged
+	sources: Mutex<HashMap<FileId, (SystemTime, Source)>>,
+	/// Files read from disk with the modification time, reused while unchanged
+	files: Mutex<HashMap<FileId, (SystemTime, Bytes)>>,
 	root: PathBuf,
 }
 
 pub struct LtWorldRunning<'a> {
 	world: &'a LtWorld,
 	main: FileId,
+	/// Files read while compiling
+	accessed: Mutex<HashSet<FileId>>,
+	/// Source used for the main file, see [`LtWorld::with_wrapper`]
+	wrapper: Option<Source>,
+}
+
+/// Replaced with the path of the wrapped file from the root (`/chapters/intro.typ`).
+pub const WRAPPER_PLACEHOLDER: &str = "{{path}}";
+
+/// Options for the font search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontOptions {
+	/// Additional directories with fonts
+	pub font_paths: Vec<PathBuf>,
+	/// Search the fonts installed on the system
+	pub include_system_fonts: bool,
+}
+
+impl Default for FontOptions {
+	fn default() -> Self {
+		Self {
+			font_paths: Vec::new(),
+			include_system_fonts: true,
+		}
+	}
+}
+
+/// Options for the package storage.
+#[derive(Debug, Clone, Default)]
+pub struct PackageOptions {
+	/// Fail for packages which are not downloaded instead of downloading them
+	pub offline: bool,
+	/// Directory with local packages, defaults to the typst data directory
+	pub package_path: Option<PathBuf>,
+	/// Directory for downloaded packages, defaults to the typst cache directory
+	pub package_cache_path: Option<PathBuf>,
+}
+
+/// Fonts searched on the first use.
+#[derive(Debug)]
+struct FontData {
+	options: FontOptions,
+	fonts: OnceLock<(LazyHash<FontBook>, Vec<FontSlot>)>,
+}
+
+/// Fonts shared by the worlds with the same options, so rebuilding a world does not search again.
+static FONTS: Mutex<Vec<Arc<FontData>>> = Mutex::new(Vec::new());
+
+impl FontData {
+	fn shared(options: FontOptions) -> Arc<Self> {
+		let mut fonts = FONTS.lock().unwrap();
+		if let Some(data) = fonts.iter().find(|data| data.options == options) {
+			return data.clone();
+		}
+		let data = Arc::new(Self { options, fonts: OnceLock::new() });
+		fonts.push(data.clone());
+		data
+	}
+
+	fn get(&self) -> &(LazyHash<FontBook>, Vec<FontSlot>) {
+		self.fonts.get_or_init(|| {
+			let fonts = Fonts::searcher()
+				.include_embedded_fonts(true)
+				.include_system_fonts(self.options.include_system_fonts)
+				.search_with(&self.options.font_paths);
+			(LazyHash::new(fonts.book), fonts.fonts)
+		})
+	}
 }
 
 impl LtWorld {
+	/// World with the embedded and system fonts, packages are downloaded if required.
 	pub fn new(root: PathBuf) -> Self {
+		Self::with_options(root, FontOptions::default(), PackageOptions::default())
+	}
+
+	/// The fonts are searched on the first compilation.
+	pub fn with_options(
+		root: PathBuf,
+		font_options: FontOptions,
+		package_options: PackageOptions,
+	) -> Self {
 		let mut inputs = Dict::new();
 		inputs.insert("spellcheck".into(), Value::Bool(true));
 		let root = root.canonicalize().unwrap();
 
-		let fonts = Fonts::searcher()
-			.include_embedded_fonts(true)
-			.include_system_fonts(true)
-			.search();
-
 		Self {
 			library: LazyHash::new(Library::builder().with_inputs(inputs).build()),
 			now: chrono::Utc::now(),
 
-			packages: PackageStorage::new(None, None, Downloader::new("typst-languagetool")),
+			packages: PackageStorage::new(
+				package_options.package_cache_path,
+				package_options.package_path,
+				Downloader::new("typst-languagetool"),
+			),
+			offline: package_options.offline,
 
-			font_book: LazyHash::new(fonts.book),
-			fonts: fonts.fonts,
+			fonts: FontData::shared(font_options),
 			root,
 			shadow_files: HashMap::new(),
+			sources: Mutex::new(HashMap::new()),
+			files: Mutex::new(HashMap::new()),
 		}
 	}
 
+	/// Use the date for `datetime.today()` instead of the time the world was created.
+	pub fn with_creation_date(mut self, date: Option<DateTime<Utc>>) -> Self {
+		if let Some(date) = date {
+			self.now = date;
+		}
+		self
+	}
+
 	pub fn root(&self) -> &Path {
 		&self.root
 	}
 
 	pub fn file_id(&self, path: &Path) -> Option<FileId> {
-		let path = path.canonicalize().unwrap();
+		let path = absolute(path);
 		let path = path.strip_prefix(&self.root).ok()?;
 		let id = FileId::new(None, VirtualPath::new(path));
 		Some(id)
@@ -93,11 +187,21 @@ impl LtWorld {
 		self.shadow_files.remove(&file_id);
 	}
 
+	/// Read the file again for the next compilation, even if the modification time is unchanged.
+	pub fn invalidate(&self, path: &Path) {
+		let Some(file_id) = self.file_id(path) else {
+			return;
+		};
+		// the source is kept to update it incrementally
+		if let Some((time, _)) = self.sources.lock().unwrap().get_mut(&file_id) {
+			*time = SystemTime::UNIX_EPOCH;
+		}
+		self.files.lock().unwrap().remove(&file_id);
+	}
+
 	pub fn path(&self, file_id: FileId) -> typst::diag::FileResult<PathBuf> {
 		let path = if let Some(spec) = file_id.package() {
-			self.packages
-				.prepare_package(&spec, &mut Progress)?
-				.join(file_id.vpath().as_rootless_path())
+			self.package(spec)?.join(file_id.vpath().as_rootless_path())
 		} else {
 			self.root.join(file_id.vpath().as_rootless_path())
 		};
@@ -105,18 +209,51 @@ impl LtWorld {
 		Ok(path)
 	}
 
-	pub fn with_main(&self, main: PathBuf) -> LtWorldRunning {
-		let main = VirtualPath::new(
-			main.canonicalize()
-				.unwrap()
-				.strip_prefix(&self.root)
-				.unwrap(),
-		);
+	pub fn with_main(&self, main: PathBuf) -> LtWorldRunning<'_> {
+		let main = VirtualPath::new(absolute(&main).strip_prefix(&self.root).unwrap());
 		LtWorldRunning {
-			world: &self,
+			world: self,
 			main: FileId::new(None, main),
+			accessed: Mutex::new(HashSet::new()),
+			wrapper: None,
 		}
 	}
+
+	/// Compile the file inside the wrapper, for files which only compile when included by a document.
+	/// The wrapper is the main file with the [`WRAPPER_PLACEHOLDER`] replaced (`#include "{{path}}"`).
+	pub fn with_wrapper(&self, wrapper: &Path, path: &Path) -> FileResult<LtWorldRunning<'_>> {
+		let (Some(wrapper_id), Some(id)) = (self.file_id(wrapper), self.file_id(path)) else {
+			return Err(FileError::AccessDenied);
+		};
+		let template = self.read_source(wrapper_id, &self.path(wrapper_id)?)?;
+		let rooted = id
+			.vpath()
+			.as_rooted_path()
+			.to_string_lossy()
+			.replace('\\', "/");
+		let mut world = self.with_main(wrapper.to_owned());
+		world.wrapper = Some(Source::new(
+			wrapper_id,
+			template.text().replace(WRAPPER_PLACEHOLDER, &rooted),
+		));
+		Ok(world)
+	}
+
+	/// First of the main files whose document reads the file.
+	pub fn main_for(&self, mains: &[PathBuf], path: &Path) -> Option<PathBuf> {
+		let id = self.file_id(path)?;
+		mains
+			.iter()
+			.filter(|main| self.file_id(main).is_some())
+			.find(|main| {
+				let world = self.with_main(main.to_path_buf());
+				// failed documents still read their files
+				_ = world.compile();
+				let accessed = world.accessed.lock().unwrap();
+				accessed.contains(&id)
+			})
+			.cloned()
+	}
 }
 
 impl Deref for LtWorldRunning<'_> {
@@ -128,8 +265,26 @@ impl Deref for LtWorldRunning<'_> {
 }
 
 impl LtWorldRunning<'_> {
+	/// Compile the document, results for unchanged parts are reused from previous compilations.
 	pub fn compile(&self) -> SourceResult<Document> {
-		typst::compile(self).output
+		let document = typst::compile(self).output;
+		// drop results not used by the last compilations
+		comemo::evict(10);
+		document
+	}
+
+	/// Project files read for the document, files from packages are excluded.
+	pub fn dependencies(&self) -> Vec<PathBuf> {
+		let mut paths = self
+			.accessed
+			.lock()
+			.unwrap()
+			.iter()
+			.filter(|id| id.package().is_none())
+			.filter_map(|id| self.path(*id).ok())
+			.collect::<Vec<_>>();
+		paths.sort();
+		paths
 	}
 }
 
@@ -154,8 +309,8 @@ impl World for LtWorldRunning<'_> {
 		)
 	}
 
-	fn book(&self) -> &LazyHash<typst::text::FontBook> {
-		&self.font_book
+	fn book(&self) -> &LazyHash<FontBook> {
+		&self.fonts.get().0
 	}
 
 	fn main(&self) -> FileId {
@@ -163,38 +318,161 @@ impl World for LtWorldRunning<'_> {
 	}
 
 	fn source(&self, id: FileId) -> typst::diag::FileResult<typst::syntax::Source> {
+		self.accessed.lock().unwrap().insert(id);
+		if let Some(source) = self.wrapper.as_ref().filter(|_| id == self.main) {
+			return Ok(source.clone());
+		}
 		if let Some(source) = self.shadow_files.get(&id) {
 			return Ok(source.clone());
 		}
 
 		let path = self.path(id)?;
+		self.world.read_source(id, &path)
+	}
+
+	fn file(&self, id: FileId) -> FileResult<Bytes> {
+		self.accessed.lock().unwrap().insert(id);
+		let path = self.path(id)?;
+		self.world.read_file(id, &path)
+	}
+
+	fn font(&self, index: usize) -> Option<Font> {
+		self.fonts.get().1.get(index)?.get()
+	}
+}
+
+impl LtWorld {
+	/// Directory of the package, the package is downloaded if missing and not offline.
+	fn package(&self, spec: &PackageSpec) -> PackageResult<PathBuf> {
+		if self.offline.not() {
+			return self.packages.prepare_package(spec, &mut Progress(spec));
+		}
+		let subdir = format!("{}/{}/{}", spec.namespace, spec.name, spec.version);
+		[
+			self.packages.package_path(),
+			self.packages.package_cache_path(),
+		]
+		.into_iter()
+		.flatten()
+		.map(|dir| dir.join(&subdir))
+		.find(|dir| dir.exists())
+		.ok_or_else(|| {
+			PackageError::Other(Some(
+				format!(
+					"package {} is not downloaded and the offline mode is enabled",
+					spec
+				)
+				.into(),
+			))
+		})
+	}
+
+	/// Read the source if the file changed since the last read.
+	/// Changed sources are updated incrementally, so unchanged parts keep their spans.
+	fn read_source(&self, id: FileId, path: &Path) -> FileResult<Source> {
+		let modified = modified(path)?;
+		let mut sources = self.sources.lock().unwrap();
+		if let Some((time, source)) = sources.get(&id) {
+			if *time == modified {
+				return Ok(source.clone());
+			}
+		}
 
-		let Ok(text) = std::fs::read_to_string(&path) else {
-			return Err(FileError::NotFound(path));
+		let Ok(text) = std::fs::read_to_string(path) else {
+			return Err(FileError::NotFound(path.to_owned()));
+		};
+		let source = match sources.remove(&id) {
+			Some((_, mut source)) => {
+				source.replace(&text);
+				source
+			},
+			None => Source::new(id, text),
 		};
-		Ok(Source::new(id, text))
+		sources.insert(id, (modified, source.clone()));
+		Ok(source)
 	}
 
-	fn file(&self, id: FileId) -> FileResult<typst::foundations::Bytes> {
-		let path = self.path(id)?;
+	/// Read the file if it changed since the last read.
+	fn read_file(&self, id: FileId, path: &Path) -> FileResult<Bytes> {
+		let modified = modified(path)?;
+		let mut files = self.files.lock().unwrap();
+		if let Some((time, bytes)) = files.get(&id) {
+			if *time == modified {
+				return Ok(bytes.clone());
+			}
+		}
 
-		let Ok(bytes) = std::fs::read(&path) else {
-			return Err(FileError::NotFound(path));
+		let Ok(bytes) = std::fs::read(path) else {
+			return Err(FileError::NotFound(path.to_owned()));
 		};
-		Ok(bytes.into())
+		let bytes = Bytes::from(bytes);
+		files.insert(id, (modified, bytes.clone()));
+		Ok(bytes)
 	}
+}
 
-	fn font(&self, index: usize) -> Option<Font> {
-		self.fonts[index].get()
+fn modified(path: &Path) -> FileResult<SystemTime> {
+	std::fs::metadata(path)
+		.and_then(|metadata| metadata.modified())
+		.map_err(|_| FileError::NotFound(path.to_owned()))
+}
+
+/// Environment variable with the creation date as unix timestamp, used without the option.
+const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// Date from the option (`2024-05-01` or `2024-05-01T12:00:00Z`) or from `SOURCE_DATE_EPOCH`.
+pub fn creation_date(option: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+	if let Some(value) = option {
+		if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+			return Ok(Some(date.and_time(Default::default()).and_utc()));
+		}
+		return DateTime::parse_from_rfc3339(value)
+			.map(|date| Some(date.to_utc()))
+			.map_err(|err| format!("Invalid creation date '{}': {}", value, err));
 	}
+	let Ok(value) = std::env::var(SOURCE_DATE_EPOCH) else {
+		return Ok(None);
+	};
+	value
+		.parse::<i64>()
+		.ok()
+		.and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+		.map(Some)
+		.ok_or_else(|| format!("Invalid {} '{}'", SOURCE_DATE_EPOCH, value))
 }
 
-struct Progress;
+/// Log the download of the package.
+struct Progress<'a>(&'a PackageSpec);
 
-impl typst_kit::download::Progress for Progress {
-	fn print_start(&mut self) {}
+impl typst_kit::download::Progress for Progress<'_> {
+	fn print_start(&mut self) {
+		tracing::info!("Downloading package {}", self.0);
+	}
 
-	fn print_progress(&mut self, _state: &typst_kit::download::DownloadState) {}
+	fn print_progress(&mut self, state: &DownloadState) {
+		tracing::info!(
+			"Downloading package {}: {} of {} bytes",
+			self.0,
+			state.total_downloaded,
+			state
+				.content_len
+				.map_or_else(|| String::from("?"), |len| len.to_string())
+		);
+	}
+
+	fn print_finish(&mut self, state: &DownloadState) {
+		tracing::info!(
+			"Downloaded package {} ({} bytes in {:.2?})",
+			self.0,
+			state.total_downloaded,
+			state.start_time.elapsed()
+		);
+	}
+}
 
-	fn print_finish(&mut self, _state: &typst_kit::download::DownloadState) {}
+/// Canonical path, files only available as shadow files may not exist on disk.
+fn absolute(path: &Path) -> PathBuf {
+	path.canonicalize()
+		.or_else(|_| std::path::absolute(path))
+		.unwrap()
 }
@@ -0,0 +1,158 @@
+use std::{
+	collections::{hash_map::Entry, HashMap},
+	sync::Arc,
+};
+
+use crate::{
+	Cancellation, CheckMode, Language, LanguageTool, LanguageToolBackend, RuleInfo, Suggestion,
+};
+
+/// Checks the text with multiple backends and merges the results.
+#[derive(Debug)]
+pub struct LanguageToolComposite {
+	backends: Vec<LanguageTool>,
+}
+
+impl LanguageToolComposite {
+	pub fn new(backends: Vec<LanguageTool>) -> anyhow::Result<Self> {
+		if backends.is_empty() {
+			Err(anyhow::anyhow!(
+				"Composite backend requires atleast one backend."
+			))?;
+		}
+		Ok(Self { backends })
+	}
+
+	/// Check if all backends are reachable.
+	pub async fn ping(&mut self) -> anyhow::Result<()> {
+		for backend in &mut self.backends {
+			Box::pin(backend.ping()).await?;
+		}
+		Ok(())
+	}
+
+	/// See [`LanguageTool::take_fallback_switch`].
+	pub fn take_fallback_switch(&mut self) -> Option<String> {
+		self.backends
+			.iter_mut()
+			.find_map(LanguageTool::take_fallback_switch)
+	}
+}
+
+impl LanguageToolBackend for LanguageToolComposite {
+	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
+		for backend in &mut self.backends {
+			Box::pin(backend.allow_words(lang.clone(), words)).await?;
+		}
+		Ok(())
+	}
+
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		for backend in &mut self.backends {
+			Box::pin(backend.disable_checks(lang.clone(), checks)).await?;
+		}
+		Ok(())
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		for backend in &mut self.backends {
+			Box::pin(backend.set_check_mode(mode)).await?;
+		}
+		Ok(())
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		let mut results = Vec::with_capacity(self.backends.len());
+		for backend in &mut self.backends {
+			results.push(Box::pin(backend.check_text(lang.clone(), text, cancellation)).await?);
+		}
+		Ok(merge(results))
+	}
+
+	async fn check_texts(
+		&mut self,
+		texts: &[(String, String)],
+		jobs: usize,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+		let mut results = vec![Vec::with_capacity(self.backends.len()); texts.len()];
+		let start = cancellation.checked();
+		for backend in &mut self.backends {
+			// every backend checks the same texts, count them once
+			cancellation.reset_checked(start);
+			let checked = Box::pin(backend.check_texts(texts, jobs, cancellation)).await?;
+			for (result, suggestions) in results.iter_mut().zip(checked) {
+				result.push(suggestions);
+			}
+		}
+		Ok(results.into_iter().map(merge).collect())
+	}
+
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		let mut languages = Vec::<Language>::new();
+		for backend in &mut self.backends {
+			for language in Box::pin(backend.list_languages()).await? {
+				if languages
+					.iter()
+					.any(|existing| existing.long_code == language.long_code)
+				{
+					continue;
+				}
+				languages.push(language);
+			}
+		}
+		Ok(languages)
+	}
+
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		let mut rules = Vec::<RuleInfo>::new();
+		for backend in &mut self.backends {
+			for rule in Box::pin(backend.list_rules(lang.clone())).await? {
+				if rules.iter().any(|existing| existing.id == rule.id) {
+					continue;
+				}
+				rules.push(rule);
+			}
+		}
+		Ok(rules)
+	}
+}
+
+/// Merge the suggestions of the backends for the same text.
+fn merge(results: Vec<Vec<Suggestion>>) -> Vec<Suggestion> {
+	let mut suggestions = Vec::<Suggestion>::new();
+	let mut ranges = HashMap::<(usize, usize, Arc<str>), usize>::new();
+	for suggestion in results.into_iter().flatten() {
+		// suggestions for the same range and rule are merged into the first one,
+		// spelling rules from different backends count as the same rule
+		let rule = if suggestion.is_spelling() {
+			Arc::from("")
+		} else {
+			suggestion.rule_id.clone()
+		};
+		match ranges.entry((suggestion.start, suggestion.end, rule)) {
+			Entry::Occupied(entry) => {
+				let existing = &mut suggestions[*entry.get()];
+				let mut replacements = existing.replacements.to_vec();
+				for replacement in suggestion.replacements.iter() {
+					if replacements.contains(replacement) {
+						continue;
+					}
+					replacements.push(replacement.clone());
+				}
+				existing.replacements = replacements.into();
+			},
+			Entry::Vacant(entry) => {
+				entry.insert(suggestions.len());
+				suggestions.push(suggestion);
+			},
+		}
+	}
+	suggestions.sort_by_key(|suggestion| (suggestion.start, suggestion.end));
+	suggestions
+}
@@ -0,0 +1,467 @@
+use std::{
+	collections::{HashMap, HashSet},
+	ops::Not,
+	path::PathBuf,
+};
+
+use crate::{
+	Cancellation, CheckMode, Language, LanguageTool, LanguageToolBackend, RuleInfo, Suggestion,
+};
+
+/// Allows words without a language for every checked language.
+///
+/// The words are added before the first check of a language, because the languages
+/// of the document are only known while checking.
+#[derive(Debug)]
+pub struct LanguageToolDictionary {
+	inner: Box<LanguageTool>,
+	words: Vec<String>,
+	languages: HashSet<String>,
+}
+
+impl LanguageToolDictionary {
+	pub fn new(inner: LanguageTool, words: Vec<String>) -> Self {
+		Self {
+			inner: Box::new(inner),
+			words,
+			languages: HashSet::new(),
+		}
+	}
+
+	/// See [`LanguageTool::take_fallback_switch`].
+	pub fn take_fallback_switch(&mut self) -> Option<String> {
+		self.inner.take_fallback_switch()
+	}
+
+	/// Allow the words if the language is checked the first time.
+	async fn prepare(&mut self, lang: &str) -> anyhow::Result<()> {
+		if self.languages.contains(lang).not() {
+			Box::pin(self.inner.allow_words(lang.into(), &self.words)).await?;
+			self.languages.insert(lang.into());
+		}
+		Ok(())
+	}
+}
+
+impl LanguageToolBackend for LanguageToolDictionary {
+	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
+		Box::pin(self.inner.allow_words(lang, words)).await
+	}
+
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		Box::pin(self.inner.disable_checks(lang, checks)).await
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		Box::pin(self.inner.set_check_mode(mode)).await
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		self.prepare(&lang).await?;
+		Box::pin(self.inner.check_text(lang, text, cancellation)).await
+	}
+
+	async fn check_texts(
+		&mut self,
+		texts: &[(String, String)],
+		jobs: usize,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+		for (lang, _) in texts {
+			self.prepare(lang).await?;
+		}
+		Box::pin(self.inner.check_texts(texts, jobs, cancellation)).await
+	}
+
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		Box::pin(self.inner.list_languages()).await
+	}
+
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		Box::pin(self.inner.list_rules(lang)).await
+	}
+}
+
+/// Words for each language.
+pub type Words = HashMap<String, Vec<String>>;
+
+/// Prefix for entries matched without case.
+const IGNORE_CASE: &str = "(?i)";
+/// Suffix for entries matching words with any ending.
+const ANY_SUFFIX: char = '*';
+
+/// Check if the entry uses `(?i)` or `*`, plain words and phrases are supported by every backend.
+pub fn has_modifiers(entry: &str) -> bool {
+	entry.starts_with(IGNORE_CASE) || entry.ends_with(ANY_SUFFIX)
+}
+
+/// Allowed entries for backends which filter the suggestions themselves.
+///
+/// - `Typst`: the exact word
+/// - `(?i)Typst`: the word in any case
+/// - `Datenbank*`: words starting with `Datenbank`
+/// - `New York`: words in the phrase
+#[derive(Debug, Default, Clone)]
+pub(crate) struct AllowedWords {
+	words: HashSet<String>,
+	/// Lowercase words for entries with `(?i)`
+	lowercase_words: HashSet<String>,
+	/// Phrases and entries with `*`
+	patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+	/// In UTF-16, lowercase for `ignore_case`
+	text: Vec<u16>,
+	ignore_case: bool,
+	any_suffix: bool,
+}
+
+impl AllowedWords {
+	/// Add the entry, `ignore_case` applies `(?i)` to every entry.
+	pub fn insert(&mut self, entry: &str, ignore_case: bool) {
+		let (entry, ignore_case) = match entry.strip_prefix(IGNORE_CASE) {
+			Some(entry) => (entry, true),
+			None => (entry, ignore_case),
+		};
+		let (entry, any_suffix) = match entry.strip_suffix(ANY_SUFFIX) {
+			Some(entry) => (entry, true),
+			None => (entry, false),
+		};
+		if entry.is_empty() {
+			return;
+		}
+		let entry = if ignore_case {
+			lowercase_keep_offsets(entry)
+		} else {
+			entry.to_owned()
+		};
+		if any_suffix || entry.contains(char::is_whitespace) {
+			self.patterns.push(Pattern {
+				text: entry.encode_utf16().collect(),
+				ignore_case,
+				any_suffix,
+			});
+		} else if ignore_case {
+			self.lowercase_words.insert(entry);
+		} else {
+			self.words.insert(entry);
+		}
+	}
+
+	/// Check if the range (in UTF-16) of the text is an allowed word or part of an allowed phrase.
+	pub fn contains(&self, text: &[u16], start: usize, end: usize) -> bool {
+		let Some(range) = text.get(start..end) else {
+			return false;
+		};
+		if range.is_empty() {
+			return false;
+		}
+		let word = String::from_utf16_lossy(range);
+		if self.words.contains(&word) {
+			return true;
+		}
+		if self.lowercase_words.is_empty().not()
+			&& self
+				.lowercase_words
+				.contains(&lowercase_keep_offsets(&word))
+		{
+			return true;
+		}
+		self.patterns.iter().any(|pattern| {
+			let len = pattern.text.len();
+			// only the text around the range is compared, the suffix is only needed up to the end
+			let first = start.saturating_sub(len);
+			let last = (end + len).min(text.len());
+			let window = &text[first..last];
+			let lowercase;
+			let window = if pattern.ignore_case {
+				lowercase = lowercase_keep_offsets(&String::from_utf16_lossy(window))
+					.encode_utf16()
+					.collect::<Vec<_>>();
+				&lowercase
+			} else {
+				window
+			};
+			(first..=start).any(|pattern_start| {
+				let offset = pattern_start - first;
+				if window.get(offset..(offset + len)) != Some(pattern.text.as_slice()) {
+					return false;
+				}
+				let mut pattern_end = pattern_start + len;
+				if pattern.any_suffix {
+					pattern_end += window[(offset + len)..]
+						.iter()
+						.take_while(|&&c| is_word_unit(c))
+						.count();
+				}
+				pattern_end >= end
+			})
+		})
+	}
+}
+
+/// Letters, digits and surrogates, which are part of a word.
+fn is_word_unit(unit: u16) -> bool {
+	char::from_u32(unit.into()).is_none_or(char::is_alphanumeric)
+}
+
+/// Lowercase every char, if it does not change the UTF-16 length.
+fn lowercase_keep_offsets(text: &str) -> String {
+	text.chars()
+		.map(|c| {
+			let mut lower = c.to_lowercase();
+			match (lower.next(), lower.next()) {
+				(Some(l), None) if l.len_utf16() == c.len_utf16() => l,
+				_ => c,
+			}
+		})
+		.collect()
+}
+
+/// Read dictionary files with one word per line, optionally prefixed with the language (`en-US:word`).
+/// Empty lines and lines starting with `#` are ignored.
+/// Returns the words for each language and the words for all languages.
+#[cfg(feature = "fs")]
+pub fn load_files(files: &[PathBuf]) -> anyhow::Result<(Words, Vec<String>)> {
+	let mut dictionary = Words::new();
+	let mut all = Vec::new();
+	for file in files {
+		let content = std::fs::read_to_string(file).map_err(|err| {
+			anyhow::anyhow!("Failed to read dictionary {}: {}", file.display(), err)
+		})?;
+		for line in content.lines() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			match line.split_once(':') {
+				Some((lang, word)) if is_language(lang) && word.trim().is_empty().not() => {
+					dictionary
+						.entry(lang.into())
+						.or_default()
+						.push(word.trim().into());
+				},
+				_ => all.push(line.into()),
+			}
+		}
+	}
+	Ok((dictionary, all))
+}
+
+#[cfg(not(feature = "fs"))]
+pub fn load_files(files: &[PathBuf]) -> anyhow::Result<(Words, Vec<String>)> {
+	if files.is_empty().not() {
+		Err(anyhow::anyhow!(
+			"Feature 'fs' is disabled, 'dictionary_files' are not supported."
+		))?;
+	}
+	Ok((Words::new(), Vec::new()))
+}
+
+#[cfg(feature = "fs")]
+fn is_language(lang: &str) -> bool {
+	lang.is_empty().not() && lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Words from the dictionary files and the imported dictionaries.
+pub fn load(files: &[PathBuf], imports: &[PathBuf]) -> anyhow::Result<(Words, Vec<String>)> {
+	let (mut dictionary, mut all) = load_files(files)?;
+	let (imported, imported_all) = import_files(imports)?;
+	for (lang, words) in imported {
+		dictionary.entry(lang).or_default().extend(words);
+	}
+	all.extend(imported_all);
+	Ok((dictionary, all))
+}
+
+/// Read dictionaries of other tools:
+/// - Hunspell `.dic` files, the language is the file name (`en_US.dic`)
+/// - LTeX settings (`.json`) with `ltex.dictionary`, entries with `:` are read as external files
+/// - LTeX external files (`ltex.dictionary.en-US.txt`) with one word per line
+///
+/// Returns the words for each language and the words for all languages.
+#[cfg(feature = "fs")]
+pub fn import_files(files: &[PathBuf]) -> anyhow::Result<(Words, Vec<String>)> {
+	let mut dictionary = Words::new();
+	let mut all = Vec::new();
+	for file in files {
+		let bytes = std::fs::read(file).map_err(|err| {
+			anyhow::anyhow!("Failed to read dictionary {}: {}", file.display(), err)
+		})?;
+		let name = file
+			.file_name()
+			.and_then(|name| name.to_str())
+			.unwrap_or_default();
+		// the encoding of a Hunspell dictionary is declared in the affix file next to it
+		let encoding = if name.ends_with(".dic") {
+			hunspell_encoding(&std::fs::read(file.with_extension("aff")).unwrap_or_default())
+		} else {
+			String::from("UTF-8")
+		};
+		let content = decode(bytes, &encoding, file)?;
+		if let Some(stem) = name.strip_suffix(".dic") {
+			let words = hunspell_words(&content);
+			match hunspell_language(stem) {
+				Some(lang) => dictionary.entry(lang).or_default().extend(words),
+				None => all.extend(words),
+			}
+		} else if name.ends_with(".json") {
+			let settings = serde_json::from_str::<serde_json::Value>(&content).map_err(|err| {
+				anyhow::anyhow!("Invalid LTeX settings {}: {}", file.display(), err)
+			})?;
+			let directory = file.parent().unwrap_or(std::path::Path::new("."));
+			for (lang, words) in ltex_settings(&settings) {
+				let words = ltex_words(words, directory)?;
+				dictionary.entry(lang.clone()).or_default().extend(words);
+			}
+		} else {
+			let words = content
+				.lines()
+				.map(str::trim)
+				.filter(|word| word.is_empty().not())
+				.map(String::from);
+			let lang = name
+				.strip_prefix("ltex.dictionary.")
+				.and_then(|name| name.strip_suffix(".txt"));
+			match lang {
+				Some(lang) => dictionary.entry(lang.into()).or_default().extend(words),
+				None => all.extend(words),
+			}
+		}
+	}
+	Ok((dictionary, all))
+}
+
+#[cfg(not(feature = "fs"))]
+pub fn import_files(files: &[PathBuf]) -> anyhow::Result<(Words, Vec<String>)> {
+	if files.is_empty().not() {
+		Err(anyhow::anyhow!(
+			"Feature 'fs' is disabled, 'dictionary_imports' are not supported."
+		))?;
+	}
+	Ok((Words::new(), Vec::new()))
+}
+
+/// Words without the affix flags, the first line is the word count.
+#[cfg(feature = "fs")]
+fn hunspell_words(content: &str) -> impl Iterator<Item = String> + '_ {
+	content.lines().enumerate().filter_map(|(index, line)| {
+		let line = line.trim();
+		if index == 0 && line.parse::<usize>().is_ok() {
+			return None;
+		}
+		// morphological fields are separated with a tab
+		let word = line.split('\t').next()?.split('/').next()?.trim();
+		word.is_empty().not().then(|| word.into())
+	})
+}
+
+/// Encoding of the `SET` line in the Hunspell affix file, defaults to UTF-8.
+#[cfg(feature = "fs")]
+pub(crate) fn hunspell_encoding(aff: &[u8]) -> String {
+	aff.split(|byte| *byte == b'\n')
+		.find_map(|line| line.strip_prefix(b"SET"))
+		.map(|encoding| String::from_utf8_lossy(encoding).trim().to_owned())
+		.unwrap_or_else(|| String::from("UTF-8"))
+}
+
+/// Decode the file with the encoding of the `SET` line, only UTF-8, ISO8859-1 and ISO8859-15 are supported.
+#[cfg(feature = "fs")]
+pub(crate) fn decode(
+	bytes: Vec<u8>,
+	encoding: &str,
+	path: &std::path::Path,
+) -> anyhow::Result<String> {
+	match encoding.to_ascii_uppercase().replace(['-', '_'], "").as_str() {
+		"UTF8" => String::from_utf8(bytes).map_err(|err| {
+			anyhow::anyhow!("Invalid UTF-8 in {}: {}", path.display(), err)
+		}),
+		"ISO88591" | "LATIN1" => Ok(bytes.into_iter().map(char::from).collect()),
+		// ISO8859-15 replaces 8 chars of ISO8859-1
+		"ISO885915" | "LATIN9" => Ok(bytes
+			.into_iter()
+			.map(|byte| match byte {
+				0xA4 => '€',
+				0xA6 => 'Š',
+				0xA8 => 'š',
+				0xB4 => 'Ž',
+				0xB8 => 'ž',
+				0xBC => 'Œ',
+				0xBD => 'œ',
+				0xBE => 'Ÿ',
+				byte => char::from(byte),
+			})
+			.collect()),
+		_ => Err(anyhow::anyhow!(
+			"Unsupported encoding '{}' of {}, convert the dictionary to UTF-8 (`iconv -f {} -t UTF-8`) and change the `SET` line.",
+			encoding,
+			path.display(),
+			encoding
+		)),
+	}
+}
+
+/// Language code from the file name (`en_US` -> `en-US`), other names are used for all languages.
+#[cfg(feature = "fs")]
+fn hunspell_language(stem: &str) -> Option<String> {
+	let lang = stem.replace('_', "-");
+	let short = lang.split('-').next()?;
+	let valid = (2..=3).contains(&short.len())
+		&& short.chars().all(|c| c.is_ascii_lowercase())
+		&& is_language(&lang);
+	valid.then_some(lang)
+}
+
+/// Words for each language from `ltex.dictionary` in VS Code settings or the `dictionary` of the LTeX settings.
+#[cfg(feature = "fs")]
+fn ltex_settings(
+	settings: &serde_json::Value,
+) -> impl Iterator<Item = (&String, &serde_json::Value)> {
+	let dictionary = settings
+		.get("ltex.dictionary")
+		.or_else(|| settings.get("ltex")?.get("dictionary"))
+		.or_else(|| settings.get("dictionary"));
+	dictionary
+		.and_then(serde_json::Value::as_object)
+		.into_iter()
+		.flatten()
+}
+
+/// Entries starting with `:` are external files, entries starting with `-` remove words in LTeX and are skipped.
+#[cfg(feature = "fs")]
+fn ltex_words(
+	words: &serde_json::Value,
+	directory: &std::path::Path,
+) -> anyhow::Result<Vec<String>> {
+	let mut result = Vec::new();
+	for word in words
+		.as_array()
+		.into_iter()
+		.flatten()
+		.filter_map(|word| word.as_str())
+	{
+		if let Some(path) = word.strip_prefix(':') {
+			let path = directory.join(path);
+			let content = std::fs::read_to_string(&path).map_err(|err| {
+				anyhow::anyhow!("Failed to read dictionary {}: {}", path.display(), err)
+			})?;
+			result.extend(
+				content
+					.lines()
+					.map(str::trim)
+					.filter(|word| word.is_empty().not())
+					.map(String::from),
+			);
+		} else if word.starts_with('-').not() && word.is_empty().not() {
+			result.push(word.into());
+		}
+	}
+	Ok(result)
+}
@@ -0,0 +1,167 @@
+use crate::{
+	BackendOptions, Cancellation, Cancelled, CheckMode, Language, LanguageTool,
+	LanguageToolBackend, RuleInfo, Suggestion,
+};
+
+/// Switches to the fallback backend if the primary backend fails.
+#[derive(Debug)]
+pub struct LanguageToolFallback {
+	active: Box<LanguageTool>,
+	fallback: Option<BackendOptions>,
+	allowed_words: Vec<(String, Vec<String>)>,
+	disabled_checks: Vec<(String, Vec<String>)>,
+	check_mode: Option<CheckMode>,
+	/// Message for the switch to the fallback backend, until it is taken
+	switched: Option<String>,
+}
+
+impl LanguageToolFallback {
+	/// Uses the fallback directly if the primary backend could not be created or is unreachable.
+	pub async fn new(
+		primary: anyhow::Result<LanguageTool>,
+		fallback: BackendOptions,
+	) -> anyhow::Result<Self> {
+		let primary = match primary {
+			Ok(mut primary) => Box::pin(primary.ping()).await.map(|_| primary),
+			Err(err) => Err(err),
+		};
+		let lt = match primary {
+			Ok(primary) => Self {
+				active: Box::new(primary),
+				fallback: Some(fallback),
+				allowed_words: Vec::new(),
+				disabled_checks: Vec::new(),
+				check_mode: None,
+				switched: None,
+			},
+			Err(err) => {
+				tracing::debug!("Using fallback backend: {:?}", fallback);
+				Self {
+					active: Box::new(LanguageTool::from_backend(&fallback)?),
+					switched: Some(message(&err, &fallback)),
+					fallback: None,
+					allowed_words: Vec::new(),
+					disabled_checks: Vec::new(),
+					check_mode: None,
+				}
+			},
+		};
+		Ok(lt)
+	}
+
+	async fn switch(&mut self, err: anyhow::Error) -> anyhow::Result<()> {
+		let Some(fallback) = self.fallback.take() else {
+			return Err(err);
+		};
+		let mut lt = LanguageTool::from_backend(&fallback)?;
+		for (lang, words) in &self.allowed_words {
+			Box::pin(lt.allow_words(lang.clone(), words)).await?;
+		}
+		for (lang, checks) in &self.disabled_checks {
+			Box::pin(lt.disable_checks(lang.clone(), checks)).await?;
+		}
+		if let Some(mode) = self.check_mode {
+			Box::pin(lt.set_check_mode(mode)).await?;
+		}
+		tracing::debug!("Using fallback backend: {:?}", fallback);
+		self.switched = Some(message(&err, &fallback));
+		*self.active = lt;
+		Ok(())
+	}
+
+	/// Message for the switch to the fallback backend, only returned once.
+	pub fn take_switched(&mut self) -> Option<String> {
+		self.switched.take()
+	}
+}
+
+fn message(err: &anyhow::Error, fallback: &BackendOptions) -> String {
+	let name = serde_json::to_value(fallback)
+		.ok()
+		.and_then(|value| value.get("backend")?.as_str().map(String::from))
+		.unwrap_or_default();
+	format!(
+		"Backend failed ({}), using the fallback backend '{}'.",
+		err, name
+	)
+}
+
+impl LanguageToolBackend for LanguageToolFallback {
+	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
+		self.allowed_words.push((lang.clone(), words.to_vec()));
+		if let Err(err) = Box::pin(self.active.allow_words(lang.clone(), words)).await {
+			self.switch(err).await?;
+		}
+		Ok(())
+	}
+
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		self.disabled_checks.push((lang.clone(), checks.to_vec()));
+		if let Err(err) = Box::pin(self.active.disable_checks(lang.clone(), checks)).await {
+			self.switch(err).await?;
+		}
+		Ok(())
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		self.check_mode = Some(mode);
+		if let Err(err) = Box::pin(self.active.set_check_mode(mode)).await {
+			self.switch(err).await?;
+		}
+		Ok(())
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		match Box::pin(self.active.check_text(lang.clone(), text, cancellation)).await {
+			Ok(suggestions) => Ok(suggestions),
+			Err(err) if err.is::<Cancelled>() => Err(err),
+			Err(err) => {
+				self.switch(err).await?;
+				Box::pin(self.active.check_text(lang, text, cancellation)).await
+			},
+		}
+	}
+
+	async fn check_texts(
+		&mut self,
+		texts: &[(String, String)],
+		jobs: usize,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+		let start = cancellation.checked();
+		match Box::pin(self.active.check_texts(texts, jobs, cancellation)).await {
+			Ok(results) => Ok(results),
+			Err(err) if err.is::<Cancelled>() => Err(err),
+			Err(err) => {
+				self.switch(err).await?;
+				cancellation.reset_checked(start);
+				Box::pin(self.active.check_texts(texts, jobs, cancellation)).await
+			},
+		}
+	}
+
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		match Box::pin(self.active.list_languages()).await {
+			Ok(languages) => Ok(languages),
+			Err(err) => {
+				self.switch(err).await?;
+				Box::pin(self.active.list_languages()).await
+			},
+		}
+	}
+
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		match Box::pin(self.active.list_rules(lang.clone())).await {
+			Ok(rules) => Ok(rules),
+			Err(err) => {
+				self.switch(err).await?;
+				Box::pin(self.active.list_rules(lang)).await
+			},
+		}
+	}
+}
@@ -0,0 +1,470 @@
+use std::{
+	collections::{hash_map::Entry, HashMap, HashSet},
+	ops::Not,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use crate::{
+	backends::dictionary::{decode, has_modifiers, hunspell_encoding, AllowedWords},
+	Cancellation, CheckMode, Language, LanguageToolBackend, RuleInfo, Suggestion,
+};
+
+const MAX_REPLACEMENTS: usize = 5;
+
+#[derive(Debug)]
+pub struct LanguageToolHunspell {
+	directory: PathBuf,
+	languages: HashMap<String, Dictionary>,
+	check_mode: CheckMode,
+}
+
+impl LanguageToolHunspell {
+	pub fn new(directory: &Path) -> anyhow::Result<Self> {
+		if directory.is_dir().not() {
+			Err(anyhow::anyhow!(
+				"Hunspell directory {:?} does not exist.",
+				directory
+			))?;
+		}
+		Ok(Self {
+			directory: directory.to_owned(),
+			languages: HashMap::new(),
+			check_mode: CheckMode::All,
+		})
+	}
+
+	fn dictionary(&mut self, lang: String) -> anyhow::Result<&mut Dictionary> {
+		let dictionary = match self.languages.entry(lang) {
+			Entry::Occupied(entry) => entry.into_mut(),
+			Entry::Vacant(entry) => {
+				let dictionary = Dictionary::load(&self.directory, entry.key())?;
+				entry.insert(dictionary)
+			},
+		};
+		Ok(dictionary)
+	}
+}
+
+impl LanguageToolBackend for LanguageToolHunspell {
+	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
+		let dictionary = self.dictionary(lang)?;
+		for word in words {
+			// plain words are also used for the replacements
+			if has_modifiers(word) || word.contains(char::is_whitespace) {
+				dictionary.allowed.insert(word, false);
+			} else {
+				dictionary.words.insert(word.clone());
+			}
+		}
+		Ok(())
+	}
+
+	async fn disable_checks(&mut self, _lang: String, _checks: &[String]) -> anyhow::Result<()> {
+		Ok(())
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		self.check_mode = mode;
+		Ok(())
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		// only spelling rules
+		if self.check_mode == CheckMode::Grammar {
+			return Ok(Vec::new());
+		}
+		let dictionary = self.dictionary(lang)?;
+
+		let message = Arc::<str>::from("Possible spelling mistake found.");
+		let category = Arc::<str>::from("Possible Typo");
+		let rule_id = Arc::<str>::from("HUNSPELL_RULE");
+		let utf16 = text.encode_utf16().collect::<Vec<_>>();
+		let mut suggestions = Vec::new();
+		for (start, end, word) in words(text) {
+			cancellation.check()?;
+			if dictionary.contains(word) || dictionary.allowed.contains(&utf16, start, end) {
+				continue;
+			}
+			suggestions.push(Suggestion {
+				start,
+				end,
+				message: message.clone(),
+				replacements: dictionary.suggest(word).into(),
+				rule_description: category.clone(),
+				rule_id: rule_id.clone(),
+				category: category.clone(),
+			});
+		}
+		Ok(suggestions)
+	}
+
+	/// Every `<lang>.dic` in the directory.
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		let mut languages = Vec::new();
+		for entry in std::fs::read_dir(&self.directory)? {
+			let path = entry?.path();
+			if path.extension().is_some_and(|ext| ext == "dic").not() {
+				continue;
+			}
+			let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+				continue;
+			};
+			let long_code = name.replace('_', "-");
+			let code = long_code.split('-').next().unwrap_or_default().to_owned();
+			languages.push(Language { name: name.to_owned(), code, long_code });
+		}
+		languages.sort_by(|a, b| a.long_code.cmp(&b.long_code));
+		Ok(languages)
+	}
+
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		if self.check_mode == CheckMode::Grammar {
+			return Ok(Vec::new());
+		}
+		self.dictionary(lang)?;
+		Ok(vec![RuleInfo {
+			id: String::from("HUNSPELL_RULE"),
+			description: String::from("Possible Typo"),
+			category: String::from("Possible Typo"),
+		}])
+	}
+}
+
+/// Words with their positions in UTF-16 code units.
+fn words(text: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+	let mut chars = text.char_indices().peekable();
+	let mut offset = 0;
+	std::iter::from_fn(move || loop {
+		let (byte_start, c) = chars.next()?;
+		let start = offset;
+		offset += c.len_utf16();
+		if c.is_alphabetic().not() {
+			continue;
+		}
+		let mut byte_end = byte_start + c.len_utf8();
+		while let Some(&(idx, c)) = chars.peek() {
+			let inner = c == '\'' || c == '’' || c == '-';
+			if c.is_alphabetic().not() && inner.not() {
+				break;
+			}
+			chars.next();
+			offset += c.len_utf16();
+			byte_end = idx + c.len_utf8();
+		}
+		let word = text[byte_start..byte_end].trim_end_matches(['\'', '’', '-']);
+		// the trimmed `’` is one UTF-16 code unit but three bytes
+		let trimmed = text[byte_start + word.len()..byte_end]
+			.encode_utf16()
+			.count();
+		return Some((start, offset - trimmed, word));
+	})
+}
+
+#[derive(Debug)]
+struct Dictionary {
+	words: HashSet<String>,
+	try_chars: Vec<char>,
+	/// Allowed phrases and entries with modifiers
+	allowed: AllowedWords,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagType {
+	Single,
+	Long,
+	Numeric,
+}
+
+#[derive(Debug)]
+struct Affix {
+	prefix: bool,
+	cross_product: bool,
+	rules: Vec<AffixRule>,
+}
+
+#[derive(Debug)]
+struct AffixRule {
+	strip: String,
+	add: String,
+	condition: Vec<Condition>,
+}
+
+#[derive(Debug)]
+enum Condition {
+	Any,
+	Char(char),
+	Set(Vec<char>),
+	NotSet(Vec<char>),
+}
+
+impl Condition {
+	fn parse(text: &str) -> Vec<Self> {
+		if text == "." {
+			return Vec::new();
+		}
+		let mut conditions = Vec::new();
+		let mut chars = text.chars();
+		while let Some(c) = chars.next() {
+			let condition = match c {
+				'.' => Self::Any,
+				'[' => {
+					let mut set = Vec::new();
+					let mut negated = false;
+					for c in chars.by_ref() {
+						match c {
+							']' => break,
+							'^' if set.is_empty() && negated.not() => negated = true,
+							c => set.push(c),
+						}
+					}
+					if negated {
+						Self::NotSet(set)
+					} else {
+						Self::Set(set)
+					}
+				},
+				c => Self::Char(c),
+			};
+			conditions.push(condition);
+		}
+		conditions
+	}
+
+	fn matches(&self, c: char) -> bool {
+		match self {
+			Self::Any => true,
+			Self::Char(expected) => *expected == c,
+			Self::Set(set) => set.contains(&c),
+			Self::NotSet(set) => set.contains(&c).not(),
+		}
+	}
+}
+
+impl AffixRule {
+	fn apply(&self, word: &str, prefix: bool) -> Option<String> {
+		let chars = word.chars().collect::<Vec<_>>();
+		if chars.len() < self.condition.len() {
+			return None;
+		}
+		let part = if prefix {
+			&chars[..self.condition.len()]
+		} else {
+			&chars[(chars.len() - self.condition.len())..]
+		};
+		let matches = part
+			.iter()
+			.zip(&self.condition)
+			.all(|(&c, condition)| condition.matches(c));
+		if matches.not() {
+			return None;
+		}
+		if prefix {
+			let rest = word.strip_prefix(self.strip.as_str())?;
+			Some(format!("{}{}", self.add, rest))
+		} else {
+			let rest = word.strip_suffix(self.strip.as_str())?;
+			Some(format!("{}{}", rest, self.add))
+		}
+	}
+}
+
+impl Dictionary {
+	fn load(directory: &Path, lang: &str) -> anyhow::Result<Self> {
+		let candidates = [
+			lang.replace('-', "_"),
+			lang.to_owned(),
+			lang.split(['-', '_']).next().unwrap_or(lang).to_owned(),
+		];
+		let name = candidates
+			.iter()
+			.find(|name| directory.join(format!("{}.dic", name)).is_file())
+			.ok_or_else(|| {
+				anyhow::anyhow!(
+					"No hunspell dictionary for language '{}' in {:?}.",
+					lang,
+					directory
+				)
+			})?;
+
+		let aff_path = directory.join(format!("{}.aff", name));
+		let dic_path = directory.join(format!("{}.dic", name));
+		let aff = std::fs::read(&aff_path).unwrap_or_default();
+		// the encoding of both files is declared in the affix file
+		let encoding = hunspell_encoding(&aff);
+		let aff = decode(aff, &encoding, &aff_path)?;
+		let dic = decode(std::fs::read(&dic_path)?, &encoding, &dic_path)?;
+
+		let mut flag_type = FlagType::Single;
+		let mut try_chars = Vec::new();
+		let mut affixes = HashMap::<String, Affix>::new();
+		for line in aff.lines() {
+			let mut parts = line.split_whitespace();
+			match parts.next() {
+				Some("FLAG") => {
+					flag_type = match parts.next() {
+						Some("long") => FlagType::Long,
+						Some("num") => FlagType::Numeric,
+						_ => FlagType::Single,
+					}
+				},
+				Some("TRY") => try_chars = parts.next().unwrap_or_default().chars().collect(),
+				Some(kind @ ("PFX" | "SFX")) => {
+					let (Some(flag), Some(second), Some(third)) =
+						(parts.next(), parts.next(), parts.next())
+					else {
+						continue;
+					};
+					match affixes.entry(flag.to_owned()) {
+						Entry::Vacant(entry) => {
+							entry.insert(Affix {
+								prefix: kind == "PFX",
+								cross_product: second == "Y",
+								rules: Vec::new(),
+							});
+						},
+						Entry::Occupied(mut entry) => {
+							let strip = if second == "0" { "" } else { second };
+							let add = third.split('/').next().unwrap_or_default();
+							let add = if add == "0" { "" } else { add };
+							entry.get_mut().rules.push(AffixRule {
+								strip: strip.to_owned(),
+								add: add.to_owned(),
+								condition: Condition::parse(parts.next().unwrap_or(".")),
+							});
+						},
+					}
+				},
+				_ => {},
+			}
+		}
+
+		let mut words = HashSet::new();
+		for line in dic.lines().skip(1) {
+			let entry = line.split(['\t', ' ']).next().unwrap_or_default();
+			let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+			if word.is_empty() {
+				continue;
+			}
+			let flags = parse_flags(flags, flag_type);
+			let affixes = flags
+				.iter()
+				.filter_map(|flag| affixes.get(flag))
+				.collect::<Vec<_>>();
+
+			let mut prefixed = Vec::new();
+			for affix in affixes.iter().filter(|affix| affix.prefix) {
+				for rule in &affix.rules {
+					if let Some(form) = rule.apply(word, true) {
+						prefixed.push((form, affix.cross_product));
+					}
+				}
+			}
+			for affix in affixes.iter().filter(|affix| affix.prefix.not()) {
+				for rule in &affix.rules {
+					if let Some(form) = rule.apply(word, false) {
+						words.insert(form);
+					}
+					if affix.cross_product.not() {
+						continue;
+					}
+					for (form, _) in prefixed.iter().filter(|(_, cross)| *cross) {
+						if let Some(form) = rule.apply(form, false) {
+							words.insert(form);
+						}
+					}
+				}
+			}
+			words.extend(prefixed.into_iter().map(|(form, _)| form));
+			words.insert(word.to_owned());
+		}
+
+		Ok(Self {
+			words,
+			try_chars,
+			allowed: AllowedWords::default(),
+		})
+	}
+
+	fn contains(&self, word: &str) -> bool {
+		if self.words.contains(word) {
+			return true;
+		}
+		let mut chars = word.chars();
+		let Some(first) = chars.next() else {
+			return true;
+		};
+		if first.is_uppercase() {
+			let lower = first.to_lowercase().chain(chars).collect::<String>();
+			if self.words.contains(&lower) {
+				return true;
+			}
+		}
+		if word
+			.chars()
+			.all(|c| c.is_uppercase() || c.is_alphabetic().not())
+		{
+			let lower = word.to_lowercase();
+			return self.words.contains(&capitalize(&lower)) || self.words.contains(&lower);
+		}
+		false
+	}
+
+	fn suggest(&self, word: &str) -> Vec<String> {
+		let chars = word.chars().collect::<Vec<_>>();
+		let mut candidates = Vec::new();
+		let mut push = |candidate: String| {
+			if candidates.contains(&candidate).not() && self.words.contains(&candidate) {
+				candidates.push(candidate);
+			}
+		};
+		for i in 0..chars.len() {
+			if i + 1 < chars.len() {
+				let mut swapped = chars.clone();
+				swapped.swap(i, i + 1);
+				push(swapped.into_iter().collect());
+			}
+			let mut removed = chars.clone();
+			removed.remove(i);
+			push(removed.into_iter().collect());
+		}
+		for &c in &self.try_chars {
+			for i in 0..=chars.len() {
+				let mut inserted = chars.clone();
+				inserted.insert(i, c);
+				push(inserted.into_iter().collect());
+				if i < chars.len() {
+					let mut replaced = chars.clone();
+					replaced[i] = c;
+					push(replaced.into_iter().collect());
+				}
+			}
+		}
+		candidates.truncate(MAX_REPLACEMENTS);
+		candidates
+	}
+}
+
+fn capitalize(word: &str) -> String {
+	let mut chars = word.chars();
+	match chars.next() {
+		Some(first) => first.to_uppercase().chain(chars).collect(),
+		None => String::new(),
+	}
+}
+
+fn parse_flags(flags: &str, flag_type: FlagType) -> Vec<String> {
+	match flag_type {
+		FlagType::Single => flags.chars().map(String::from).collect(),
+		FlagType::Long => {
+			let chars = flags.chars().collect::<Vec<_>>();
+			chars.chunks(2).map(|c| c.iter().collect()).collect()
+		},
+		FlagType::Numeric => flags.split(',').map(String::from).collect(),
+	}
+}
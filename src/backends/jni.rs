@@ -1,48 +1,267 @@
-use std::{
-	collections::{hash_map::Entry, HashMap},
-	ops::Not,
-};
+use std::{collections::HashMap, ops::Not, sync::mpsc, thread::JoinHandle};
 
 use jni::{
 	objects::{GlobalRef, JObject, JValue},
 	InitArgsBuilder, JNIEnv, JavaVM,
 };
 
-use crate::{LanguageToolBackend, Suggestion};
+use crate::{
+	backends::dictionary::{has_modifiers, AllowedWords},
+	Cancellation, CheckMode, JniOptions, Language, LanguageToolBackend, RuleInfo, Suggestion,
+};
+
+type Job = Box<dyn FnOnce(&mut Worker, &mut JNIEnv) + Send>;
 
+/// The JNI calls are executed on a dedicated worker thread, to not block the async runtime.
 #[derive(Debug)]
 pub struct LanguageToolJNI {
-	jvm: JavaVM,
-	languages: HashMap<String, GlobalRef>,
+	sender: Option<mpsc::Sender<Job>>,
+	worker: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+struct Worker {
+	/// LanguageTool instance and last usage for every language
+	languages: HashMap<String, (GlobalRef, u64)>,
+	tick: u64,
+	/// Kept to recreate evicted languages
+	allowed_words: HashMap<String, Vec<String>>,
+	/// Entries with modifiers, which are not supported by LanguageTool
+	allowed_patterns: HashMap<String, AllowedWords>,
+	disabled_checks: HashMap<String, Vec<String>>,
+	check_mode: CheckMode,
+	options: JniOptions,
 }
 
-fn new_jvm(class_path: &str) -> anyhow::Result<JavaVM> {
-	let jvm_args = InitArgsBuilder::new()
+fn new_jvm(class_path: &str, options: &JniOptions) -> anyhow::Result<JavaVM> {
+	let mut jvm_args = InitArgsBuilder::new()
 		.version(jni::JNIVersion::V8)
-		.option(format!("-Djava.class.path={}", class_path))
-		.build()?;
+		.option(format!("-Djava.class.path={}", class_path));
+	if let Some(max_heap) = &options.max_heap {
+		jvm_args = jvm_args.option(format!("-Xmx{}", max_heap));
+	}
+	for option in &options.jvm_options {
+		jvm_args = jvm_args.option(option);
+	}
+	let jvm_args = jvm_args.build()?;
 	let jvm = JavaVM::new(jvm_args)?;
 	Ok(jvm)
 }
 
 impl LanguageToolJNI {
-	pub fn new(class_path: &str) -> anyhow::Result<Self> {
-		let jvm = new_jvm(class_path)?;
-		Ok(Self { languages: HashMap::new(), jvm })
+	pub fn new(class_path: &str, options: &JniOptions) -> anyhow::Result<Self> {
+		let jvm = new_jvm(class_path, options)?;
+		let mut worker = Worker {
+			languages: HashMap::new(),
+			tick: 0,
+			allowed_words: HashMap::new(),
+			allowed_patterns: HashMap::new(),
+			disabled_checks: HashMap::new(),
+			check_mode: CheckMode::All,
+			options: options.clone(),
+		};
+		let (sender, receiver) = mpsc::channel::<Job>();
+		let worker = std::thread::Builder::new()
+			.name("languagetool-jni".into())
+			.spawn(move || {
+				let mut env = match jvm.attach_current_thread_permanently() {
+					Ok(env) => env,
+					Err(err) => {
+						tracing::error!("Failed to attach JNI worker: {}", err);
+						return;
+					},
+				};
+				for job in receiver {
+					// free the local references after every job
+					let res = env.with_local_frame(16, |env| -> anyhow::Result<()> {
+						job(&mut worker, env);
+						Ok(())
+					});
+					if let Err(err) = res {
+						tracing::error!("JNI worker failed: {}", err);
+					}
+					// a pending exception fails every later call of the permanently attached thread
+					if env.exception_check().unwrap_or(false) {
+						tracing::warn!("Clearing the pending Java exception of the JNI worker");
+						_ = env.exception_describe();
+						_ = env.exception_clear();
+					}
+				}
+				for (lang_tool, _) in worker.languages.values() {
+					_ = env.call_method(lang_tool, "shutdown", "()V", &[]);
+				}
+			})?;
+		Ok(Self {
+			sender: Some(sender),
+			worker: Some(worker),
+		})
 	}
 
-	pub fn new_bundled() -> anyhow::Result<Self> {
+	pub fn new_bundled(options: &JniOptions) -> anyhow::Result<Self> {
 		#[cfg(feature = "bundle")]
 		let path = include!(concat!(env!("OUT_DIR"), "/jar_path.rs"));
 
 		#[cfg(not(feature = "bundle"))]
 		let path = Err(anyhow::anyhow!("Feature 'bundle-jar' not enabled."))?;
 
-		let jvm = new_jvm(path)?;
-		Ok(Self { languages: HashMap::new(), jvm })
+		Self::new(path, options)
+	}
+
+	async fn run<T: Send + 'static>(
+		&self,
+		f: impl FnOnce(&mut Worker, &mut JNIEnv) -> anyhow::Result<T> + Send + 'static,
+	) -> anyhow::Result<T> {
+		let (sender, receiver) = tokio::sync::oneshot::channel();
+		let job: Job = Box::new(move |worker, env| {
+			_ = sender.send(f(worker, env));
+		});
+		self.sender
+			.as_ref()
+			.and_then(|jobs| jobs.send(job).ok())
+			.ok_or_else(|| anyhow::anyhow!("JNI worker stopped."))?;
+		receiver
+			.await
+			.map_err(|_| anyhow::anyhow!("JNI worker stopped."))?
+	}
+}
+
+impl Worker {
+	fn lang_tool(&mut self, lang: String, env: &mut JNIEnv) -> anyhow::Result<&GlobalRef> {
+		self.tick += 1;
+		if self.languages.contains_key(&lang).not() {
+			if let Some(max_languages) = self.options.max_languages {
+				while self.languages.len() >= max_languages.max(1) {
+					self.evict(env);
+				}
+			}
+			let lang_tool = Self::create_lang_tool(lang.clone(), env, &self.options)?;
+			Self::apply_check_mode(&lang_tool, self.check_mode, env)?;
+			if let Some(words) = self.allowed_words.get(&lang) {
+				Self::accept_phrases(&lang_tool, words, env)?;
+			}
+			if let Some(checks) = self.disabled_checks.get(&lang) {
+				Self::disable_rules(&lang_tool, checks, env)?;
+			}
+			self.languages.insert(lang.clone(), (lang_tool, 0));
+		}
+		let (lang_tool, last_used) = self.languages.get_mut(&lang).unwrap();
+		*last_used = self.tick;
+		Ok(lang_tool)
+	}
+
+	/// Remove the least recently used language, the global reference is freed on drop.
+	fn evict(&mut self, env: &mut JNIEnv) {
+		let Some(lang) = self
+			.languages
+			.iter()
+			.min_by_key(|(_, (_, last_used))| *last_used)
+			.map(|(lang, _)| lang.clone())
+		else {
+			return;
+		};
+		if let Some((lang_tool, _)) = self.languages.remove(&lang) {
+			_ = env.call_method(&lang_tool, "shutdown", "()V", &[]);
+		}
+	}
+
+	/// Disable the spelling or the other rules.
+	fn apply_check_mode(
+		lang_tool: &JObject,
+		mode: CheckMode,
+		env: &mut JNIEnv,
+	) -> anyhow::Result<()> {
+		if mode == CheckMode::All {
+			return Ok(());
+		}
+		let rules = env
+			.call_method(lang_tool, "getAllActiveRules", "()Ljava/util/List;", &[])?
+			.l()?;
+		let list = env.get_list(&rules)?;
+		for i in 0..list.size(env)? {
+			let Some(rule) = list.get(env, i)? else {
+				continue;
+			};
+			let spelling =
+				env.is_instance_of(&rule, "org/languagetool/rules/spelling/SpellingCheckRule")?;
+			if spelling == (mode == CheckMode::Spelling) {
+				continue;
+			}
+			let id = env
+				.call_method(&rule, "getId", "()Ljava/lang/String;", &[])?
+				.l()?;
+			env.call_method(
+				lang_tool,
+				"disableRule",
+				"(Ljava/lang/String;)V",
+				&[JValue::Object(&id)],
+			)?;
+		}
+		Ok(())
+	}
+
+	fn accept_phrases(
+		lang_tool: &JObject,
+		words: &[String],
+		env: &mut JNIEnv,
+	) -> anyhow::Result<()> {
+		let rules = env
+			.call_method(lang_tool, "getAllActiveRules", "()Ljava/util/List;", &[])?
+			.l()?;
+		let list = env.get_list(&rules)?;
+		let args = env.new_object("java/util/ArrayList", "()V", &[])?;
+		let args = env.get_list(&args)?;
+		for word in words {
+			let word = env.new_string(word)?;
+			args.add(env, &word)?;
+		}
+
+		for i in 0..list.size(env)? {
+			let Some(rule) = list.get(env, i)? else {
+				continue;
+			};
+			if env
+				.is_instance_of(&rule, "org/languagetool/rules/spelling/SpellingCheckRule")?
+				.not()
+			{
+				continue;
+			}
+
+			env.call_method(
+				&rule,
+				"acceptPhrases",
+				"(Ljava/util/List;)V",
+				&[JValue::Object(args.as_ref())],
+			)?;
+		}
+		Ok(())
+	}
+
+	fn disable_rules(
+		lang_tool: &JObject,
+		checks: &[String],
+		env: &mut JNIEnv,
+	) -> anyhow::Result<()> {
+		let args = env.new_object("java/util/ArrayList", "()V", &[])?;
+		let args = env.get_list(&args)?;
+		for check in checks {
+			let check = env.new_string(check)?;
+			args.add(env, &check)?;
+		}
+		env.call_method(
+			lang_tool,
+			"disableRules",
+			"(Ljava/util/List;)V",
+			&[JValue::Object(args.as_ref())],
+		)?;
+		Ok(())
 	}
 
-	fn create_lang_tool(lang: String, env: &mut JNIEnv) -> anyhow::Result<GlobalRef> {
+	fn create_lang_tool(
+		lang: String,
+		env: &mut JNIEnv,
+		options: &JniOptions,
+	) -> anyhow::Result<GlobalRef> {
+		let rule_files = options.rule_files.get(&lang);
 		let lang_code = env.new_string(lang)?;
 		let lang = env.call_static_method(
 			"org/languagetool/Languages",
@@ -51,16 +270,115 @@ impl LanguageToolJNI {
 			&[JValue::Object(&lang_code)],
 		)?;
 
-		let lang_tool = env.new_object(
-			"org/languagetool/JLanguageTool",
-			"(Lorg/languagetool/Language;)V",
-			&[lang.borrow()],
-		)?;
+		let lang_tool = match (options.cache_size, options.threads) {
+			(Some(cache_size), threads) => {
+				Self::create_cached_lang_tool(&lang.l()?, cache_size, threads, env)?
+			},
+			(None, Some(threads)) => env.new_object(
+				"org/languagetool/MultiThreadedJLanguageTool",
+				"(Lorg/languagetool/Language;I)V",
+				&[lang.borrow(), JValue::Int(threads as i32)],
+			)?,
+			(None, None) => env.new_object(
+				"org/languagetool/MultiThreadedJLanguageTool",
+				"(Lorg/languagetool/Language;)V",
+				&[lang.borrow()],
+			)?,
+		};
+		if let Some(ngram_directory) = &options.ngram_directory {
+			let path = env.new_string(ngram_directory.to_string_lossy())?;
+			let file = env.new_object(
+				"java/io/File",
+				"(Ljava/lang/String;)V",
+				&[JValue::Object(&path)],
+			)?;
+			env.call_method(
+				&lang_tool,
+				"activateLanguageModelRules",
+				"(Ljava/io/File;)V",
+				&[JValue::Object(&file)],
+			)?;
+		}
+
+		for file in rule_files.into_iter().flatten() {
+			let file = env.new_string(file.to_string_lossy())?;
+			let rules = env
+				.call_method(
+					&lang_tool,
+					"loadPatternRules",
+					"(Ljava/lang/String;)Ljava/util/List;",
+					&[JValue::Object(&file)],
+				)?
+				.l()?;
+			let rules = env.get_list(&rules)?;
+			for i in 0..rules.size(env)? {
+				let Some(rule) = rules.get(env, i)? else {
+					continue;
+				};
+				env.call_method(
+					&lang_tool,
+					"addRule",
+					"(Lorg/languagetool/rules/Rule;)V",
+					&[JValue::Object(&rule)],
+				)?;
+			}
+		}
+
 		let lang_tool = env.new_global_ref(lang_tool)?;
 
 		Ok(lang_tool)
 	}
 
+	/// Older LanguageTool versions have no multithreaded constructor with a cache,
+	/// a single threaded instance is used instead.
+	fn create_cached_lang_tool<'a>(
+		lang: &JObject<'a>,
+		cache_size: u64,
+		threads: Option<usize>,
+		env: &mut JNIEnv<'a>,
+	) -> anyhow::Result<JObject<'a>> {
+		let cache = env.new_object(
+			"org/languagetool/ResultCache",
+			"(J)V",
+			&[JValue::Long(cache_size as i64)],
+		)?;
+		let threads = threads
+			.or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+			.unwrap_or(1);
+		let null = JObject::null();
+		let lang_tool = env.new_object(
+			"org/languagetool/MultiThreadedJLanguageTool",
+			"(Lorg/languagetool/Language;Lorg/languagetool/Language;ILorg/languagetool/ResultCache;Lorg/languagetool/GlobalConfig;Lorg/languagetool/UserConfig;Z)V",
+			&[
+				JValue::Object(lang),
+				JValue::Object(&null),
+				JValue::Int(threads as i32),
+				JValue::Object(&cache),
+				JValue::Object(&null),
+				JValue::Object(&null),
+				JValue::Bool(0),
+			],
+		);
+		match lang_tool {
+			Ok(lang_tool) => Ok(lang_tool),
+			Err(jni::errors::Error::MethodNotFound { .. })
+			| Err(jni::errors::Error::JavaException) => {
+				env.exception_clear()?;
+				let lang_tool = env.new_object(
+					"org/languagetool/JLanguageTool",
+					"(Lorg/languagetool/Language;Lorg/languagetool/Language;Lorg/languagetool/ResultCache;)V",
+					&[
+						JValue::Object(lang),
+						JValue::Object(&null),
+						JValue::Object(&cache),
+					],
+				)?;
+				Ok(lang_tool)
+			},
+			Err(err) => Err(err.into()),
+		}
+	}
+
 	fn lt_request<'a>(
 		lang_tool: &JObject<'a>,
 		text: &JObject<'a>,
@@ -90,7 +408,7 @@ impl LanguageToolJNI {
 			let message = env
 				.call_method(&m, "getMessage", "()Ljava/lang/String;", &[])?
 				.l()?;
-			let message = env.get_string(&message.into())?.into();
+			let message = String::from(env.get_string(&message.into())?).into();
 
 			let replacements = env
 				.call_method(&m, "getSuggestedReplacements", "()Ljava/util/List;", &[])?
@@ -112,19 +430,32 @@ impl LanguageToolJNI {
 			let rule_id = env
 				.call_method(&rule, "getId", "()Ljava/lang/String;", &[])?
 				.l()?;
-			let rule_id = env.get_string(&rule_id.into())?.into();
+			let rule_id = String::from(env.get_string(&rule_id.into())?).into();
 			let rule_description = env
 				.call_method(&rule, "getDescription", "()Ljava/lang/String;", &[])?
 				.l()?;
-			let rule_description = env.get_string(&rule_description.into())?.into();
+			let rule_description = String::from(env.get_string(&rule_description.into())?).into();
+			let category = env
+				.call_method(
+					&rule,
+					"getCategory",
+					"()Lorg/languagetool/rules/Category;",
+					&[],
+				)?
+				.l()?;
+			let category = env
+				.call_method(&category, "getName", "()Ljava/lang/String;", &[])?
+				.l()?;
+			let category = String::from(env.get_string(&category.into())?).into();
 
 			let suggestion = Suggestion {
 				start: start as usize,
 				end: end as usize,
-				replacements,
+				replacements: replacements.into(),
 				message,
 				rule_id,
 				rule_description,
+				category,
 			};
 			suggestions.push(suggestion);
 		}
@@ -132,75 +463,158 @@ impl LanguageToolJNI {
 	}
 }
 
+impl Drop for LanguageToolJNI {
+	fn drop(&mut self) {
+		// closing the channel stops the worker
+		drop(self.sender.take());
+		if let Some(worker) = self.worker.take() {
+			_ = worker.join();
+		}
+	}
+}
+
 impl LanguageToolBackend for LanguageToolJNI {
-	async fn check_text(&mut self, lang: String, text: &str) -> anyhow::Result<Vec<Suggestion>> {
-		let mut guard = self.jvm.attach_current_thread()?;
-		let text = guard.new_string(text)?;
-		let lang_tool = match self.languages.entry(lang.clone()) {
-			Entry::Occupied(entry) => entry.into_mut(),
-			Entry::Vacant(entry) => entry.insert(Self::create_lang_tool(lang, &mut guard)?),
-		};
-		let suggestions = Self::lt_request(lang_tool, &text, &mut guard)?;
-		Ok(suggestions)
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		let text = text.to_owned();
+		let job_cancellation = cancellation.clone();
+		// a running check can not be interrupted, but queued checks are skipped
+		let job = self.run(move |worker, env| {
+			job_cancellation.check()?;
+			let utf16 = text.encode_utf16().collect::<Vec<_>>();
+			let text = env.new_string(text)?;
+			let lang_tool = worker.lang_tool(lang.clone(), env)?;
+			let mut suggestions = Worker::lt_request(lang_tool, &text, env)?;
+			if let Some(allowed) = worker.allowed_patterns.get(&lang) {
+				suggestions.retain(|suggestion| {
+					allowed
+						.contains(&utf16, suggestion.start, suggestion.end)
+						.not()
+				});
+			}
+			Ok(suggestions)
+		});
+		cancellation.run(job).await
 	}
 
 	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
-		let mut guard = self.jvm.attach_current_thread()?;
-		let lang_tool = match self.languages.entry(lang.clone()) {
-			Entry::Occupied(entry) => entry.into_mut(),
-			Entry::Vacant(entry) => entry.insert(Self::create_lang_tool(lang, &mut guard)?),
-		};
+		let (patterns, words) = words
+			.iter()
+			.cloned()
+			.partition::<Vec<_>, _>(|word| has_modifiers(word));
+		self.run(move |worker, env| {
+			let lang_tool = worker.lang_tool(lang.clone(), env)?;
+			Worker::accept_phrases(lang_tool, &words, env)?;
+			let allowed = worker.allowed_patterns.entry(lang.clone()).or_default();
+			for pattern in &patterns {
+				allowed.insert(pattern, false);
+			}
+			worker.allowed_words.entry(lang).or_default().extend(words);
+			Ok(())
+		})
+		.await
+	}
 
-		let rules = guard
-			.call_method(lang_tool, "getAllActiveRules", "()Ljava/util/List;", &[])?
-			.l()?;
-		let list = guard.get_list(&rules)?;
-		let args = guard.new_object("java/util/ArrayList", "()V", &[])?;
-		let args = guard.get_list(&args)?;
-		for word in words {
-			let word = guard.new_string(word)?;
-			args.add(&mut guard, &word)?;
-		}
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		let checks = checks.to_vec();
+		self.run(move |worker, env| {
+			let lang_tool = worker.lang_tool(lang.clone(), env)?;
+			Worker::disable_rules(lang_tool, &checks, env)?;
+			worker
+				.disabled_checks
+				.entry(lang)
+				.or_default()
+				.extend(checks);
+			Ok(())
+		})
+		.await
+	}
 
-		for i in 0..list.size(&mut guard)? {
-			let Some(rule) = list.get(&mut guard, i)? else {
-				continue;
-			};
-			if guard
-				.is_instance_of(&rule, "org/languagetool/rules/spelling/SpellingCheckRule")?
-				.not()
-			{
-				continue;
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		self.run(move |worker, env| {
+			worker.check_mode = mode;
+			// recreated with the new mode on the next use
+			for (_, (lang_tool, _)) in worker.languages.drain() {
+				_ = env.call_method(&lang_tool, "shutdown", "()V", &[]);
 			}
+			Ok(())
+		})
+		.await
+	}
 
-			guard.call_method(
-				&rule,
-				"acceptPhrases",
-				"(Ljava/util/List;)V",
-				&[JValue::Object(args.as_ref())],
-			)?;
-		}
-		Ok(())
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		self.run(|_, env| {
+			let languages = env
+				.call_static_method(
+					"org/languagetool/Languages",
+					"get",
+					"()Ljava/util/List;",
+					&[],
+				)?
+				.l()?;
+			let list = env.get_list(&languages)?;
+			let size = list.size(env)?;
+			let mut languages = Vec::with_capacity(size as usize);
+			for i in 0..size {
+				let Some(lang) = list.get(env, i)? else {
+					continue;
+				};
+				let mut get = |method: &str| -> anyhow::Result<String> {
+					let value = env
+						.call_method(&lang, method, "()Ljava/lang/String;", &[])?
+						.l()?;
+					Ok(env.get_string(&value.into())?.into())
+				};
+				languages.push(Language {
+					name: get("getName")?,
+					code: get("getShortCode")?,
+					long_code: get("getShortCodeWithCountryAndVariant")?,
+				});
+			}
+			Ok(languages)
+		})
+		.await
 	}
 
-	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
-		let mut guard = self.jvm.attach_current_thread()?;
-		let args = guard.new_object("java/util/ArrayList", "()V", &[])?;
-		let args = guard.get_list(&args)?;
-		for check in checks {
-			let check = guard.new_string(check)?;
-			args.add(&mut guard, &check)?;
-		}
-		let lang_tool = match self.languages.entry(lang.clone()) {
-			Entry::Occupied(entry) => entry.into_mut(),
-			Entry::Vacant(entry) => entry.insert(Self::create_lang_tool(lang, &mut guard)?),
-		};
-		guard.call_method(
-			lang_tool,
-			"disableRules",
-			"(Ljava/util/List;)V",
-			&[JValue::Object(args.as_ref())],
-		)?;
-		Ok(())
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		self.run(move |worker, env| {
+			let lang_tool = worker.lang_tool(lang, env)?;
+			let rules = env
+				.call_method(lang_tool, "getAllActiveRules", "()Ljava/util/List;", &[])?
+				.l()?;
+			let list = env.get_list(&rules)?;
+			let size = list.size(env)?;
+			let mut rules = Vec::with_capacity(size as usize);
+			for i in 0..size {
+				let Some(rule) = list.get(env, i)? else {
+					continue;
+				};
+				let category = env
+					.call_method(
+						&rule,
+						"getCategory",
+						"()Lorg/languagetool/rules/Category;",
+						&[],
+					)?
+					.l()?;
+				let mut get = |object: &JObject, method: &str| -> anyhow::Result<String> {
+					let value = env
+						.call_method(object, method, "()Ljava/lang/String;", &[])?
+						.l()?;
+					Ok(env.get_string(&value.into())?.into())
+				};
+				rules.push(RuleInfo {
+					id: get(&rule, "getId")?,
+					description: get(&rule, "getDescription")?,
+					category: get(&category, "getName")?,
+				});
+			}
+			Ok(rules)
+		})
+		.await
 	}
 }
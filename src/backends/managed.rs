@@ -0,0 +1,140 @@
+use std::{
+	net::TcpListener,
+	process::{Child, Command, Stdio},
+	time::{Duration, Instant},
+};
+
+use crate::{
+	remote::LanguageToolRemote, Cancellation, CheckMode, Language, LanguageToolBackend, RuleInfo,
+	Suggestion,
+};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// LanguageTool server started and stopped by typst-languagetool.
+#[derive(Debug)]
+pub struct LanguageToolManaged {
+	child: Child,
+	remote: LanguageToolRemote,
+	ready: bool,
+}
+
+impl LanguageToolManaged {
+	pub fn new(
+		server_location: &str,
+		port: Option<u16>,
+		java: Option<&str>,
+	) -> anyhow::Result<Self> {
+		let port = match port {
+			Some(port) => port,
+			None => TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port(),
+		};
+		let java = java.unwrap_or("java");
+		let child = Command::new(java)
+			.arg("-cp")
+			.arg(server_location)
+			.arg("org.languagetool.server.HTTPServer")
+			.arg("--port")
+			.arg(port.to_string())
+			.stdin(Stdio::null())
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.spawn()
+			.map_err(|err| anyhow::anyhow!("Failed to execute \"{}\": {}", java, err))?;
+
+		let remote = LanguageToolRemote::new(
+			"http://127.0.0.1",
+			&port.to_string(),
+			None,
+			0,
+			false,
+			None,
+			false,
+		)?;
+		Ok(Self { child, remote, ready: false })
+	}
+
+	/// Wait for the startup of the server and check if it is still reachable.
+	pub async fn ping(&mut self) -> anyhow::Result<()> {
+		self.wait_ready().await?;
+		self.remote.ping().await
+	}
+
+	async fn wait_ready(&mut self) -> anyhow::Result<()> {
+		if self.ready {
+			return Ok(());
+		}
+		let start = Instant::now();
+		loop {
+			if let Some(status) = self.child.try_wait()? {
+				Err(anyhow::anyhow!(
+					"LanguageTool server exited with {} during startup.",
+					status
+				))?;
+			}
+			if self.remote.ping().await.is_ok() {
+				self.ready = true;
+				return Ok(());
+			}
+			if start.elapsed() > STARTUP_TIMEOUT {
+				Err(anyhow::anyhow!(
+					"LanguageTool server did not start within {:?}.",
+					STARTUP_TIMEOUT
+				))?;
+			}
+			tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+		}
+	}
+}
+
+impl Drop for LanguageToolManaged {
+	fn drop(&mut self) {
+		_ = self.child.kill();
+		_ = self.child.wait();
+	}
+}
+
+impl LanguageToolBackend for LanguageToolManaged {
+	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
+		self.remote.allow_words(lang, words).await
+	}
+
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		self.remote.disable_checks(lang, checks).await
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		self.remote.set_check_mode(mode).await
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		cancellation.run(self.wait_ready()).await?;
+		self.remote.check_text(lang, text, cancellation).await
+	}
+
+	async fn check_texts(
+		&mut self,
+		texts: &[(String, String)],
+		jobs: usize,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+		cancellation.run(self.wait_ready()).await?;
+		self.remote.check_texts(texts, jobs, cancellation).await
+	}
+
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		self.wait_ready().await?;
+		self.remote.list_languages().await
+	}
+
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		self.wait_ready().await?;
+		self.remote.list_rules(lang).await
+	}
+}
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, ops::Not};
+
+use crate::{
+	backends::dictionary::AllowedWords, Cancellation, CheckMode, Language, LanguageToolBackend,
+	RuleInfo, Suggestion,
+};
+
+/// Returns scripted suggestions, to test integrations without a JVM or server.
+///
+/// Suggestions for disabled rules and allowed words are removed like in the real backends.
+#[derive(Debug, Default)]
+pub struct LanguageToolMock {
+	responses: HashMap<String, Vec<Suggestion>>,
+	languages: Vec<Language>,
+	rules: Vec<RuleInfo>,
+	checked: Vec<(String, String)>,
+	allowed_words: HashMap<String, Vec<String>>,
+	disabled_checks: HashMap<String, Vec<String>>,
+	check_mode: CheckMode,
+}
+
+impl LanguageToolMock {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Suggestions for the exact text, other texts have no suggestions.
+	pub fn with_response(mut self, text: impl Into<String>, suggestions: Vec<Suggestion>) -> Self {
+		self.responses.insert(text.into(), suggestions);
+		self
+	}
+
+	pub fn with_languages(mut self, languages: Vec<Language>) -> Self {
+		self.languages = languages;
+		self
+	}
+
+	pub fn with_rules(mut self, rules: Vec<RuleInfo>) -> Self {
+		self.rules = rules;
+		self
+	}
+
+	/// Checked texts with their language in order.
+	pub fn checked(&self) -> &[(String, String)] {
+		&self.checked
+	}
+
+	pub fn allowed_words(&self, lang: &str) -> &[String] {
+		self.allowed_words.get(lang).map_or(&[], Vec::as_slice)
+	}
+
+	pub fn disabled_checks(&self, lang: &str) -> &[String] {
+		self.disabled_checks.get(lang).map_or(&[], Vec::as_slice)
+	}
+}
+
+impl LanguageToolBackend for LanguageToolMock {
+	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
+		self.allowed_words
+			.entry(lang)
+			.or_default()
+			.extend_from_slice(words);
+		Ok(())
+	}
+
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		self.disabled_checks
+			.entry(lang)
+			.or_default()
+			.extend_from_slice(checks);
+		Ok(())
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		self.check_mode = mode;
+		Ok(())
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		_cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		self.checked.push((lang.clone(), text.to_owned()));
+		let Some(suggestions) = self.responses.get(text) else {
+			return Ok(Vec::new());
+		};
+		let utf16 = text.encode_utf16().collect::<Vec<_>>();
+		let mut allowed = AllowedWords::default();
+		for word in self.allowed_words(&lang) {
+			allowed.insert(word, false);
+		}
+		let suggestions = suggestions
+			.iter()
+			.filter(|suggestion| self.check_mode.includes(suggestion))
+			.filter(|suggestion| {
+				self.disabled_checks(&lang)
+					.iter()
+					.all(|check| check.as_str() != &*suggestion.rule_id)
+			})
+			.filter(|suggestion| {
+				let allowed = allowed.contains(&utf16, suggestion.start, suggestion.end);
+				(suggestion.is_spelling() && allowed).not()
+			})
+			.cloned()
+			.collect();
+		Ok(suggestions)
+	}
+
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		Ok(self.languages.clone())
+	}
+
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		let rules = self
+			.rules
+			.iter()
+			.filter(|rule| self.disabled_checks(&lang).contains(&rule.id).not())
+			.cloned()
+			.collect();
+		Ok(rules)
+	}
+}
@@ -1,5 +1,21 @@
+pub mod composite;
+pub mod dictionary;
+pub mod fallback;
+pub mod rules;
+#[cfg(feature = "fs")]
+pub mod vale;
+
 #[cfg(any(feature = "bundle", feature = "jar"))]
 pub mod jni;
 
-#[cfg(feature = "server")]
+#[cfg(feature = "remote")]
 pub mod remote;
+
+#[cfg(feature = "server")]
+pub mod managed;
+
+#[cfg(feature = "hunspell")]
+pub mod hunspell;
+
+#[cfg(feature = "mock")]
+pub mod mock;
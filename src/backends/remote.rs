@@ -1,46 +1,241 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+	collections::{HashMap, HashSet},
+	ops::Not,
+	sync::Mutex,
+	time::Duration,
+};
 
-use languagetool_rust::{check::Match, CheckRequest, ServerClient};
+use futures_util::{StreamExt, TryStreamExt};
+use languagetool_rust::{
+	words::{LoginArgs, WordsAddRequest, WordsDeleteRequest, WordsResponse},
+	CheckRequest, ServerClient,
+};
 
-use crate::{LanguageToolBackend, Suggestion};
+use crate::{
+	backends::dictionary::{has_modifiers, AllowedWords},
+	Cancellation, Cancelled, CheckMode, Language, LanguageToolBackend, RuleInfo, Suggestion,
+};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Idle connections are kept open for the next check.
+#[cfg(not(target_arch = "wasm32"))]
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+#[cfg(not(target_arch = "wasm32"))]
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+/// LanguageTool category with the spelling rules
+const TYPOS_CATEGORY: &str = "TYPOS";
+/// Words listed with one request to the server dictionary
+const WORDS_PAGE: usize = 500;
 
 #[derive(Debug)]
 pub struct LanguageToolRemote {
 	server_client: ServerClient,
 	disabled_categories: HashMap<String, Vec<String>>,
-	allowed_words: HashMap<String, HashSet<String>>,
+	allowed_words: HashMap<String, AllowedWords>,
+	ignore_case: bool,
+	retries: usize,
+	continue_on_error: bool,
+	login: Option<LoginArgs>,
+	/// Server dictionaries for the languages, only with `login`
+	server_words: HashMap<String, ServerWords>,
+	/// Project dictionaries on the server, only these are cleaned
+	dictionary: Option<String>,
+	check_mode: CheckMode,
+}
+
+/// Words of a server dictionary and the words allowed by the options.
+#[derive(Debug, Default)]
+struct ServerWords {
+	/// Words in the server dictionary
+	stored: HashSet<String>,
+	/// Words allowed by the options
+	allowed: HashSet<String>,
+	/// Stored words which are not allowed are deleted from project dictionaries before the first check of the language
+	cleaned: bool,
+}
+
+/// Clients for each timeout, shared by all backends, so the connections are reused after a reload.
+static CLIENTS: Mutex<Vec<(Option<Duration>, reqwest::Client)>> = Mutex::new(Vec::new());
+
+/// Pooled client with keep-alive, HTTP/2 is used if the server supports it.
+fn client(timeout: Option<Duration>) -> anyhow::Result<reqwest::Client> {
+	let mut clients = CLIENTS.lock().unwrap();
+	if let Some((_, client)) = clients.iter().find(|(other, _)| *other == timeout) {
+		return Ok(client.clone());
+	}
+	let mut builder = reqwest::Client::builder();
+	#[cfg(not(target_arch = "wasm32"))]
+	{
+		builder = builder
+			.pool_idle_timeout(POOL_IDLE_TIMEOUT)
+			.tcp_keepalive(TCP_KEEPALIVE)
+			.http2_adaptive_window(true);
+	}
+	if let Some(timeout) = timeout {
+		builder = builder.timeout(timeout);
+	}
+	let client = builder.build()?;
+	clients.push((timeout, client.clone()));
+	Ok(client)
 }
 
 impl LanguageToolRemote {
-	pub fn new(hostname: &str, port: &str) -> anyhow::Result<Self> {
-		let server_client = ServerClient::new(hostname, port);
+	pub fn new(
+		hostname: &str,
+		port: &str,
+		timeout: Option<Duration>,
+		retries: usize,
+		continue_on_error: bool,
+		login: Option<(&str, &str)>,
+		ignore_case: bool,
+	) -> anyhow::Result<Self> {
+		let mut server_client = ServerClient::new(hostname, port);
+		server_client.client = client(timeout)?;
+		let login = login.map(|(username, api_key)| {
+			let mut login = LoginArgs::default();
+			login.username = username.into();
+			login.api_key = api_key.into();
+			login
+		});
 		Ok(Self {
 			server_client,
 			disabled_categories: HashMap::new(),
 			allowed_words: HashMap::new(),
+			ignore_case,
+			retries,
+			continue_on_error,
+			login,
+			server_words: HashMap::new(),
+			dictionary: None,
+			check_mode: CheckMode::All,
 		})
 	}
-}
 
-impl LanguageToolBackend for LanguageToolRemote {
-	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
-		self.allowed_words
-			.entry(lang)
-			.or_default()
-			.extend(words.iter().map(Clone::clone));
-		Ok(())
+	/// Use the project dictionaries on the server, words which are not in the options are deleted from them.
+	pub fn with_dictionary(mut self, dictionary: Option<String>) -> Self {
+		self.dictionary = dictionary;
+		self
 	}
 
-	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
-		self.disabled_categories.insert(lang, checks.to_vec());
-		Ok(())
+	/// Server dictionaries are global, use one dictionary per language and project.
+	fn dictionary_name(&self, lang: &str) -> String {
+		match &self.dictionary {
+			Some(dictionary) => format!("typst-languagetool-{}-{}", dictionary, lang),
+			None => format!("typst-languagetool-{}", lang),
+		}
 	}
 
-	async fn check_text(
+	/// Server dictionary for the language, listed from the server on the first use.
+	async fn server_words(
+		&mut self,
+		login: &LoginArgs,
+		lang: &str,
+	) -> anyhow::Result<&mut ServerWords> {
+		if self.server_words.contains_key(lang).not() {
+			let mut stored = HashSet::new();
+			loop {
+				// the request of the client can not set the offset
+				let response = self
+					.server_client
+					.client
+					.get(format!("{}/words", self.server_client.api))
+					.query(&[
+						("username", login.username.as_str()),
+						("apiKey", login.api_key.as_str()),
+						("dicts", &self.dictionary_name(lang)),
+						("offset", &stored.len().to_string()),
+						("limit", &WORDS_PAGE.to_string()),
+					])
+					.send()
+					.await?
+					.error_for_status()?
+					.json::<WordsResponse>()
+					.await?;
+				let count = response.words.len();
+				stored.extend(response.words);
+				if count < WORDS_PAGE {
+					break;
+				}
+			}
+			self.server_words.insert(
+				lang.to_owned(),
+				ServerWords { stored, ..Default::default() },
+			);
+		}
+		Ok(self.server_words.get_mut(lang).unwrap())
+	}
+
+	/// Delete the words of the project dictionaries, which were removed from the options.
+	/// The shared dictionaries may contain words of other projects and are never cleaned.
+	async fn clean_server_words<'a>(
 		&mut self,
+		langs: impl IntoIterator<Item = &'a String>,
+	) -> anyhow::Result<()> {
+		let Some(login) = self.login.clone() else {
+			return Ok(());
+		};
+		if self.dictionary.is_none() {
+			return Ok(());
+		}
+		for lang in langs {
+			let words = self.server_words(&login, lang).await?;
+			if words.cleaned {
+				continue;
+			}
+			words.cleaned = true;
+			let removed = words
+				.stored
+				.difference(&words.allowed)
+				.cloned()
+				.collect::<Vec<_>>();
+			for word in removed {
+				let mut req = WordsDeleteRequest::default();
+				req.word = word.clone();
+				req.login = login.clone();
+				req.dict = Some(self.dictionary_name(lang));
+				self.server_client.words_delete(&req).await?;
+				if let Some(words) = self.server_words.get_mut(lang) {
+					words.stored.remove(&word);
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Send the request, retry with exponential backoff on failure.
+	async fn request(
+		&self,
+		req: &CheckRequest,
+	) -> anyhow::Result<languagetool_rust::CheckResponse> {
+		let mut backoff = INITIAL_BACKOFF;
+		let mut attempt = 0;
+		loop {
+			match self.server_client.check(req).await {
+				Ok(response) => return Ok(response),
+				Err(err) if attempt < self.retries => {
+					attempt += 1;
+					tracing::warn!(
+						"Request failed ({}), retry {}/{} in {:?}",
+						err,
+						attempt,
+						self.retries,
+						backoff
+					);
+					tokio::time::sleep(backoff).await;
+					backoff *= 2;
+				},
+				Err(err) => return Err(err.into()),
+			}
+		}
+	}
+
+	/// Only borrows the backend, so multiple checks can run at once.
+	async fn check(
+		&self,
 		lang: String,
 		text: &str,
-	) -> anyhow::Result<Vec<crate::Suggestion>> {
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
 		let disabled_rules = self.disabled_categories.get(&lang).cloned();
 		let allowed = self.allowed_words.get(&lang);
 
@@ -48,22 +243,48 @@ impl LanguageToolBackend for LanguageToolRemote {
 			.with_text(String::from(text))
 			.with_language(lang);
 		req.disabled_rules = disabled_rules;
+		match self.check_mode {
+			CheckMode::All => {},
+			CheckMode::Spelling => {
+				req.enabled_categories = Some(vec![String::from(TYPOS_CATEGORY)]);
+				req.enabled_only = true;
+			},
+			CheckMode::Grammar => {
+				req.disabled_categories = Some(vec![String::from(TYPOS_CATEGORY)]);
+			},
+		}
+		if let Some(login) = &self.login {
+			req.username = Some(login.username.clone());
+			req.api_key = Some(login.api_key.clone());
+			req.dicts = Some(vec![self.dictionary_name(&req.language)]);
+		}
 
-		let response = self.server_client.check(&req).await?;
+		// dropping the request aborts it
+		let response = match cancellation.run(self.request(&req)).await {
+			Ok(response) => response,
+			Err(err) if self.continue_on_error && err.is::<Cancelled>().not() => {
+				tracing::warn!("Skipping chunk: {}", err);
+				return Ok(Vec::new());
+			},
+			Err(err) => return Err(err),
+		};
 
+		let text = text.encode_utf16().collect::<Vec<_>>();
 		let mut suggestions = Vec::with_capacity(response.matches.len());
 		for m in response.matches {
 			if let Some(allowed) = allowed {
-				if filter_match(&m, allowed) {
+				if allowed.contains(&text, m.offset, m.offset + m.length) {
 					continue;
 				}
 			}
 			let suggestion = Suggestion {
+				// offsets of LanguageTool are in Java chars (UTF-16 code units)
 				start: m.offset,
 				end: m.offset + m.length,
-				message: m.message,
-				rule_description: m.rule.description,
-				rule_id: m.rule.id,
+				message: m.message.into(),
+				rule_description: m.rule.description.into(),
+				rule_id: m.rule.id.into(),
+				category: m.rule.category.name.into(),
 				replacements: m.replacements.into_iter().map(|x| x.value).collect(),
 			};
 			suggestions.push(suggestion);
@@ -71,19 +292,139 @@ impl LanguageToolBackend for LanguageToolRemote {
 
 		Ok(suggestions)
 	}
+
+	pub async fn ping(&self) -> anyhow::Result<()> {
+		self.server_client.ping().await?;
+		Ok(())
+	}
+}
+
+impl LanguageToolBackend for LanguageToolRemote {
+	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
+		if let Some(login) = self.login.clone() {
+			// the server dictionary does not support phrases and modifiers
+			for word in words.iter().filter(|word| {
+				word.contains(char::is_whitespace).not() && has_modifiers(word).not()
+			}) {
+				let server_words = self.server_words(&login, &lang).await?;
+				server_words.allowed.insert(word.clone());
+				if server_words.stored.contains(word) {
+					continue;
+				}
+				let mut req = WordsAddRequest::default();
+				req.word = word.clone();
+				req.login = login.clone();
+				req.dict = Some(self.dictionary_name(&lang));
+				self.server_client.words_add(&req).await?;
+				if let Some(server_words) = self.server_words.get_mut(&lang) {
+					server_words.stored.insert(word.clone());
+				}
+			}
+		}
+		let dictionary = self.allowed_words.entry(lang).or_default();
+		for word in words {
+			dictionary.insert(word, self.ignore_case);
+		}
+		Ok(())
+	}
+
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		self.disabled_categories.insert(lang, checks.to_vec());
+		Ok(())
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		self.check_mode = mode;
+		Ok(())
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		self.clean_server_words([&lang]).await?;
+		self.check(lang, text, cancellation).await
+	}
+
+	async fn check_texts(
+		&mut self,
+		texts: &[(String, String)],
+		jobs: usize,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+		self.clean_server_words(texts.iter().map(|(lang, _)| lang))
+			.await?;
+		// the requests share the connection pool of the client
+		futures_util::stream::iter(texts)
+			.map(|(lang, text)| self.check(lang.clone(), text, cancellation))
+			.buffered(jobs.max(1))
+			.inspect_ok(|_| cancellation.add_checked())
+			.try_collect()
+			.await
+	}
+
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		let languages = self
+			.server_client
+			.languages()
+			.await?
+			.into_iter()
+			.map(|lang| Language {
+				name: lang.name,
+				code: lang.code,
+				long_code: lang.long_code,
+			})
+			.collect();
+		Ok(languages)
+	}
+
+	/// Rules from the `configinfo` endpoint, without rules disabled by default.
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		let response = self
+			.server_client
+			.client
+			.get(format!("{}/configinfo", self.server_client.api))
+			.query(&[("language", &lang)])
+			.send()
+			.await?
+			.error_for_status()?
+			.json::<ConfigInfo>()
+			.await?;
+		let disabled = self.disabled_categories.get(&lang);
+		let rules = response
+			.rules
+			.into_iter()
+			.filter(|rule| rule.is_default_off.as_deref() != Some("yes"))
+			.filter(|rule| {
+				disabled
+					.is_some_and(|disabled| disabled.contains(&rule.rule_id))
+					.not()
+			})
+			.map(|rule| RuleInfo {
+				id: rule.rule_id,
+				description: rule.description,
+				category: rule.category_name,
+			})
+			.collect();
+		Ok(rules)
+	}
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct ConfigInfo {
+	rules: Vec<ConfigRule>,
 }
 
-fn filter_match(m: &Match, allowed: &HashSet<String>) -> bool {
-	if m.context.length == 0 {
-		return false;
-	}
-	let mut iter = m.context.text.char_indices();
-	let Some((start, _)) = iter.nth(m.context.offset) else {
-		return false;
-	};
-	let Some((end, _)) = iter.nth(m.context.length - 1) else {
-		return false;
-	};
-	let text = &m.context.text[start..end];
-	allowed.contains(text)
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ConfigRule {
+	rule_id: String,
+	#[serde(default)]
+	description: String,
+	#[serde(default)]
+	category_name: String,
+	#[serde(default)]
+	is_default_off: Option<String>,
 }
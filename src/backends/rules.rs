@@ -0,0 +1,158 @@
+use std::{collections::HashSet, ops::Not, sync::Arc};
+
+use regex::Regex;
+
+use crate::{
+	utf16_offset, Cancellation, CheckMode, Language, LanguageToolBackend, RuleInfo, RuleOptions,
+	Suggestion,
+};
+
+/// User defined regex rules, checked without a LanguageTool instance.
+#[derive(Debug)]
+pub struct LanguageToolRules {
+	rules: Vec<Rule>,
+	disabled: HashSet<(String, String)>,
+	check_mode: CheckMode,
+}
+
+#[derive(Debug)]
+struct Rule {
+	id: Arc<str>,
+	regex: Regex,
+	message: Arc<str>,
+	replacement: Option<String>,
+	language: Option<String>,
+}
+
+impl LanguageToolRules {
+	pub fn new(rules: &[RuleOptions]) -> anyhow::Result<Self> {
+		let rules = rules
+			.iter()
+			.enumerate()
+			.map(|(idx, rule)| {
+				let regex = Regex::new(&rule.pattern).map_err(|err| {
+					anyhow::anyhow!("Invalid pattern for rule {}: {}", idx + 1, err)
+				})?;
+				Ok(Rule {
+					id: rule
+						.id
+						.clone()
+						.unwrap_or_else(|| format!("USER_RULE_{}", idx + 1))
+						.into(),
+					regex,
+					message: rule.message.as_str().into(),
+					replacement: rule.replacement.clone(),
+					language: rule.language.clone(),
+				})
+			})
+			.collect::<anyhow::Result<Vec<_>>>()?;
+		Ok(Self {
+			rules,
+			disabled: HashSet::new(),
+			check_mode: CheckMode::All,
+		})
+	}
+}
+
+impl LanguageToolBackend for LanguageToolRules {
+	async fn allow_words(&mut self, _lang: String, _words: &[String]) -> anyhow::Result<()> {
+		Ok(())
+	}
+
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		self.disabled
+			.extend(checks.iter().map(|check| (lang.clone(), check.clone())));
+		Ok(())
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		self.check_mode = mode;
+		Ok(())
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		_cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		let mut suggestions = Vec::new();
+		// only grammar and style rules
+		if self.check_mode == CheckMode::Spelling {
+			return Ok(suggestions);
+		}
+		for rule in &self.rules {
+			if let Some(language) = &rule.language {
+				if matches_language(language, &lang).not() {
+					continue;
+				}
+			}
+			if self.disabled.contains(&(lang.clone(), rule.id.to_string())) {
+				continue;
+			}
+			for captures in rule.regex.captures_iter(text) {
+				let m = captures.get(0).unwrap();
+				if m.is_empty() {
+					continue;
+				}
+				let replacements = match &rule.replacement {
+					Some(replacement) => {
+						let mut value = String::new();
+						captures.expand(replacement, &mut value);
+						vec![value]
+					},
+					None => Vec::new(),
+				};
+				suggestions.push(Suggestion {
+					start: utf16_offset(text, m.start()),
+					end: utf16_offset(text, m.end()),
+					message: rule.message.clone(),
+					replacements: replacements.into(),
+					rule_description: rule.message.clone(),
+					rule_id: rule.id.clone(),
+					category: "User Rules".into(),
+				});
+			}
+		}
+		Ok(suggestions)
+	}
+
+	/// The rules apply to every language.
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		Ok(Vec::new())
+	}
+
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		if self.check_mode == CheckMode::Spelling {
+			return Ok(Vec::new());
+		}
+		let rules = self
+			.rules
+			.iter()
+			.filter(|rule| {
+				rule.language
+					.as_ref()
+					.is_none_or(|language| matches_language(language, &lang))
+			})
+			.filter(|rule| {
+				self.disabled
+					.contains(&(lang.clone(), rule.id.to_string()))
+					.not()
+			})
+			.map(|rule| RuleInfo {
+				id: rule.id.to_string(),
+				description: rule.message.to_string(),
+				category: String::from("User Rules"),
+			})
+			.collect();
+		Ok(rules)
+	}
+}
+
+/// `en` matches `en`, `en-US`, `en-GB`, ...
+pub(crate) fn matches_language(filter: &str, lang: &str) -> bool {
+	lang == filter
+		|| lang
+			.strip_prefix(filter)
+			.is_some_and(|rest| rest.starts_with('-'))
+}
@@ -0,0 +1,312 @@
+use std::{
+	collections::{HashMap, HashSet},
+	ops::Not,
+	path::Path,
+	sync::Arc,
+};
+
+use regex::Regex;
+
+use crate::{
+	utf16_offset, Cancellation, CheckMode, Language, LanguageToolBackend, RuleInfo, Suggestion,
+};
+
+/// Vale compatible style rules (`existence`, `substitution` and `occurrence`).
+#[derive(Debug)]
+pub struct LanguageToolVale {
+	rules: Vec<Rule>,
+	disabled: HashSet<(String, String)>,
+	check_mode: CheckMode,
+}
+
+#[derive(Debug)]
+struct Rule {
+	id: Arc<str>,
+	message: String,
+	description: Arc<str>,
+	kind: RuleKind,
+}
+
+#[derive(Debug)]
+enum RuleKind {
+	Existence(Regex),
+	Substitution(Vec<(Regex, String)>),
+	Occurrence {
+		regex: Regex,
+		min: Option<usize>,
+		max: Option<usize>,
+	},
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct ValeRule {
+	extends: String,
+	#[serde(default)]
+	message: String,
+	#[serde(default)]
+	description: Option<String>,
+	#[serde(default)]
+	ignorecase: bool,
+	#[serde(default)]
+	nonword: bool,
+	#[serde(default)]
+	tokens: Vec<String>,
+	#[serde(default)]
+	raw: Vec<String>,
+	#[serde(default)]
+	swap: HashMap<String, String>,
+	#[serde(default)]
+	token: Option<String>,
+	#[serde(default)]
+	min: Option<usize>,
+	#[serde(default)]
+	max: Option<usize>,
+}
+
+impl LanguageToolVale {
+	/// Load the rules from `<styles_path>/<style>/<rule>.yml`.
+	/// All styles are loaded if `styles` is empty.
+	pub fn new(styles_path: &Path, styles: &[String]) -> anyhow::Result<Self> {
+		let mut rules = Vec::new();
+		for style in std::fs::read_dir(styles_path)? {
+			let style = style?;
+			if style.file_type()?.is_dir().not() {
+				continue;
+			}
+			let style_name = style.file_name().to_string_lossy().into_owned();
+			if styles.is_empty().not() && styles.contains(&style_name).not() {
+				continue;
+			}
+			let mut paths = std::fs::read_dir(style.path())?
+				.map(|entry| entry.map(|entry| entry.path()))
+				.collect::<Result<Vec<_>, _>>()?;
+			paths.sort();
+			for path in paths {
+				match path.extension() {
+					Some(ext) if ext == "yml" || ext == "yaml" => {},
+					_ => continue,
+				}
+				let Some(rule_name) = path.file_stem() else {
+					continue;
+				};
+				let id = format!("{}.{}", style_name, rule_name.to_string_lossy());
+				let file = std::fs::File::open(&path)?;
+				let rule = serde_yaml::from_reader::<_, ValeRule>(file)
+					.map_err(|err| anyhow::anyhow!("Invalid vale rule {}: {}", id, err))?;
+				if let Some(rule) = Rule::new(id, rule)? {
+					rules.push(rule);
+				}
+			}
+		}
+		Ok(Self {
+			rules,
+			disabled: HashSet::new(),
+			check_mode: CheckMode::All,
+		})
+	}
+}
+
+impl Rule {
+	fn new(id: String, rule: ValeRule) -> anyhow::Result<Option<Self>> {
+		let build = |pattern: &str| {
+			let pattern = match (rule.nonword, rule.ignorecase) {
+				(true, true) => format!("(?i){}", pattern),
+				(true, false) => pattern.to_owned(),
+				(false, true) => format!(r"(?i)\b(?:{})\b", pattern),
+				(false, false) => format!(r"\b(?:{})\b", pattern),
+			};
+			Regex::new(&pattern)
+				.map_err(|err| anyhow::anyhow!("Invalid pattern in vale rule {}: {}", id, err))
+		};
+		let kind = match rule.extends.as_str() {
+			"existence" => {
+				let pattern = if rule.raw.is_empty() {
+					rule.tokens.join("|")
+				} else {
+					rule.raw.concat()
+				};
+				RuleKind::Existence(build(&pattern)?)
+			},
+			"substitution" => {
+				let mut swap = rule
+					.swap
+					.iter()
+					.map(|(pattern, replacement)| Ok((build(pattern)?, replacement.clone())))
+					.collect::<anyhow::Result<Vec<_>>>()?;
+				swap.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+				RuleKind::Substitution(swap)
+			},
+			"occurrence" => {
+				let Some(token) = &rule.token else {
+					return Ok(None);
+				};
+				RuleKind::Occurrence {
+					regex: build(token)?,
+					min: rule.min,
+					max: rule.max,
+				}
+			},
+			_ => return Ok(None),
+		};
+		Ok(Some(Self {
+			description: rule.description.unwrap_or_else(|| id.clone()).into(),
+			id: id.into(),
+			message: rule.message,
+			kind,
+		}))
+	}
+
+	/// Style of the rule (`<style>.<rule>`).
+	fn category(&self) -> &str {
+		self.id.split('.').next().unwrap_or_default()
+	}
+
+	fn check(&self, text: &str, suggestions: &mut Vec<Suggestion>) {
+		let mut push = |start: usize, end: usize, message: String, replacements: Vec<String>| {
+			suggestions.push(Suggestion {
+				start: utf16_offset(text, start),
+				end: utf16_offset(text, end),
+				message: message.into(),
+				replacements: replacements.into(),
+				rule_description: self.description.clone(),
+				rule_id: self.id.clone(),
+				category: self.category().into(),
+			})
+		};
+		match &self.kind {
+			RuleKind::Existence(regex) => {
+				for m in regex.find_iter(text).filter(|m| m.is_empty().not()) {
+					push(
+						m.start(),
+						m.end(),
+						format_message(&self.message, &[m.as_str()]),
+						Vec::new(),
+					);
+				}
+			},
+			RuleKind::Substitution(swap) => {
+				for (regex, replacement) in swap {
+					for m in regex.find_iter(text).filter(|m| m.is_empty().not()) {
+						let replacements = replacement
+							.split('|')
+							.map(String::from)
+							.filter(|replacement| replacement != m.as_str())
+							.collect::<Vec<_>>();
+						if replacements.is_empty() {
+							continue;
+						}
+						push(
+							m.start(),
+							m.end(),
+							format_message(&self.message, &[replacement, m.as_str()]),
+							replacements,
+						);
+					}
+				}
+			},
+			RuleKind::Occurrence { regex, min, max } => {
+				let matches = regex
+					.find_iter(text)
+					.filter(|m| m.is_empty().not())
+					.collect::<Vec<_>>();
+				if let Some(max) = *max {
+					if let Some(m) = matches.get(max) {
+						push(
+							m.start(),
+							m.end(),
+							format_message(&self.message, &[&matches.len().to_string()]),
+							Vec::new(),
+						);
+					}
+				}
+				if let Some(min) = *min {
+					let trimmed = text.trim_end();
+					if matches.len() < min && trimmed.is_empty().not() {
+						push(
+							text.len() - text.trim_start().len(),
+							trimmed.len(),
+							format_message(&self.message, &[&matches.len().to_string()]),
+							Vec::new(),
+						);
+					}
+				}
+			},
+		}
+	}
+}
+
+impl LanguageToolBackend for LanguageToolVale {
+	async fn allow_words(&mut self, _lang: String, _words: &[String]) -> anyhow::Result<()> {
+		Ok(())
+	}
+
+	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+		self.disabled
+			.extend(checks.iter().map(|check| (lang.clone(), check.clone())));
+		Ok(())
+	}
+
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
+		self.check_mode = mode;
+		Ok(())
+	}
+
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		_cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		let mut suggestions = Vec::new();
+		// only grammar and style rules
+		if self.check_mode == CheckMode::Spelling {
+			return Ok(suggestions);
+		}
+		for rule in &self.rules {
+			if self.disabled.contains(&(lang.clone(), rule.id.to_string())) {
+				continue;
+			}
+			rule.check(text, &mut suggestions);
+		}
+		Ok(suggestions)
+	}
+
+	/// The rules apply to every language.
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		Ok(Vec::new())
+	}
+
+	/// The style is used as category.
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		if self.check_mode == CheckMode::Spelling {
+			return Ok(Vec::new());
+		}
+		let rules = self
+			.rules
+			.iter()
+			.filter(|rule| {
+				self.disabled
+					.contains(&(lang.clone(), rule.id.to_string()))
+					.not()
+			})
+			.map(|rule| RuleInfo {
+				id: rule.id.to_string(),
+				description: rule.description.to_string(),
+				category: rule.category().to_owned(),
+			})
+			.collect();
+		Ok(rules)
+	}
+}
+
+/// Replace the `%s` placeholders in order.
+fn format_message(message: &str, args: &[&str]) -> String {
+	let mut parts = message.split("%s");
+	let mut result = parts.next().unwrap_or_default().to_owned();
+	let mut args = args.iter();
+	for part in parts {
+		result += args.next().copied().unwrap_or_default();
+		result += part;
+	}
+	result
+}
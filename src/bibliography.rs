@@ -0,0 +1,296 @@
+use std::{
+	collections::HashMap,
+	ops::{Not, Range},
+	path::Path,
+};
+
+use typst::{
+	foundations::{IntoValue, NativeElement, Packed, Selector, Value},
+	model::{BibliographyElem, CiteGroup, Document},
+	syntax::Span,
+	World,
+};
+
+use crate::convert::{ChunkBuilder, Mapping};
+
+/// Function generating the text of the references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+	Bibliography,
+	Cite,
+}
+
+impl Function {
+	fn name(self) -> &'static str {
+		match self {
+			Self::Bibliography => "bibliography",
+			Self::Cite => "cite",
+		}
+	}
+
+	fn separator(self) -> &'static str {
+		match self {
+			Self::Bibliography => "\n\n",
+			Self::Cite => " ",
+		}
+	}
+}
+
+/// Only check the `fields` of the entries (`title`, `parent.title`, ...) in the text of the `bibliography` and `cite` functions.
+///
+/// The fields are lists for the function names, the other text of the functions, like author names and identifiers, is skipped.
+/// The field values are found in the generated text, so fields changed by the citation style are not found.
+pub fn bibliography(
+	world: &impl World,
+	doc: &Document,
+	paragraphs: Vec<(String, Mapping)>,
+	fields: &HashMap<String, Vec<String>>,
+) -> Vec<(String, Mapping)> {
+	let mut functions = HashMap::new();
+	let mut entries = Vec::new();
+	for content in doc
+		.introspector
+		.query(&Selector::Elem(BibliographyElem::elem(), None))
+		.iter()
+	{
+		let Some(elem) = content.to_packed::<BibliographyElem>() else {
+			continue;
+		};
+		functions.insert(elem.span(), Function::Bibliography);
+		entries.extend(load_entries(world, elem));
+	}
+	if functions.is_empty() {
+		return paragraphs;
+	}
+	// the text of a group has the span of the first citation
+	for content in doc
+		.introspector
+		.query(&Selector::Elem(CiteGroup::elem(), None))
+		.iter()
+	{
+		if let Some(group) = content.to_packed::<CiteGroup>() {
+			for cite in group.children() {
+				functions.insert(cite.span(), Function::Cite);
+			}
+		}
+	}
+
+	let values = [Function::Bibliography, Function::Cite].map(|function| {
+		let mut values = Vec::new();
+		for field in fields.get(function.name()).into_iter().flatten() {
+			let path = field.split('.').collect::<Vec<_>>();
+			for entry in &entries {
+				field_values(entry, &path, &mut values);
+			}
+		}
+		values
+			.iter()
+			.map(|value| fold(value.trim()))
+			.filter(|value| value.is_empty().not())
+			.collect::<Vec<_>>()
+	});
+
+	paragraphs
+		.into_iter()
+		.filter_map(|(text, mapping)| {
+			let (text, mapping) = apply(text, mapping, &functions, &values);
+			mapping.chars.is_empty().not().then_some((text, mapping))
+		})
+		.collect()
+}
+
+/// Entries of the bibliography files as their Hayagriva YAML fields.
+fn load_entries(world: &impl World, elem: &Packed<BibliographyElem>) -> Vec<serde_json::Value> {
+	let Value::Array(paths) = elem.path().clone().into_value() else {
+		return Vec::new();
+	};
+	let mut entries = Vec::new();
+	for path in paths {
+		let Value::Str(path) = path else {
+			continue;
+		};
+		let Some(bytes) = elem
+			.span()
+			.resolve_path(&path)
+			.ok()
+			.and_then(|id| world.file(id).ok())
+		else {
+			continue;
+		};
+		let Ok(src) = std::str::from_utf8(&bytes) else {
+			continue;
+		};
+		let extension = Path::new(path.as_str())
+			.extension()
+			.and_then(|extension| extension.to_str())
+			.unwrap_or_default();
+		let library = match extension.to_lowercase().as_str() {
+			"yml" | "yaml" => hayagriva::io::from_yaml_str(src).ok(),
+			"bib" => hayagriva::io::from_biblatex_str(src).ok(),
+			_ => None,
+		};
+		entries.extend(
+			library
+				.iter()
+				.flat_map(|library| library.iter())
+				.filter_map(|entry| serde_json::to_value(entry).ok()),
+		);
+	}
+	entries
+}
+
+/// Fields with persons, the names are abbreviated or reordered by the citation styles.
+const PERSON_FIELDS: [&str; 3] = ["author", "editor", "affiliated"];
+
+/// String values of the field, nested fields are separated by a `.`.
+/// The names of persons are split into words.
+fn field_values(value: &serde_json::Value, path: &[&str], res: &mut Vec<String>) {
+	use serde_json::Value;
+	match (value, path) {
+		(Value::Array(items), _) => {
+			for item in items {
+				field_values(item, path, res);
+			}
+		},
+		(Value::String(value), []) => res.push(value.replace(['{', '}'], "")),
+		(Value::Object(map), []) => {
+			for value in map.values() {
+				field_values(value, path, res);
+			}
+		},
+		(Value::Object(map), [key, rest @ ..]) => {
+			let Some(value) = map.get(*key) else {
+				return;
+			};
+			if rest.is_empty().not() || PERSON_FIELDS.contains(key).not() {
+				field_values(value, rest, res);
+				return;
+			}
+			let mut names = Vec::new();
+			field_values(value, rest, &mut names);
+			res.extend(
+				names
+					.iter()
+					.flat_map(|name| name.split([' ', ',']))
+					.filter(|word| word.chars().count() > 1)
+					.map(|word| word.to_string()),
+			);
+		},
+		_ => {},
+	}
+}
+
+/// Chars for a case insensitive comparison.
+fn fold(text: &str) -> Vec<char> {
+	text.chars()
+		.map(|c| c.to_lowercase().next().unwrap_or(c))
+		.collect()
+}
+
+/// Chunk with the text of the functions reduced to the field values.
+fn apply(
+	text: String,
+	mapping: Mapping,
+	functions: &HashMap<Span, Function>,
+	values: &[Vec<Vec<char>>; 2],
+) -> (String, Mapping) {
+	// ranges of consecutive chars generated by the same function
+	let mut runs = Vec::<(Function, usize, usize)>::new();
+	for (index, (span, _)) in mapping.chars.iter().enumerate() {
+		let Some(function) = functions.get(span) else {
+			continue;
+		};
+		match runs.last_mut() {
+			Some((last, _, end)) if *last == *function && *end == index => *end = index + 1,
+			_ => runs.push((*function, index, index + 1)),
+		}
+	}
+	if runs.is_empty() {
+		return (text, mapping);
+	}
+
+	// chars for the UTF-16 code units of the text
+	let units = text
+		.chars()
+		.flat_map(|c| std::iter::repeat_n(c, c.len_utf16()))
+		.collect::<Vec<_>>();
+
+	let mut builder = ChunkBuilder::new(&text, &mapping);
+	let mut last = 0;
+	for (function, start, end) in runs {
+		builder.append(last..start);
+		last = end;
+
+		let values = match function {
+			Function::Bibliography => &values[0],
+			Function::Cite => &values[1],
+		};
+		let kept = kept_ranges(&units[start..end], values);
+		if kept.is_empty() {
+			// no double space for a removed citation in a sentence
+			let next = units.get(end).copied();
+			if builder.text().ends_with(' ')
+				&& next.is_none_or(|c| c.is_whitespace() || ".,;:!?)".contains(c))
+			{
+				builder.pop();
+			}
+			continue;
+		}
+		for (index, range) in kept.into_iter().enumerate() {
+			if index > 0 {
+				builder.insert(function.separator());
+			}
+			builder.append(start + range.start..start + range.end);
+		}
+	}
+	builder.append(last..mapping.chars.len());
+
+	let (res, chars) = builder.finish();
+	(res, Mapping { chars, ..mapping })
+}
+
+/// Ranges of the units matching one of the values as whole words, the units are UTF-16 code units with their char.
+fn kept_ranges(units: &[char], values: &[Vec<char>]) -> Vec<Range<usize>> {
+	// one entry for each char, with the range of the code units
+	let mut folded = Vec::with_capacity(units.len());
+	let mut index = 0;
+	while index < units.len() {
+		let c = units[index];
+		let end = index + c.len_utf16();
+		folded.push((index..end, c.to_lowercase().next().unwrap_or(c)));
+		index = end;
+	}
+	let alphanumeric =
+		|position: Option<&(_, char)>| position.is_some_and(|(_, c)| c.is_alphanumeric());
+
+	let mut kept = vec![false; folded.len()];
+	for value in values {
+		if value.len() > folded.len() {
+			continue;
+		}
+		for start in 0..=(folded.len() - value.len()) {
+			let end = start + value.len();
+			let matches = folded[start..end]
+				.iter()
+				.zip(value)
+				.all(|((_, a), b)| a == b);
+			let word = alphanumeric(start.checked_sub(1).and_then(|before| folded.get(before)))
+				.not() && alphanumeric(folded.get(end)).not();
+			if matches && word {
+				kept[start..end].fill(true);
+			}
+		}
+	}
+
+	let mut ranges = Vec::<Range<usize>>::new();
+	for ((units, _), kept) in folded.into_iter().zip(kept) {
+		if kept.not() {
+			continue;
+		}
+		match ranges.last_mut() {
+			Some(range) if range.end == units.start => range.end = units.end,
+			_ => ranges.push(units),
+		}
+	}
+	ranges
+}
@@ -0,0 +1,73 @@
+use std::{
+	collections::{hash_map::DefaultHasher, HashMap},
+	hash::{Hash, Hasher},
+	num::NonZeroUsize,
+};
+
+use lru::LruCache;
+
+use crate::Suggestion;
+
+/// Paragraphs kept by `Cache::new`.
+pub const DEFAULT_CACHE_SIZE: usize = 10_000;
+
+/// Suggestions of checked paragraphs, keyed by a hash of the language and the text.
+/// The least recently used paragraphs are removed if the cache is full.
+#[derive(Debug)]
+pub struct Cache {
+	cache: LruCache<u64, Vec<Suggestion>>,
+}
+
+impl Cache {
+	pub fn new() -> Self {
+		Self::with_capacity(DEFAULT_CACHE_SIZE)
+	}
+
+	/// Cache for at most `capacity` paragraphs.
+	pub fn with_capacity(capacity: usize) -> Self {
+		let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+		Self { cache: LruCache::new(capacity) }
+	}
+
+	pub fn get(&mut self, text: &str, lang: &str) -> Option<Vec<Suggestion>> {
+		self.cache.get(&key(text, lang)).cloned()
+	}
+
+	pub fn insert(&mut self, text: &str, lang: &str, suggestions: Vec<Suggestion>) {
+		self.cache.put(key(text, lang), suggestions);
+	}
+}
+
+impl Default for Cache {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Paragraphs of the last check of a document, only changed paragraphs must be checked again.
+#[derive(Debug, Default)]
+pub struct Incremental {
+	paragraphs: HashMap<u64, Vec<Suggestion>>,
+}
+
+impl Incremental {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Suggestions from the last check, `None` if the paragraph changed.
+	pub fn get(&self, text: &str, lang: &str) -> Option<Vec<Suggestion>> {
+		self.paragraphs.get(&key(text, lang)).cloned()
+	}
+
+	pub fn insert(&mut self, text: &str, lang: &str, suggestions: Vec<Suggestion>) {
+		self.paragraphs.insert(key(text, lang), suggestions);
+	}
+}
+
+fn key(text: &str, lang: &str) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	lang.hash(&mut hasher);
+	text.hash(&mut hasher);
+	hasher.finish()
+}
@@ -0,0 +1,116 @@
+use std::{
+	future::Future,
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Arc,
+	},
+	time::Instant,
+};
+
+use tokio::sync::Notify;
+
+/// Cancels running checks, manually or after a deadline, and counts the checked texts for progress.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+	inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+	cancelled: AtomicBool,
+	checked: AtomicUsize,
+	notify: Notify,
+	deadline: Option<Instant>,
+}
+
+impl Cancellation {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_deadline(deadline: Instant) -> Self {
+		Self {
+			inner: Arc::new(Inner {
+				deadline: Some(deadline),
+				..Default::default()
+			}),
+		}
+	}
+
+	pub fn cancel(&self) {
+		self.inner.cancelled.store(true, Ordering::SeqCst);
+		self.inner.notify.notify_waiters();
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.inner.cancelled.load(Ordering::SeqCst)
+			|| self
+				.inner
+				.deadline
+				.is_some_and(|deadline| Instant::now() >= deadline)
+	}
+
+	/// Error with [`Cancelled`] if the check is cancelled.
+	pub fn check(&self) -> anyhow::Result<()> {
+		if self.is_cancelled() {
+			Err(Cancelled)?;
+		}
+		Ok(())
+	}
+
+	/// Number of texts checked so far.
+	pub fn checked(&self) -> usize {
+		self.inner.checked.load(Ordering::SeqCst)
+	}
+
+	pub(crate) fn add_checked(&self) {
+		self.inner.checked.fetch_add(1, Ordering::SeqCst);
+	}
+
+	/// Reset the count for texts which are checked again.
+	pub(crate) fn reset_checked(&self, checked: usize) {
+		self.inner.checked.store(checked, Ordering::SeqCst);
+	}
+
+	/// Wait until the check is cancelled.
+	pub async fn cancelled(&self) {
+		let notified = self.inner.notify.notified();
+		tokio::pin!(notified);
+		notified.as_mut().enable();
+		if self.is_cancelled() {
+			return;
+		}
+		match self.inner.deadline {
+			Some(deadline) => {
+				tokio::select! {
+					_ = notified => {},
+					_ = tokio::time::sleep_until(deadline.into()) => {},
+				}
+			},
+			None => notified.await,
+		}
+	}
+
+	/// Drop the future if the check is cancelled.
+	pub async fn run<T>(
+		&self,
+		future: impl Future<Output = anyhow::Result<T>>,
+	) -> anyhow::Result<T> {
+		tokio::select! {
+			result = future => result,
+			_ = self.cancelled() => Err(Cancelled)?,
+		}
+	}
+}
+
+/// Error for cancelled checks, available with `err.is::<Cancelled>()`.
+#[derive(Debug, Clone, Copy)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Check cancelled.")
+	}
+}
+
+impl std::error::Error for Cancelled {}
@@ -0,0 +1,81 @@
+use std::{
+	collections::HashMap,
+	ops::Not,
+	sync::atomic::{AtomicUsize, Ordering},
+	time::Duration,
+};
+
+/// Requests faster than half of the target use larger chunks, slower requests use smaller chunks.
+const TARGET_LATENCY: Duration = Duration::from_secs(1);
+/// Smaller chunks lose the context for grammar rules.
+const MIN_CHUNK_SIZE: usize = 200;
+/// The public LanguageTool API rejects requests above 20KB, leaves room for multi-byte chars.
+pub const MAX_CHUNK_SIZE: usize = 10_000;
+
+/// Chunk size for [`crate::convert::document`], adjusted from the request latency if `auto` is set.
+/// A changed size changes the chunks, so cached chunks are checked again.
+#[derive(Debug)]
+pub struct ChunkSize {
+	size: AtomicUsize,
+	auto: bool,
+	/// Fixed sizes for short language codes (`de`, ...)
+	languages: HashMap<String, usize>,
+}
+
+impl ChunkSize {
+	pub fn new(size: usize, auto: bool) -> Self {
+		let size = if auto {
+			size.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+		} else {
+			size
+		};
+		Self {
+			size: AtomicUsize::new(size),
+			auto,
+			languages: HashMap::new(),
+		}
+	}
+
+	/// Use fixed sizes for the short language codes instead of the shared size.
+	pub fn with_languages(mut self, languages: HashMap<String, usize>) -> Self {
+		self.languages = languages;
+		self
+	}
+
+	pub fn get(&self) -> usize {
+		self.size.load(Ordering::Relaxed)
+	}
+
+	/// Size for chunks with the short language code.
+	pub fn get_for(&self, language: &str) -> usize {
+		self.languages
+			.get(language)
+			.copied()
+			.unwrap_or_else(|| self.get())
+	}
+
+	pub fn is_auto(&self) -> bool {
+		self.auto
+	}
+
+	/// Adjust the size for a request with `chars` chars per chunk.
+	pub fn observe(&self, chars: usize, latency: Duration) {
+		let size = self.get();
+		// only full chunks show the latency for the size
+		if self.auto.not() || chars < size / 2 {
+			return;
+		}
+		let next = if latency < TARGET_LATENCY / 2 {
+			size * 3 / 2
+		} else if latency > TARGET_LATENCY {
+			size * 2 / 3
+		} else {
+			size
+		}
+		.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+		if next != size {
+			tracing::debug!("Chunk size {} ({:.2?} for {} chars)", next, latency, chars);
+			self.size.store(next, Ordering::Relaxed);
+		}
+	}
+}
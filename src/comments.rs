@@ -0,0 +1,371 @@
+use std::{
+	collections::HashMap,
+	ops::{Not, Range},
+};
+
+use typst::{
+	syntax::{FileId, LinkedNode, Source, Span, SyntaxKind},
+	text::Lang,
+	World,
+};
+
+use crate::{
+	convert::{self, Mapping},
+	ChunkSize,
+};
+
+/// Settings from the LTeX magic comments before a position in a source file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Settings {
+	/// `enabled=false`
+	disabled: bool,
+	/// `language=de-DE`, `auto` resets the language
+	language: Option<String>,
+	/// `disabledRules=RULE_A,RULE_B`
+	disabled_rules: Vec<String>,
+}
+
+/// Apply the LTeX magic comments (`// ltex: language=de-DE enabled=false`) in the source files to the chunks.
+///
+/// A comment changes the settings until the end of its file or the next comment.
+/// The chunks are split where the settings change and the chunks with `enabled=false` are removed.
+pub fn ltex_comments(
+	world: &impl World,
+	paragraphs: Vec<(String, Mapping)>,
+) -> Vec<(String, Mapping)> {
+	let mut files = HashMap::<FileId, Vec<(usize, Settings)>>::new();
+	let mut res = Vec::new();
+	for (text, mapping) in paragraphs {
+		for (span, _) in &mapping.chars {
+			let Some(id) = span.id() else {
+				continue;
+			};
+			files.entry(id).or_insert_with(|| match world.source(id) {
+				Ok(source) => directives(&source),
+				Err(_) => Vec::new(),
+			});
+		}
+		split(world, &files, text, mapping, &mut res);
+	}
+	res
+}
+
+/// Split the chunk into parts with the same settings.
+fn split(
+	world: &impl World,
+	files: &HashMap<FileId, Vec<(usize, Settings)>>,
+	text: String,
+	mapping: Mapping,
+	res: &mut Vec<(String, Mapping)>,
+) {
+	let default = Settings::default();
+	let mut current = &default;
+	let settings = mapping
+		.chars
+		.iter()
+		.map(|(span, range)| {
+			let Some(directives) = span.id().and_then(|id| files.get(&id)) else {
+				return current;
+			};
+			if directives.is_empty() {
+				current = &default;
+				return current;
+			}
+			let Some(offset) = world.source(span.id().unwrap()).ok().and_then(|source| {
+				let node = source.find(*span)?;
+				Some(match node.kind() {
+					SyntaxKind::Text => node.range().start + range.start as usize,
+					_ => node.range().start,
+				})
+			}) else {
+				return current;
+			};
+			current = directives
+				.iter()
+				.rev()
+				.find(|(start, _)| *start <= offset)
+				.map(|(_, settings)| settings)
+				.unwrap_or(&default);
+			current
+		})
+		.collect::<Vec<_>>();
+
+	let first = settings.first().copied().unwrap_or(&default);
+	if settings.iter().all(|settings| *settings == first) {
+		if first.disabled.not() {
+			res.push((text, with_settings(mapping, first)));
+		}
+		return;
+	}
+
+	let bytes = convert::byte_offsets(&text);
+	let mut start = 0;
+	while start < settings.len() {
+		let part = settings[start];
+		let end = settings[start..]
+			.iter()
+			.position(|settings| *settings != part)
+			.map_or(settings.len(), |len| start + len);
+		if part.disabled.not() {
+			let chunk = Mapping {
+				chars: mapping.chars[start..end].to_vec(),
+				language: mapping.language,
+				comment_language: None,
+				disabled_rules: Vec::new(),
+				headings: Vec::new(),
+				heading_rules: Vec::new(),
+				skipped_words: Vec::new(),
+				comment: mapping.comment,
+			};
+			res.push((
+				text[bytes[start]..bytes[end]].to_string(),
+				with_settings(chunk, part),
+			));
+		}
+		start = end;
+	}
+}
+
+fn with_settings(mut mapping: Mapping, settings: &Settings) -> Mapping {
+	mapping.comment_language = settings.language.clone();
+	mapping.disabled_rules = settings.disabled_rules.clone();
+	mapping
+}
+
+/// Offsets after the magic comments in the source with the settings from there on.
+fn directives(source: &Source) -> Vec<(usize, Settings)> {
+	let mut comments = Vec::new();
+	collect_comments(&LinkedNode::new(source.root()), &mut comments);
+
+	let mut settings = Settings::default();
+	let mut res = Vec::new();
+	for (end, comment) in comments {
+		let content = comment
+			.strip_prefix("//")
+			.or_else(|| {
+				comment
+					.strip_prefix("/*")
+					.map(|comment| comment.trim_end_matches("*/"))
+			})
+			.unwrap_or(&comment)
+			.trim();
+		let Some(content) = content
+			.get(..5)
+			.filter(|prefix| prefix.eq_ignore_ascii_case("ltex:"))
+			.map(|_| &content[5..])
+		else {
+			continue;
+		};
+		for setting in content.split_whitespace() {
+			let Some((key, value)) = setting.split_once('=') else {
+				tracing::warn!("Invalid LTeX setting '{}' in {:?}", setting, source.id());
+				continue;
+			};
+			match key.to_lowercase().as_str() {
+				"enabled" => settings.disabled = value.eq_ignore_ascii_case("false"),
+				"language" if value.eq_ignore_ascii_case("auto") => settings.language = None,
+				"language" => settings.language = Some(value.to_string()),
+				"disabledrules" => {
+					settings.disabled_rules = value
+						.split(',')
+						.filter(|rule| rule.is_empty().not())
+						.map(str::to_string)
+						.collect()
+				},
+				_ => tracing::warn!("Unknown LTeX setting '{}' in {:?}", key, source.id()),
+			}
+		}
+		res.push((end, settings.clone()));
+	}
+	res
+}
+
+fn collect_comments(node: &LinkedNode, comments: &mut Vec<(usize, String)>) {
+	match node.kind() {
+		SyntaxKind::LineComment | SyntaxKind::BlockComment => {
+			comments.push((node.range().end, node.text().to_string()))
+		},
+		_ => {
+			for child in node.children() {
+				collect_comments(&child, comments);
+			}
+		},
+	}
+}
+
+/// Text of the comments in the source files as chunks, for the file or the files of the paragraphs.
+///
+/// Consecutive line comments are joined into one paragraph and the LTeX magic comments are skipped.
+/// The comments have the language of the first paragraph in the file.
+pub fn source_comments(
+	world: &impl World,
+	paragraphs: &[(String, Mapping)],
+	file_id: Option<FileId>,
+	chunk_size: &ChunkSize,
+) -> Vec<(String, Mapping)> {
+	let mut files = Vec::new();
+	match file_id {
+		Some(id) => files.push(id),
+		None => {
+			for (span, _) in paragraphs.iter().flat_map(|(_, mapping)| &mapping.chars) {
+				match span.id() {
+					Some(id) if id.package().is_none() && files.contains(&id).not() => {
+						files.push(id)
+					},
+					_ => {},
+				}
+			}
+		},
+	}
+
+	let mut res = Vec::new();
+	for id in files {
+		let Ok(source) = world.source(id) else {
+			continue;
+		};
+		let language = paragraphs
+			.iter()
+			.find(|(_, mapping)| mapping.chars.iter().any(|(span, _)| span.id() == Some(id)))
+			.map_or(Lang::ENGLISH, |(_, mapping)| mapping.language);
+		let mut comments = Vec::new();
+		collect_comment_nodes(&LinkedNode::new(source.root()), &mut comments);
+
+		let mut chunk = CommentChunk::new(language);
+		let mut previous = None::<(SyntaxKind, usize)>;
+		for (kind, span, range) in comments {
+			let text = &source.text()[range.clone()];
+			let segments = segments(kind, text);
+			if segments.is_empty() || text.len() > u16::MAX as usize {
+				continue;
+			}
+			let joined = previous.is_some_and(|(previous, end)| {
+				let between = &source.text()[end..range.start];
+				previous == SyntaxKind::LineComment
+					&& kind == SyntaxKind::LineComment
+					&& between.trim().is_empty()
+					&& between.matches('\n').count() == 1
+			});
+			previous = Some((kind, range.end));
+			if joined.not() && chunk.mapping.chars.len() > chunk_size.get_for(language.as_str()) {
+				res.push(chunk.finish());
+				chunk = CommentChunk::new(language);
+			}
+			if chunk.text.is_empty().not() {
+				chunk.detached(if joined { " " } else { "\n\n" });
+			}
+			for (index, segment) in segments.into_iter().enumerate() {
+				if index > 0 {
+					chunk.detached(" ");
+				}
+				chunk.push(span, text, segment);
+			}
+		}
+		if chunk.text.is_empty().not() {
+			res.push(chunk.finish());
+		}
+	}
+	res
+}
+
+struct CommentChunk {
+	text: String,
+	mapping: Mapping,
+}
+
+impl CommentChunk {
+	fn new(language: Lang) -> Self {
+		Self {
+			text: String::new(),
+			mapping: Mapping {
+				chars: Vec::new(),
+				language,
+				comment_language: None,
+				disabled_rules: Vec::new(),
+				headings: Vec::new(),
+				heading_rules: Vec::new(),
+				skipped_words: Vec::new(),
+				comment: true,
+			},
+		}
+	}
+
+	fn detached(&mut self, text: &str) {
+		self.text += text;
+		for _ in text.encode_utf16() {
+			self.mapping.chars.push((Span::detached(), 0..0));
+		}
+	}
+
+	/// Part of the comment, the chars are mapped to the byte range in the comment.
+	fn push(&mut self, span: Span, comment: &str, range: Range<usize>) {
+		for (index, c) in comment[range.clone()].char_indices() {
+			let start = (range.start + index) as u16;
+			for _ in 0..c.len_utf16() {
+				self.mapping
+					.chars
+					.push((span, start..start + c.len_utf8() as u16));
+			}
+		}
+		self.text += &comment[range];
+	}
+
+	fn finish(self) -> (String, Mapping) {
+		(self.text, self.mapping)
+	}
+}
+
+/// Byte ranges of the text in the comment without the comment markers, empty for LTeX magic comments.
+fn segments(kind: SyntaxKind, comment: &str) -> Vec<Range<usize>> {
+	let (start, end) = match kind {
+		SyntaxKind::LineComment => (
+			comment.len() - comment.trim_start_matches('/').len(),
+			comment.len(),
+		),
+		_ => (
+			2.min(comment.len()),
+			comment.len()
+				- if comment.ends_with("*/") && comment.len() >= 4 {
+					2
+				} else {
+					0
+				},
+		),
+	};
+	let content = &comment[start..end];
+	let magic = content
+		.trim_start()
+		.get(..5)
+		.is_some_and(|prefix| prefix.eq_ignore_ascii_case("ltex:"));
+	if magic {
+		return Vec::new();
+	}
+	// lines without the indentation and the `*` of block comments
+	let mut res = Vec::new();
+	let mut offset = start;
+	for line in content.split('\n') {
+		let mut rest = line.trim_start();
+		if kind == SyntaxKind::BlockComment {
+			rest = rest.trim_start_matches('*').trim_start();
+		}
+		let trimmed = rest.trim_end();
+		if trimmed.is_empty().not() {
+			let line_start = offset + line.len() - rest.len();
+			res.push(line_start..line_start + trimmed.len());
+		}
+		offset += line.len() + 1;
+	}
+	res
+}
+
+fn collect_comment_nodes(node: &LinkedNode, comments: &mut Vec<(SyntaxKind, Span, Range<usize>)>) {
+	match node.kind() {
+		SyntaxKind::LineComment | SyntaxKind::BlockComment => {
+			comments.push((node.kind(), node.span(), node.range()))
+		},
+		_ => {
+			for child in node.children() {
+				collect_comment_nodes(&child, comments);
+			}
+		},
+	}
+}
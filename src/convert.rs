@@ -1,29 +1,62 @@
-use std::ops::Range;
+use std::{
+	collections::{HashMap, HashSet},
+	ops::{Not, Range, RangeInclusive},
+	sync::Arc,
+};
 
 use typst::{
-	layout::{Abs, Em, Point},
-	model::Document,
-	syntax::{FileId, Source, Span, SyntaxKind},
-	text::{Lang, TextItem},
+	foundations::{Content, NativeElement, Selector, StyleChain},
+	introspection::Location,
+	layout::{Abs, Em, Frame, FrameItem, Point, Position, Size},
+	model::{Destination, Document, FigureElem, HeadingElem},
+	syntax::{FileId, LinkedNode, Side, Source, Span, SyntaxKind},
+	text::{Lang, SmartQuoteElem, SpaceElem, TextElem, TextItem},
 	World,
 };
 
-use crate::Suggestion;
+use crate::{ChunkSize, Suggestion};
 
+/// Source locations and language for the chars of a text chunk.
 #[derive(Debug)]
 pub struct Mapping {
-	chars: Vec<(Span, Range<u16>)>,
-	language: Lang,
+	pub(crate) chars: Vec<(Span, Range<u16>)>,
+	pub(crate) language: Lang,
+	/// Language from a LTeX magic comment
+	pub(crate) comment_language: Option<String>,
+	/// Rules disabled by a LTeX magic comment
+	pub(crate) disabled_rules: Vec<String>,
+	/// Ranges of the headings (UTF-16 code units of the chunk), see [`crate::headings`]
+	pub(crate) headings: Vec<Range<usize>>,
+	/// Rules disabled in the headings
+	pub(crate) heading_rules: Vec<String>,
+	/// Ranges of the skipped words (UTF-16 code units of the chunk), see [`crate::skip_words`]
+	pub(crate) skipped_words: Vec<Range<usize>>,
+	/// Text of comments in the source, see [`crate::source_comments`]
+	pub(crate) comment: bool,
 }
 
 impl Mapping {
+	/// Source locations for the suggestion, only in the source file if specified.
 	pub fn location(
 		&self,
 		suggestion: &Suggestion,
 		world: &impl World,
 		source: Option<&Source>,
 	) -> Vec<(FileId, Range<usize>)> {
-		let chars = &self.chars[suggestion.start..suggestion.end];
+		self.source_location(suggestion.start..suggestion.end, world, source)
+	}
+
+	/// Source locations for the range (UTF-16 code units of the chunk), only in the source file if specified.
+	/// A range outside of the chunk has no locations.
+	pub fn source_location(
+		&self,
+		range: Range<usize>,
+		world: &impl World,
+		source: Option<&Source>,
+	) -> Vec<(FileId, Range<usize>)> {
+		let Some(chars) = self.chars.get(range) else {
+			return Vec::new();
+		};
 		let mut locations = Vec::<(FileId, Range<usize>)>::new();
 		for (span, range) in chars.iter().cloned() {
 			let Some(id) = span.id() else {
@@ -44,7 +77,10 @@ impl Mapping {
 			let Some(node) = source.find(span) else {
 				continue;
 			};
-			if node.kind() == SyntaxKind::Text {
+			if matches!(
+				node.kind(),
+				SyntaxKind::Text | SyntaxKind::LineComment | SyntaxKind::BlockComment
+			) {
 				let start = node.range().start;
 				let range = (start + range.start as usize)..(start + range.end as usize);
 				match locations.last_mut() {
@@ -66,6 +102,60 @@ impl Mapping {
 		locations
 	}
 
+	/// File of the first char with a source in the range (UTF-16 code units of the chunk).
+	pub fn file_id(&self, range: Range<usize>) -> Option<FileId> {
+		let end = range.end.min(self.chars.len());
+		self.chars
+			.get(range.start..end)?
+			.iter()
+			.find_map(|(span, _)| span.id())
+	}
+
+	/// Long language code from a LTeX magic comment, see [`crate::ltex_comments`].
+	pub fn comment_language(&self) -> Option<&str> {
+		self.comment_language.as_deref()
+	}
+
+	/// Rule disabled by a LTeX magic comment for the chunk.
+	pub fn disables(&self, rule_id: &str) -> bool {
+		self.disabled_rules.iter().any(|rule| rule == rule_id)
+	}
+
+	/// Rule of the suggestion disabled for the chunk or in the heading of the suggestion,
+	/// or a spelling suggestion for a skipped word.
+	pub fn skips(&self, suggestion: &Suggestion) -> bool {
+		if self.disables(&suggestion.rule_id) {
+			return true;
+		}
+		if suggestion.is_spelling()
+			&& self
+				.skipped_words
+				.iter()
+				.any(|word| word.start < suggestion.end && suggestion.start < word.end)
+		{
+			return true;
+		}
+		self.heading_rules
+			.iter()
+			.any(|rule| **rule == *suggestion.rule_id)
+			&& self
+				.headings
+				.iter()
+				.any(|heading| heading.start <= suggestion.start && suggestion.end <= heading.end)
+	}
+
+	/// Span and byte range in the span for each UTF-16 code unit of the chunk,
+	/// detached for the whitespace between the text items.
+	pub fn chars(&self) -> &[(Span, Range<u16>)] {
+		&self.chars
+	}
+
+	/// Language of the text in the document.
+	pub fn language(&self) -> Lang {
+		self.language
+	}
+
+	/// Language code from the document (`en`, ...).
 	pub fn short_language(&self) -> &str {
 		self.language.as_str()
 	}
@@ -74,69 +164,566 @@ impl Mapping {
 	// defaults to european region codes (maybe).
 	// todo: default to highest population.
 	pub fn long_language(&self) -> String {
-		match self.language {
-			Lang::FRENCH => "fr-FR".into(),
-			Lang::SWEDISH => "sv-SE".into(),
-			Lang::ITALIAN => "it-IT".into(),
-			Lang::SPANISH => "es-ES".into(),
-			Lang::DUTCH => "nl-NL".into(),
-			Lang::CHINESE => "zh-CN".into(),
-			Lang::UKRAINIAN => "uk-UA".into(),
-			Lang::SLOVENIAN => "sl-SI".into(),
-			Lang::RUSSIAN => "ru-RU".into(),
-			Lang::ROMANIAN => "ro-RO".into(),
-			Lang::POLISH => "pl-PL".into(),
-			Lang::JAPANESE => "ja-JP".into(),
-			Lang::GREEK => "el-GR".into(),
-			Lang::DANISH => "da-DK".into(),
-			Lang::CATALAN => "ca-ES".into(),
-			Lang::PORTUGUESE => "pt-PT".into(),
-			Lang::ENGLISH => "en-GB".into(),
-			Lang::GERMAN => "de-DE".into(),
-			lang => lang.as_str().into(),
+		long_language(self.language)
+	}
+}
+
+/// Default long language code for the language of the document.
+pub(crate) fn long_language(lang: Lang) -> String {
+	match lang {
+		Lang::FRENCH => "fr-FR".into(),
+		Lang::SWEDISH => "sv-SE".into(),
+		Lang::ITALIAN => "it-IT".into(),
+		Lang::SPANISH => "es-ES".into(),
+		Lang::DUTCH => "nl-NL".into(),
+		Lang::CHINESE => "zh-CN".into(),
+		Lang::UKRAINIAN => "uk-UA".into(),
+		Lang::SLOVENIAN => "sl-SI".into(),
+		Lang::RUSSIAN => "ru-RU".into(),
+		Lang::ROMANIAN => "ro-RO".into(),
+		Lang::POLISH => "pl-PL".into(),
+		Lang::JAPANESE => "ja-JP".into(),
+		Lang::GREEK => "el-GR".into(),
+		Lang::DANISH => "da-DK".into(),
+		Lang::CATALAN => "ca-ES".into(),
+		Lang::PORTUGUESE => "pt-PT".into(),
+		Lang::ENGLISH => "en-GB".into(),
+		Lang::GERMAN => "de-DE".into(),
+		lang => lang.as_str().into(),
+	}
+}
+
+/// Byte offsets for the UTF-16 code units of the text, with the length of the text for the end.
+pub(crate) fn byte_offsets(text: &str) -> Vec<usize> {
+	let mut bytes = Vec::with_capacity(text.len() + 1);
+	for (index, c) in text.char_indices() {
+		for _ in 0..c.len_utf16() {
+			bytes.push(index);
 		}
 	}
+	bytes.push(text.len());
+	bytes
+}
+
+/// Text and chars of a chunk rebuilt from ranges of the chunk and inserted text.
+pub(crate) struct ChunkBuilder<'a> {
+	text: &'a str,
+	chars: &'a [(Span, Range<u16>)],
+	bytes: Vec<usize>,
+	res: String,
+	res_chars: Vec<(Span, Range<u16>)>,
+}
+
+impl<'a> ChunkBuilder<'a> {
+	pub(crate) fn new(text: &'a str, mapping: &'a Mapping) -> Self {
+		Self {
+			text,
+			chars: &mapping.chars,
+			bytes: byte_offsets(text),
+			res: String::with_capacity(text.len()),
+			res_chars: Vec::with_capacity(mapping.chars.len()),
+		}
+	}
+
+	/// Append the UTF-16 code units of the chunk.
+	pub(crate) fn append(&mut self, range: Range<usize>) {
+		self.res += &self.text[self.bytes[range.start]..self.bytes[range.end]];
+		self.res_chars.extend_from_slice(&self.chars[range]);
+	}
+
+	/// Append text without a location.
+	pub(crate) fn insert(&mut self, text: &str) {
+		self.res += text;
+		self.res_chars
+			.extend(text.encode_utf16().map(|_| (Span::detached(), 0..0)));
+	}
+
+	/// Remove the last char, the appended text must end with a single UTF-16 code unit char.
+	pub(crate) fn pop(&mut self) {
+		self.res.pop();
+		self.res_chars.pop();
+	}
+
+	/// Text built so far.
+	pub(crate) fn text(&self) -> &str {
+		&self.res
+	}
+
+	/// UTF-16 code units built so far.
+	pub(crate) fn len(&self) -> usize {
+		self.res_chars.len()
+	}
+
+	pub(crate) fn finish(self) -> (String, Vec<(Span, Range<u16>)>) {
+		(self.res, self.res_chars)
+	}
+}
+
+/// Typst markup for the chars Typst outputs for the shorthands and smart quotes.
+const MARKUP: [(char, &str); 11] = [
+	('\u{2013}', "--"),
+	('\u{2014}', "---"),
+	('\u{2026}', "..."),
+	('\u{a0}', "~"),
+	('\u{ad}', "-?"),
+	('\u{201c}', "\""),
+	('\u{201d}', "\""),
+	('\u{201e}', "\""),
+	('\u{2018}', "'"),
+	('\u{2019}', "'"),
+	('\u{201a}', "'"),
+];
+
+/// Replacements with the typographic chars as the Typst markup (`–` as `--`, `“` as `"`, ...),
+/// so the replacements match the source and not only the output.
+/// Replacements which are the same as the source are removed, strings in code keep the chars.
+pub fn markup_replacements(
+	replacements: &Arc<[String]>,
+	locations: &[(FileId, Range<usize>)],
+	world: &impl World,
+	source: Option<&Source>,
+) -> Arc<[String]> {
+	let Some((id, range)) = locations.first() else {
+		return replacements.clone();
+	};
+	let source = match source {
+		Some(source) if source.id() == *id => source.clone(),
+		_ => match world.source(*id) {
+			Ok(source) => source,
+			Err(_) => return replacements.clone(),
+		},
+	};
+	let in_string = LinkedNode::new(source.root())
+		.leaf_at(range.start, Side::After)
+		.is_some_and(|leaf| leaf.kind() == SyntaxKind::Str);
+	if in_string {
+		return replacements.clone();
+	}
+	let current = source.text().get(range.clone());
+	let changed = replacements
+		.iter()
+		.any(|replacement| replacement.chars().any(|c| markup(c).is_some()));
+	if changed.not() {
+		return replacements.clone();
+	}
+	replacements
+		.iter()
+		.map(|replacement| {
+			let mut res = String::with_capacity(replacement.len());
+			for c in replacement.chars() {
+				match markup(c) {
+					Some(markup) => res += markup,
+					None => res.push(c),
+				}
+			}
+			res
+		})
+		.filter(|replacement| locations.len() > 1 || Some(replacement.as_str()) != current)
+		.collect()
+}
+
+fn markup(c: char) -> Option<&'static str> {
+	MARKUP
+		.iter()
+		.find(|(typographic, _)| *typographic == c)
+		.map(|(_, markup)| *markup)
 }
 
 const LINE_SPACING: Em = Em::new(0.65);
 
+/// Part of the document to convert.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+	/// Page numbers starting at 1
+	pub pages: Option<RangeInclusive<usize>>,
+	/// Heading text or label of the section
+	pub section: Option<String>,
+	/// Headers and footers of the pages, each distinct text once
+	pub headers_footers: bool,
+	/// Entries of the outlines, which repeat the text of the headings and captions
+	pub outline: bool,
+}
+
+/// Text chunks of about `chunk_size` chars, only for the text in the file if specified.
+/// The headers and footers of the pages and the outlines are skipped.
 pub fn document(
 	doc: &Document,
-	chunk_size: usize,
+	chunk_size: &ChunkSize,
 	file_id: Option<FileId>,
+) -> Vec<(String, Mapping)> {
+	convert(doc, chunk_size, file_id, false, false, |_| {
+		Some((-Abs::inf(), Abs::inf()))
+	})
+}
+
+/// Text of the document split at the pages and where the language changes, without a chunk size.
+/// For other tools using the text, like word counts, the source locations are in the [`Mapping`].
+pub fn text(doc: &Document) -> Vec<(String, Mapping)> {
+	document(doc, &ChunkSize::new(usize::MAX, false), None)
+}
+
+/// Only convert the text in the selected pages and section.
+pub fn document_selection(
+	doc: &Document,
+	chunk_size: &ChunkSize,
+	file_id: Option<FileId>,
+	selection: &Selection,
+) -> anyhow::Result<Vec<(String, Mapping)>> {
+	let section = match &selection.section {
+		Some(section) => Some(section_bounds(doc, section)?),
+		None => None,
+	};
+	let res = convert(
+		doc,
+		chunk_size,
+		file_id,
+		selection.headers_footers,
+		selection.outline,
+		|page| {
+			if let Some(pages) = &selection.pages {
+				if pages.contains(&page).not() {
+					return None;
+				}
+			}
+			let Some((start, end)) = section else {
+				return Some((-Abs::inf(), Abs::inf()));
+			};
+			if page < start.page.get() || end.is_some_and(|end| page > end.page.get()) {
+				return None;
+			}
+			let top = if page == start.page.get() {
+				start.point.y
+			} else {
+				-Abs::inf()
+			};
+			let bottom = match end {
+				Some(end) if page == end.page.get() => end.point.y,
+				_ => Abs::inf(),
+			};
+			Some((top, bottom))
+		},
+	);
+	Ok(res)
+}
+
+/// Convert the pages, `bounds` returns the vertical range to convert for the page number.
+/// The headers and footers are converted once for each distinct text with `headers_footers`,
+/// the entries of the outlines are skipped without `outline`.
+fn convert(
+	doc: &Document,
+	chunk_size: &ChunkSize,
+	file_id: Option<FileId>,
+	headers_footers: bool,
+	outline: bool,
+	bounds: impl Fn(usize) -> Option<(Abs, Abs)>,
 ) -> Vec<(String, Mapping)> {
 	let mut res = Vec::new();
+	let bodies = body_bounds(doc);
+	let mut marginals = HashSet::new();
+	let outline = outline.not().then(|| Outline::new(doc));
 
-	for page in &doc.pages {
+	for (index, page) in doc.pages.iter().enumerate() {
+		let Some(bounds) = bounds(index + 1) else {
+			continue;
+		};
+		let body = bodies[index];
+		let (top, bottom) = body.unwrap_or((-Abs::inf(), Abs::inf()));
 		let mut converter = Converter::new(chunk_size, Lang::ENGLISH);
+		converter.bounds = (bounds.0.max(top), bounds.1.min(bottom));
+		converter.outline = outline.as_ref();
 		converter.frame(&page.frame, Point::zero(), &mut res, file_id);
 		if converter.contains_file {
 			res.push((converter.text, converter.mapping));
 		}
+
+		if headers_footers.not() || body.is_none() {
+			continue;
+		}
+		for marginal in [(-Abs::inf(), top), (bottom, Abs::inf())] {
+			let mut converted = Vec::new();
+			let mut converter = Converter::new(chunk_size, Lang::ENGLISH);
+			converter.bounds = marginal;
+			converter.frame(&page.frame, Point::zero(), &mut converted, file_id);
+			if converter.contains_file {
+				converted.push((converter.text, converter.mapping));
+			}
+			// the same spans on the pages, like `Page 1` and `Page 2`, are converted once
+			res.extend(converted.into_iter().filter(|(_, mapping)| {
+				let mut spans = mapping
+					.chars
+					.iter()
+					.map(|(span, _)| *span)
+					.filter(|span| span.is_detached().not())
+					.collect::<Vec<_>>();
+				spans.dedup();
+				marginals.insert(spans)
+			}));
+		}
+	}
+
+	let captions = doc
+		.introspector
+		.query(&Selector::Elem(FigureElem::elem(), None))
+		.iter()
+		.filter_map(|content| content.to_packed::<FigureElem>())
+		.filter_map(|figure| figure.caption(StyleChain::default()).clone())
+		.flat_map(|caption| body_spans(&caption.body))
+		.collect::<HashSet<_>>();
+	if captions.is_empty() {
+		return res;
+	}
+	res.into_iter()
+		.flat_map(|(text, mapping)| split_captions(text, mapping, &captions))
+		.filter(|(_, mapping)| {
+			mapping.chars.iter().any(|(span, _)| {
+				span.id()
+					.is_some_and(|id| file_id.is_none_or(|file_id| file_id == id))
+			})
+		})
+		.collect()
+}
+
+/// Text of the outline entries, the entries repeat the text of the outlined headings and figures.
+struct Outline {
+	/// Spans of the headings and captions for their locations
+	bodies: HashMap<Location, HashSet<Span>>,
+	/// Spans of the generated titles, like `Contents`, which have the span of the heading
+	titles: HashSet<Span>,
+}
+
+impl Outline {
+	fn new(doc: &Document) -> Self {
+		let mut bodies = HashMap::new();
+		let mut titles = HashSet::new();
+		let selector = Selector::Or(
+			[
+				Selector::Elem(HeadingElem::elem(), None),
+				Selector::Elem(FigureElem::elem(), None),
+			]
+			.into(),
+		);
+		for content in doc.introspector.query(&selector).iter() {
+			let Some(location) = content.location() else {
+				continue;
+			};
+			let spans = if let Some(heading) = content.to_packed::<HeadingElem>() {
+				let spans = body_spans(heading.body());
+				if spans.is_empty().not() && spans.iter().all(|span| *span == content.span()) {
+					titles.insert(content.span());
+				}
+				spans
+			} else if let Some(figure) = content.to_packed::<FigureElem>() {
+				match figure.caption(StyleChain::default()) {
+					Some(caption) => body_spans(&caption.body),
+					None => continue,
+				}
+			} else {
+				continue;
+			};
+			bodies.insert(location, spans.into_iter().collect());
+		}
+		Self { bodies, titles }
+	}
+}
+
+/// Spans of the text, spaces and quotes in the content.
+pub(crate) fn body_spans(body: &Content) -> Vec<Span> {
+	let text = Selector::Or(
+		[
+			Selector::Elem(TextElem::elem(), None),
+			Selector::Elem(SpaceElem::elem(), None),
+			Selector::Elem(SmartQuoteElem::elem(), None),
+		]
+		.into(),
+	);
+	body.query(text)
+		.iter()
+		.map(|content| content.span())
+		.filter(|span| span.is_detached().not())
+		.collect()
+}
+
+/// Split the captions of the figures into separate chunks, the glued text around a figure has bogus errors.
+/// The supplement and numbering (`Figure 1: `) before the caption are part of the caption.
+fn split_captions(
+	text: String,
+	mapping: Mapping,
+	captions: &HashSet<Span>,
+) -> Vec<(String, Mapping)> {
+	let utf16 = text.encode_utf16().collect::<Vec<_>>();
+	// detached chars on the same line, not the line breaks between paragraphs
+	let inline =
+		|index: usize| mapping.chars[index].0.is_detached() && utf16[index] != u16::from(b'\n');
+	let mut runs = Vec::<Range<usize>>::new();
+	for (index, (span, _)) in mapping.chars.iter().enumerate() {
+		if captions.contains(span).not() {
+			continue;
+		}
+		match runs.last_mut() {
+			Some(run) if (run.end..index).all(inline) => run.end = index + 1,
+			_ => {
+				let mut start = index;
+				while start > runs.last().map_or(0, |run| run.end) && inline(start - 1) {
+					start -= 1;
+				}
+				runs.push(start..index + 1);
+			},
+		}
+	}
+	if runs.is_empty() {
+		return vec![(text, mapping)];
+	}
+
+	let bytes = byte_offsets(&text);
+	let part = |range: Range<usize>| {
+		let chunk = Mapping {
+			chars: mapping.chars[range.clone()].to_vec(),
+			language: mapping.language,
+			comment_language: None,
+			disabled_rules: Vec::new(),
+			headings: Vec::new(),
+			heading_rules: Vec::new(),
+			skipped_words: Vec::new(),
+			comment: false,
+		};
+		(
+			text[bytes[range.start]..bytes[range.end]].to_string(),
+			chunk,
+		)
+	};
+	let mut res = Vec::new();
+	let mut last = 0;
+	for run in runs {
+		if last < run.start {
+			res.push(part(last..run.start));
+		}
+		res.push(part(run.clone()));
+		last = run.end;
+	}
+	if last < mapping.chars.len() {
+		res.push(part(last..mapping.chars.len()));
 	}
 	res
 }
 
-struct Converter {
+/// Vertical range of the body for the pages, the headers and footers are above and below the body.
+///
+/// The body is only a group on pages with more than a few items, so the most common range
+/// of the tallest body group is used for all pages with the same size.
+/// Without a body group, like for documents with one short page, no body is detected.
+fn body_bounds(doc: &Document) -> Vec<Option<(Abs, Abs)>> {
+	let mut counts = HashMap::<(Size, (Abs, Abs)), usize>::new();
+	for page in &doc.pages {
+		if let Some(band) = tallest_group(&page.frame) {
+			*counts.entry((page.frame.size(), band)).or_default() += 1;
+		}
+	}
+	doc.pages
+		.iter()
+		.map(|page| {
+			counts
+				.iter()
+				.filter(|((size, _), _)| *size == page.frame.size())
+				.max_by_key(|((_, band), count)| (**count, band.1 - band.0, band.0))
+				.map(|((_, band), _)| *band)
+		})
+		.collect()
+}
+
+/// Typst inlines the body into the page with at most this number of items.
+const INLINE_LIMIT: usize = 5;
+
+/// Vertical range of the tallest group with at least half the page height, which could be the body.
+///
+/// Groups with few items, like a large figure on an inlined body, would clip the text of the body.
+fn tallest_group(frame: &Frame) -> Option<(Abs, Abs)> {
+	frame
+		.items()
+		.filter_map(|(pos, item)| match item {
+			FrameItem::Group(group)
+				if group.frame.height() < frame.height()
+					&& group.frame.height() * 2.0 >= frame.height()
+					&& group.frame.items().len() > INLINE_LIMIT =>
+			{
+				Some((pos.y, pos.y + group.frame.height()))
+			},
+			_ => None,
+		})
+		.max_by_key(|(top, bottom)| *bottom - *top)
+}
+
+/// Start of the heading and start of the next heading with the same or a higher level.
+fn section_bounds(doc: &Document, section: &str) -> anyhow::Result<(Position, Option<Position>)> {
+	let name = section.trim();
+	let label = name.trim_start_matches('<').trim_end_matches('>');
+	let headings = doc
+		.introspector
+		.query(&Selector::Elem(HeadingElem::elem(), None));
+	let headings = headings
+		.iter()
+		.filter_map(|content| {
+			let heading = content.to_packed::<HeadingElem>()?;
+			let position = doc.introspector.position(content.location()?);
+			Some((heading, position))
+		})
+		.collect::<Vec<_>>();
+
+	let Some(index) = headings.iter().position(|(heading, _)| {
+		heading.label().is_some_and(|l| l.as_str() == label)
+			|| heading.body().plain_text().trim() == name
+	}) else {
+		Err(anyhow::anyhow!(
+			"No heading with the text or label '{}' found.",
+			section
+		))?
+	};
+	let (heading, start) = &headings[index];
+	let level = heading.resolve_level(StyleChain::default());
+	let end = headings[(index + 1)..]
+		.iter()
+		.find(|(next, _)| next.resolve_level(StyleChain::default()) <= level)
+		.map(|(_, position)| *position);
+	Ok((*start, end))
+}
+
+struct Converter<'a> {
 	text: String,
 	mapping: Mapping,
 	x: Abs,
 	y: Abs,
 	span: (Span, u16),
-	chunk_size: usize,
+	chunk_size: &'a ChunkSize,
 	contains_file: bool,
+	/// Vertical range of the page to convert
+	bounds: (Abs, Abs),
+	/// Skip the text of the outlines
+	outline: Option<&'a Outline>,
+	/// Line of the last skipped outline entry
+	outline_line: Option<Abs>,
 }
 
-impl Converter {
-	fn new(chunk_size: usize, language: Lang) -> Self {
+impl<'a> Converter<'a> {
+	fn new(chunk_size: &'a ChunkSize, language: Lang) -> Self {
 		Self {
 			text: String::new(),
-			mapping: Mapping { chars: Vec::new(), language },
+			mapping: Mapping {
+				chars: Vec::new(),
+				language,
+				comment_language: None,
+				disabled_rules: Vec::new(),
+				headings: Vec::new(),
+				heading_rules: Vec::new(),
+				skipped_words: Vec::new(),
+				comment: false,
+			},
 			x: Abs::zero(),
 			y: Abs::zero(),
 			span: (Span::detached(), 0),
 			contains_file: false,
 			chunk_size,
+			bounds: (-Abs::inf(), Abs::inf()),
+			outline: None,
+			outline_line: None,
 		}
 	}
 
@@ -154,15 +741,25 @@ impl Converter {
 				Mapping {
 					chars: Vec::new(),
 					language: Lang::ENGLISH,
+					comment_language: None,
+					disabled_rules: Vec::new(),
+					headings: Vec::new(),
+					heading_rules: Vec::new(),
+					skipped_words: Vec::new(),
+					comment: false,
 				},
 			);
 			res.push((text, mapping));
 		}
+		let bounds = self.bounds;
+		let outline = self.outline;
 		*self = Converter::new(self.chunk_size, language);
+		self.bounds = bounds;
+		self.outline = outline;
 	}
 
 	fn insert_parbreak(&mut self, res: &mut Vec<(String, Mapping)>) {
-		if self.mapping.chars.len() > self.chunk_size {
+		if self.mapping.chars.len() > self.chunk_size.get_for(self.mapping.language.as_str()) {
 			self.seperate(res);
 			return;
 		}
@@ -195,11 +792,63 @@ impl Converter {
 		res: &mut Vec<(String, Mapping)>,
 		file_id: Option<FileId>,
 	) {
+		// the outline entries are links to the outlined elements
+		let links = match self.outline {
+			Some(_) => frame
+				.items()
+				.filter_map(|(p, item)| match item {
+					FrameItem::Link(Destination::Location(location), size) => {
+						Some((*p + pos, *size, *location))
+					},
+					_ => None,
+				})
+				.collect(),
+			None => Vec::new(),
+		};
 		for &(p, ref item) in frame.items() {
+			if let FrameItem::Text(text) = item {
+				if self.outline_entry(text, p + pos, &links) {
+					continue;
+				}
+			}
 			self.item(p + pos, item, res, file_id);
 		}
 	}
 
+	/// Text of an outline entry or the title of an outline.
+	/// The entry text is linked to the outlined element, the other text on the line has no location.
+	fn outline_entry(
+		&mut self,
+		text: &TextItem,
+		pos: Point,
+		links: &[(Point, Size, Location)],
+	) -> bool {
+		let Some(outline) = self.outline else {
+			return false;
+		};
+		let mut spans = text.glyphs.iter().map(|glyph| glyph.span.0);
+		if spans.clone().all(|span| outline.titles.contains(&span)) {
+			return true;
+		}
+		let linked = links.iter().find(|(p, size, _)| {
+			pos.x >= p.x && pos.x < p.x + size.x && pos.y >= p.y && pos.y <= p.y + size.y
+		});
+		let entry = linked.is_some_and(|(_, _, location)| {
+			outline.bodies.get(location).is_some_and(|body| {
+				spans
+					.clone()
+					.all(|span| span.is_detached() || body.contains(&span))
+			})
+		});
+		if entry {
+			self.outline_line = Some(pos.y);
+			return true;
+		}
+		// fill and page number of the entry
+		self.outline_line.is_some_and(|line| line.approx_eq(pos.y))
+			&& spans.all(|span| span.is_detached())
+	}
+
 	fn item(
 		&mut self,
 		pos: Point,
@@ -211,6 +860,9 @@ impl Converter {
 		match item {
 			I::Group(g) => self.frame(&g.frame, pos, res, file_id),
 			I::Text(t) => {
+				if pos.y < self.bounds.0 || pos.y >= self.bounds.1 {
+					return;
+				}
 				if self.mapping.language != t.lang {
 					self.seperate(res);
 				}
@@ -0,0 +1,158 @@
+use std::ops::Not;
+
+use typst::syntax::{FileId, Span};
+
+use crate::convert::Mapping;
+
+/// How the text without a location in the checked sources is handled,
+/// like numbering, outline entries, counters and text from other files.
+#[derive(
+	serde::Serialize,
+	serde::Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	Default,
+	PartialEq,
+	Eq
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DetachedText {
+	/// Checked, the suggestions only in the text are dropped
+	#[default]
+	Check,
+	/// Removed before the check
+	Skip,
+	/// Checked, the suggestions are reported at the nearest text with a location
+	Nearest,
+}
+
+impl std::str::FromStr for DetachedText {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"check" => Ok(Self::Check),
+			"skip" => Ok(Self::Skip),
+			"nearest" => Ok(Self::Nearest),
+			_ => Err(anyhow::anyhow!(
+				"Unknown detached text mode '{}', expected 'check', 'skip' or 'nearest'.",
+				s
+			)),
+		}
+	}
+}
+
+/// Apply the `mode` to the text without a location in the file, or in any file for `None`.
+///
+/// The whitespace between the text is kept, so the paragraphs stay separated.
+pub fn detached_text(
+	paragraphs: Vec<(String, Mapping)>,
+	file_id: Option<FileId>,
+	mode: DetachedText,
+) -> Vec<(String, Mapping)> {
+	if mode == DetachedText::Check {
+		return paragraphs;
+	}
+	paragraphs
+		.into_iter()
+		.filter_map(|(text, mapping)| {
+			let located = mapping
+				.chars
+				.iter()
+				.map(|(span, _)| located(*span, file_id))
+				.collect::<Vec<_>>();
+			let (text, mapping) = match mode {
+				DetachedText::Check => (text, mapping),
+				DetachedText::Skip => skip(text, mapping, &located),
+				DetachedText::Nearest => nearest(text, mapping, &located),
+			};
+			mapping.chars.is_empty().not().then_some((text, mapping))
+		})
+		.collect()
+}
+
+fn located(span: Span, file_id: Option<FileId>) -> bool {
+	span.id()
+		.is_some_and(|id| file_id.is_none_or(|file_id| file_id == id))
+}
+
+/// Remove the text without a location, the whitespace between the remaining text is collapsed.
+fn skip(text: String, mapping: Mapping, located: &[bool]) -> (String, Mapping) {
+	if located.iter().all(|located| *located) {
+		return (text, mapping);
+	}
+	let mut res = String::with_capacity(text.len());
+	let mut chars = Vec::with_capacity(mapping.chars.len());
+	// whitespace without a location since the last kept char
+	let mut pending = None::<&str>;
+	let mut removed = false;
+	let mut index = 0;
+	for c in text.chars() {
+		let units = index..index + c.len_utf16();
+		index = units.end;
+		if located[units.start] {
+			// no double space around removed text in a sentence
+			if removed && c == ' ' && res.ends_with(' ') {
+				continue;
+			}
+			removed = false;
+			if let Some(separator) = pending.take() {
+				let collapsed =
+					separator == " " && (res.is_empty() || res.ends_with(char::is_whitespace));
+				if collapsed.not() {
+					res += separator;
+					chars.extend(separator.chars().map(|_| (Span::detached(), 0..0)));
+				}
+			}
+			res.push(c);
+			chars.extend_from_slice(&mapping.chars[units]);
+		} else if c == '\n' {
+			pending = Some("\n\n");
+		} else if c.is_whitespace() {
+			pending = pending.or(Some(" "));
+		} else {
+			removed = true;
+		}
+	}
+	(res, Mapping { chars, ..mapping })
+}
+
+/// Use the location of the nearest char with a location for the chars without one, the whitespace is unchanged.
+fn nearest(text: String, mut mapping: Mapping, located: &[bool]) -> (String, Mapping) {
+	let mut previous = vec![None; located.len()];
+	let mut last = None;
+	for (index, located) in located.iter().enumerate() {
+		if *located {
+			last = Some(index);
+		}
+		previous[index] = last;
+	}
+	let mut next = vec![None; located.len()];
+	let mut last = None;
+	for (index, located) in located.iter().enumerate().rev() {
+		if *located {
+			last = Some(index);
+		}
+		next[index] = last;
+	}
+
+	let mut index = 0;
+	for c in text.chars() {
+		let units = index..index + c.len_utf16();
+		index = units.end;
+		if located[units.start] || c.is_whitespace() {
+			continue;
+		}
+		let source = match (previous[units.start], next[units.start]) {
+			(Some(previous), Some(next)) if next - units.start < units.start - previous => next,
+			(Some(previous), _) => previous,
+			(None, Some(next)) => next,
+			(None, None) => continue,
+		};
+		for unit in units {
+			mapping.chars[unit] = mapping.chars[source].clone();
+		}
+	}
+	(text, mapping)
+}
@@ -0,0 +1,193 @@
+use std::{
+	collections::HashMap,
+	ops::Not,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+};
+
+use typst::syntax::FileId;
+
+use crate::{
+	backends::{
+		dictionary::{self, AllowedWords},
+		rules::matches_language,
+	},
+	convert::Mapping,
+	LanguageToolOptions, Suggestion,
+};
+
+/// Options files in the directories of the root, which change the options for the files below them.
+pub const DIRECTORY_OPTIONS_FILES: [&str; 4] = [
+	".typst-languagetool.json",
+	".typst-languagetool.toml",
+	".typst-languagetool.yaml",
+	".typst-languagetool.yml",
+];
+
+/// Languages, allowed words and disabled checks from the [`DIRECTORY_OPTIONS_FILES`].
+///
+/// The options files are merged from the root to the directory of the file with [`LanguageToolOptions::overwrite`].
+/// The backend is shared by all files, so the allowed words and disabled checks are applied to the suggestions.
+#[derive(Debug)]
+pub struct DirectoryOptions {
+	root: PathBuf,
+	/// Merged options for each directory, `None` without options files
+	directories: Mutex<HashMap<PathBuf, Option<Arc<Overrides>>>>,
+}
+
+#[derive(Debug)]
+struct Overrides {
+	options: LanguageToolOptions,
+	/// Words from the dictionary files for all languages
+	shared_words: Vec<String>,
+	/// Matchers for the `dictionary` and the `shared_words`
+	dictionary: Vec<(String, AllowedWords)>,
+	shared: AllowedWords,
+}
+
+impl Overrides {
+	fn new(options: LanguageToolOptions, shared_words: Vec<String>) -> Self {
+		let dictionary = options
+			.dictionary
+			.iter()
+			.map(|(lang, words)| {
+				let mut allowed = AllowedWords::default();
+				for word in words {
+					allowed.insert(word, false);
+				}
+				(lang.clone(), allowed)
+			})
+			.collect();
+		let mut shared = AllowedWords::default();
+		for word in &shared_words {
+			shared.insert(word, false);
+		}
+		Self {
+			options,
+			shared_words,
+			dictionary,
+			shared,
+		}
+	}
+}
+
+impl DirectoryOptions {
+	pub fn new(root: &Path) -> Self {
+		Self {
+			root: root.to_owned(),
+			directories: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Read the options files again on the next use.
+	pub fn clear(&self) {
+		self.directories.lock().unwrap().clear();
+	}
+
+	/// Language for the chunk from the options of the file of the first char, see [`LanguageToolOptions::language`].
+	pub fn language(&self, mapping: &Mapping) -> Option<String> {
+		let overrides = self.file(mapping.file_id(0..usize::MAX)?)?;
+		overrides.options.language(mapping)
+	}
+
+	/// Remove the suggestions for disabled checks and allowed words in the files of the suggestions.
+	pub fn retain(
+		&self,
+		text: &str,
+		lang: &str,
+		mapping: &Mapping,
+		suggestions: &mut Vec<Suggestion>,
+	) {
+		let utf16 = text.encode_utf16().collect::<Vec<_>>();
+		suggestions.retain(|suggestion| {
+			let Some(overrides) = mapping
+				.file_id(suggestion.start..suggestion.end)
+				.and_then(|id| self.file(id))
+			else {
+				return true;
+			};
+			let disabled = overrides
+				.options
+				.disabled_checks
+				.iter()
+				.filter(|(filter, _)| matches_language(filter, lang))
+				.any(|(_, checks)| checks.iter().any(|check| **check == *suggestion.rule_id));
+			if disabled {
+				return false;
+			}
+			if suggestion.is_spelling().not() {
+				return true;
+			}
+			let allowed = overrides
+				.dictionary
+				.iter()
+				.filter(|(filter, _)| matches_language(filter, lang))
+				.map(|(_, allowed)| allowed)
+				.chain([&overrides.shared])
+				.any(|allowed| allowed.contains(&utf16, suggestion.start, suggestion.end));
+			allowed.not()
+		});
+	}
+
+	fn file(&self, id: FileId) -> Option<Arc<Overrides>> {
+		if id.package().is_some() {
+			return None;
+		}
+		let path = id.vpath().resolve(&self.root)?;
+		self.directory(path.parent()?)
+	}
+
+	fn directory(&self, directory: &Path) -> Option<Arc<Overrides>> {
+		if directory.starts_with(&self.root).not() {
+			return None;
+		}
+		if let Some(overrides) = self.directories.lock().unwrap().get(directory) {
+			return overrides.clone();
+		}
+		let parent = if directory == self.root {
+			None
+		} else {
+			directory.parent().and_then(|parent| self.directory(parent))
+		};
+		let overrides = match (parent, load(directory)) {
+			(parent, None) => parent,
+			(None, Some(overrides)) => Some(Arc::new(overrides)),
+			(Some(parent), Some(overrides)) => {
+				let mut shared_words = parent.shared_words.clone();
+				shared_words.extend(overrides.shared_words);
+				Some(Arc::new(Overrides::new(
+					parent.options.clone().overwrite(overrides.options),
+					shared_words,
+				)))
+			},
+		};
+		self.directories
+			.lock()
+			.unwrap()
+			.insert(directory.to_owned(), overrides.clone());
+		overrides
+	}
+}
+
+/// Options file in the directory, invalid files are skipped with a warning.
+fn load(directory: &Path) -> Option<Overrides> {
+	let path = DIRECTORY_OPTIONS_FILES
+		.iter()
+		.map(|name| directory.join(name))
+		.find(|path| path.is_file())?;
+	let result = LanguageToolOptions::load(&path).and_then(|mut options| {
+		let (file_dictionary, shared_words) =
+			dictionary::load(&options.dictionary_files, &options.dictionary_imports)?;
+		for (lang, words) in file_dictionary {
+			options.dictionary.entry(lang).or_default().extend(words);
+		}
+		Ok(Overrides::new(options, shared_words))
+	});
+	match result {
+		Ok(overrides) => Some(overrides),
+		Err(err) => {
+			tracing::warn!("Skipped options {}: {}", path.display(), err);
+			None
+		},
+	}
+}
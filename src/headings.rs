@@ -0,0 +1,148 @@
+use std::{
+	collections::HashSet,
+	ops::{Not, Range},
+};
+
+use typst::{
+	foundations::{NativeElement, Selector},
+	model::{Document, HeadingElem},
+	syntax::Span,
+};
+
+use crate::convert::{body_spans, ChunkBuilder, Mapping};
+
+/// How the text of the headings is checked.
+#[derive(
+	serde::Serialize,
+	serde::Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	Default,
+	PartialEq,
+	Eq
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Headings {
+	/// Part of the text like a paragraph
+	#[default]
+	Text,
+	/// Independent sentences, a period is added after headings without punctuation
+	Sentence,
+	/// Not checked
+	Skip,
+}
+
+impl std::str::FromStr for Headings {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"text" => Ok(Self::Text),
+			"sentence" => Ok(Self::Sentence),
+			"skip" => Ok(Self::Skip),
+			_ => Err(anyhow::anyhow!(
+				"Unknown headings mode '{}', expected 'text', 'sentence' or 'skip'.",
+				s
+			)),
+		}
+	}
+}
+
+/// Apply the `mode` to the text of the headings in the chunks, the `disabled_checks` are skipped in the headings.
+///
+/// The headings are found with the spans of the text in their bodies, the numbering is not part of the heading.
+pub fn headings(
+	doc: &Document,
+	paragraphs: Vec<(String, Mapping)>,
+	mode: Headings,
+	disabled_checks: &[String],
+) -> Vec<(String, Mapping)> {
+	if mode == Headings::Text && disabled_checks.is_empty() {
+		return paragraphs;
+	}
+	let spans = heading_spans(doc);
+	if spans.is_empty() {
+		return paragraphs;
+	}
+	paragraphs
+		.into_iter()
+		.filter_map(|(text, mapping)| {
+			let (text, mut mapping) = apply(text, mapping, &spans, mode);
+			if mapping.chars.is_empty() {
+				return None;
+			}
+			if mapping.headings.is_empty().not() {
+				mapping.heading_rules = disabled_checks.to_vec();
+			}
+			Some((text, mapping))
+		})
+		.collect()
+}
+
+/// Spans of the text, spaces and quotes in the bodies of the headings.
+fn heading_spans(doc: &Document) -> HashSet<Span> {
+	doc.introspector
+		.query(&Selector::Elem(HeadingElem::elem(), None))
+		.iter()
+		.filter_map(|content| content.to_packed::<HeadingElem>())
+		.flat_map(|heading| body_spans(heading.body()))
+		.collect()
+}
+
+/// Chunk with the headings changed for the mode and the ranges of the remaining headings.
+fn apply(
+	text: String,
+	mapping: Mapping,
+	spans: &HashSet<Span>,
+	mode: Headings,
+) -> (String, Mapping) {
+	let runs = runs(&mapping, spans);
+	if runs.is_empty() {
+		return (text, mapping);
+	}
+
+	let mut builder = ChunkBuilder::new(&text, &mapping);
+	let mut headings = Vec::new();
+	let mut last = 0;
+	for run in runs {
+		builder.append(last..run.start);
+		last = run.end;
+		if mode == Headings::Skip {
+			continue;
+		}
+		let start = builder.len();
+		let byte_start = builder.text().len();
+		builder.append(run);
+		let heading = &builder.text()[byte_start..];
+		let punctuated = heading.trim_end().ends_with(['.', '!', '?', ':', ';']);
+		if mode == Headings::Sentence && punctuated.not() {
+			builder.insert(".");
+		}
+		headings.push(start..builder.len());
+	}
+	builder.append(last..mapping.chars.len());
+
+	let (res, chars) = builder.finish();
+	let mapping = Mapping { chars, headings, ..mapping };
+	(res, mapping)
+}
+
+/// Ranges of consecutive chars in the headings (UTF-16 code units of the chunk),
+/// the space inserted for a line break in a heading is part of the heading.
+fn runs(mapping: &Mapping, spans: &HashSet<Span>) -> Vec<Range<usize>> {
+	let mut runs = Vec::<Range<usize>>::new();
+	for (index, (span, _)) in mapping.chars.iter().enumerate() {
+		if spans.contains(span).not() {
+			continue;
+		}
+		match runs.last_mut() {
+			Some(run) if run.end == index => run.end = index + 1,
+			Some(run) if run.end + 1 == index && mapping.chars[run.end].0.is_detached() => {
+				run.end = index + 1
+			},
+			_ => runs.push(index..index + 1),
+		}
+	}
+	runs
+}
@@ -1,74 +1,347 @@
+//! Check typst documents with LanguageTool.
+//!
+//! The checking pipeline without the command line and language server:
+//! 1. Create a backend from [`LanguageToolOptions`] with [`LanguageTool::new`].
+//! 2. Convert a compiled document into text chunks with [`convert::document`].
+//! 3. Check the chunks with [`LanguageToolBackend::check_texts`].
+//! 4. Map the suggestions back to the source with a [`FileCollector`].
+//!
+//! ```no_run
+//! use typst::{model::Document, World};
+//! use typst_languagetool::{
+//!     Cancellation, ChunkSize, Diagnostic, FileCollector, LanguageTool, LanguageToolBackend,
+//!     LanguageToolOptions,
+//! };
+//!
+//! async fn check(
+//!     doc: &Document,
+//!     world: &impl World,
+//!     options: &LanguageToolOptions,
+//! ) -> anyhow::Result<Vec<Diagnostic>> {
+//!     let mut lt = LanguageTool::new(options).await?;
+//!     let chunk_size = ChunkSize::new(options.chunk_size, false)
+//!         .with_languages(options.language_chunk_sizes.clone());
+//!     let paragraphs = typst_languagetool::convert::document(doc, &chunk_size, None);
+//!     let texts = paragraphs
+//!         .iter()
+//!         .map(|(text, mapping)| (mapping.long_language(), text.clone()))
+//!         .collect::<Vec<_>>();
+//!     let results = lt
+//!         .check_texts(&texts, options.jobs, &Cancellation::new())
+//!         .await?;
+//!
+//!     let mut collector = FileCollector::new(None, world);
+//!     for ((_, mapping), suggestions) in paragraphs.iter().zip(results) {
+//!         collector.add(world, &suggestions, mapping);
+//!     }
+//!     Ok(collector.finish())
+//! }
+//! ```
+//!
+//! The conversion can be used without a backend, [`convert::text`] returns the text of a document
+//! and [`convert::Mapping::source_location`] maps ranges of the text back to the source.
+//!
+//! The backends are enabled with features, at least one is required:
+//! - `bundle`: bundled LanguageTool with the JVM (requires maven to build)
+//! - `jar`: LanguageTool jar with the JVM
+//! - `server`: remote or managed LanguageTool server
+//! - `hunspell`: hunspell dictionaries, only spelling
+//! - `mock`: scripted suggestions for tests
+//!
+//! The crate follows semantic versioning, the items documented here are the public API.
+
 mod backends;
+mod bibliography;
+mod cache;
+mod cancellation;
+mod chunk_size;
+mod comments;
 pub mod convert;
+mod detached;
+#[cfg(feature = "fs")]
+mod directory;
+mod headings;
+mod lists;
+mod metadata;
+mod profile;
+#[cfg(feature = "fs")]
+mod project;
+mod validate;
+mod words;
 
-use std::{collections::HashMap, ops::Range, path::PathBuf};
+use std::{
+	collections::{HashMap, HashSet},
+	ops::{Not, Range},
+	path::PathBuf,
+	sync::Arc,
+};
 
 #[allow(unused_imports)]
 pub use backends::*;
+pub use bibliography::bibliography;
+pub use cache::{Cache, Incremental, DEFAULT_CACHE_SIZE};
+pub use cancellation::{Cancellation, Cancelled};
+pub use chunk_size::{ChunkSize, MAX_CHUNK_SIZE};
+pub use comments::{ltex_comments, source_comments};
 use convert::Mapping;
+pub use detached::{detached_text, DetachedText};
+#[cfg(feature = "fs")]
+pub use directory::{DirectoryOptions, DIRECTORY_OPTIONS_FILES};
+pub use headings::{headings, Headings};
+pub use lists::list_items;
+pub use metadata::{DocumentOptions, METADATA_LABEL};
+pub use profile::Profile;
+#[cfg(feature = "fs")]
+pub use project::{Project, MANIFEST_FILE};
 use typst::{
 	syntax::{FileId, Source},
 	World,
 };
+pub use validate::OptionsWarning;
+pub use words::skip_words;
 
-#[cfg(not(any(feature = "bundle", feature = "jar", feature = "server",)))]
+#[cfg(not(any(
+	feature = "bundle",
+	feature = "jar",
+	feature = "remote",
+	feature = "hunspell",
+	feature = "mock"
+)))]
 compile_error!("No backends enabled, the backends can be enabled with feature flags");
 
+/// Shared interface of the backends.
 #[allow(async_fn_in_trait)]
 pub trait LanguageToolBackend {
+	/// Words not reported as spelling mistakes for the language.
 	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()>;
+	/// Rule ids not reported for the language.
 	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()>;
-	async fn check_text(&mut self, lang: String, text: &str) -> anyhow::Result<Vec<Suggestion>>;
+	/// Only check spelling or grammar.
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()>;
+	/// Stops with [`Cancelled`] if the check is cancelled.
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>>;
+	/// Check the `(lang, text)` pairs with up to `jobs` concurrent checks, the results are in the same order.
+	/// Backends without concurrent checks check the texts one after another.
+	/// Every checked text is counted in [`Cancellation::checked`].
+	async fn check_texts(
+		&mut self,
+		texts: &[(String, String)],
+		_jobs: usize,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+		let mut results = Vec::with_capacity(texts.len());
+		for (lang, text) in texts {
+			results.push(self.check_text(lang.clone(), text, cancellation).await?);
+			cancellation.add_checked();
+		}
+		Ok(results)
+	}
+	/// Languages supported by the backend.
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>>;
+	/// Active rules for the language.
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>>;
 }
 
+/// Backend selected by the [`BackendOptions`].
 #[derive(Debug)]
 pub enum LanguageTool {
 	#[cfg(any(feature = "bundle", feature = "jar"))]
 	JNI(jni::LanguageToolJNI),
-	#[cfg(feature = "server")]
+	#[cfg(feature = "remote")]
 	Remote(remote::LanguageToolRemote),
+	#[cfg(feature = "server")]
+	Managed(managed::LanguageToolManaged),
+	#[cfg(feature = "hunspell")]
+	Hunspell(hunspell::LanguageToolHunspell),
+	#[cfg(feature = "mock")]
+	Mock(mock::LanguageToolMock),
+	Composite(composite::LanguageToolComposite),
+	Rules(rules::LanguageToolRules),
+	#[cfg(feature = "fs")]
+	Vale(vale::LanguageToolVale),
+	Fallback(fallback::LanguageToolFallback),
+	Dictionary(dictionary::LanguageToolDictionary),
 }
 
 impl LanguageTool {
+	/// Start the backend and apply the dictionary, disabled checks and check mode.
 	pub async fn new(options: &LanguageToolOptions) -> anyhow::Result<Self> {
+		Self::start(options).await?.configure(options).await
+	}
+
+	/// Like [`LanguageTool::new`], but validate the languages, dictionaries and disabled checks with the backend first.
+	pub async fn new_validated(
+		options: &LanguageToolOptions,
+	) -> anyhow::Result<(Self, Vec<OptionsWarning>)> {
+		let mut lt = Self::start(options).await?;
+		// disabled rules are not listed as rules, so the checks are disabled after the validation
+		let warnings = validate::validate(&mut lt, options).await;
+		let lt = match lt.configure(options).await {
+			Ok(lt) => lt,
+			// the warnings may explain the error
+			Err(err) if warnings.is_empty().not() => {
+				let warnings = warnings.iter().map(ToString::to_string).collect::<Vec<_>>();
+				Err(anyhow::anyhow!("{} ({})", err, warnings.join(" ")))?
+			},
+			Err(err) => Err(err)?,
+		};
+		Ok((lt, warnings))
+	}
+
+	/// Message for the switch to the `fallback` backend since the last call, to show it to the user.
+	pub fn take_fallback_switch(&mut self) -> Option<String> {
+		match self {
+			Self::Fallback(lt) => lt.take_switched(),
+			Self::Composite(lt) => lt.take_fallback_switch(),
+			Self::Dictionary(lt) => lt.take_fallback_switch(),
+			_ => None,
+		}
+	}
+
+	async fn start(options: &LanguageToolOptions) -> anyhow::Result<Self> {
 		let mut lt = match &options.backend {
 			None => Err(anyhow::anyhow!(
-				"No Languagetool Backend (bundle, jar or server) specified."
+				"No Languagetool Backend (bundle, jar, server, managed, hunspell or composite) specified."
 			))?,
+			Some(backend) => match &options.fallback {
+				None => Self::from_backend(backend)?,
+				Some(fallback) => Self::Fallback(
+					fallback::LanguageToolFallback::new(
+						Self::from_backend(backend),
+						fallback.clone(),
+					)
+					.await?,
+				),
+			},
+		};
+		let mut additional = Vec::new();
+		if options.rules.is_empty().not() {
+			additional.push(Self::Rules(rules::LanguageToolRules::new(&options.rules)?));
+		}
+		#[cfg(feature = "fs")]
+		if let Some(styles_path) = &options.styles_path {
+			additional.push(Self::Vale(vale::LanguageToolVale::new(
+				styles_path,
+				&options.styles,
+			)?));
+		}
+		#[cfg(not(feature = "fs"))]
+		if options.styles_path.is_some() {
+			Err(anyhow::anyhow!(
+				"Feature 'fs' is disabled, 'styles_path' is not supported."
+			))?;
+		}
+		if additional.is_empty().not() {
+			additional.insert(0, lt);
+			lt = Self::Composite(composite::LanguageToolComposite::new(additional)?);
+		}
+		Ok(lt)
+	}
+
+	async fn configure(mut self, options: &LanguageToolOptions) -> anyhow::Result<Self> {
+		let (file_dictionary, shared_words) =
+			dictionary::load(&options.dictionary_files, &options.dictionary_imports)?;
+		for (lang, dict) in options.dictionary.iter().chain(&file_dictionary) {
+			self.allow_words(lang.clone(), dict).await?;
+		}
+		for (lang, checks) in &options.disabled_checks {
+			self.disable_checks(lang.clone(), checks).await?;
+		}
+		if let Some(mode) = options.check_mode {
+			self.set_check_mode(mode).await?;
+		}
+		if shared_words.is_empty().not() {
+			self = Self::Dictionary(dictionary::LanguageToolDictionary::new(self, shared_words));
+		}
+
+		Ok(self)
+	}
 
+	/// Check if the backend is reachable, the other backends fail on creation.
+	async fn ping(&mut self) -> anyhow::Result<()> {
+		match self {
+			#[cfg(feature = "remote")]
+			Self::Remote(lt) => lt.ping().await,
+			#[cfg(feature = "server")]
+			Self::Managed(lt) => lt.ping().await,
+			Self::Composite(lt) => lt.ping().await,
+			_ => Ok(()),
+		}
+	}
+
+	fn from_backend(backend: &BackendOptions) -> anyhow::Result<Self> {
+		let lt = match backend {
 			#[cfg(feature = "bundle")]
-			Some(BackendOptions::Bundle) => Self::JNI(jni::LanguageToolJNI::new_bundled()?),
+			BackendOptions::Bundle { jni } => Self::JNI(jni::LanguageToolJNI::new_bundled(jni)?),
 
 			#[cfg(not(feature = "bundle"))]
-			Some(BackendOptions::Bundle) => Err(anyhow::anyhow!("Feature 'bundle' is disabled."))?,
+			BackendOptions::Bundle { .. } => Err(anyhow::anyhow!("Feature 'bundle' is disabled."))?,
 
 			#[cfg(any(feature = "bundle", feature = "jar"))]
-			Some(BackendOptions::Jar { jar_location }) => {
-				Self::JNI(jni::LanguageToolJNI::new(jar_location)?)
+			BackendOptions::Jar { jar_location, jni } => {
+				Self::JNI(jni::LanguageToolJNI::new(jar_location, jni)?)
 			},
 			#[cfg(all(not(feature = "bundle"), not(feature = "jar")))]
-			Some(BackendOptions::Jar { jar_location: _ }) => {
-				Err(anyhow::anyhow!("Features 'bundle' and 'jar' are disabled."))?
-			},
+			BackendOptions::Jar { .. } => Err(anyhow::anyhow!("Features 'bundle' and 'jar' are disabled."))?,
+
+			#[cfg(feature = "remote")]
+			BackendOptions::Remote {
+				host,
+				port,
+				timeout,
+				retries,
+				continue_on_error,
+				username,
+				api_key,
+				ignore_case,
+				server_dictionary,
+			} => Self::Remote(
+				remote::LanguageToolRemote::new(
+					host,
+					port,
+					timeout.map(std::time::Duration::from_secs_f64),
+					*retries,
+					*continue_on_error,
+					username.as_deref().zip(api_key.as_deref()),
+					*ignore_case,
+				)?
+				.with_dictionary(server_dictionary.clone()),
+			),
+
+			#[cfg(not(feature = "remote"))]
+			BackendOptions::Remote { .. } => Err(anyhow::anyhow!("Feature 'remote' is disabled."))?,
 
 			#[cfg(feature = "server")]
-			Some(BackendOptions::Remote { host, port }) => {
-				Self::Remote(remote::LanguageToolRemote::new(host, port)?)
-			},
+			BackendOptions::Managed { server_location, server_port, java } => Self::Managed(
+				managed::LanguageToolManaged::new(server_location, *server_port, java.as_deref())?,
+			),
 
 			#[cfg(not(feature = "server"))]
-			Some(BackendOptions::Remote { host: _, port: _ }) => {
-				Err(anyhow::anyhow!("Feature 'server' is disabled."))?
+			BackendOptions::Managed { .. } => Err(anyhow::anyhow!("Feature 'server' is disabled."))?,
+
+			#[cfg(feature = "hunspell")]
+			BackendOptions::Hunspell { hunspell_directory } => {
+				Self::Hunspell(hunspell::LanguageToolHunspell::new(hunspell_directory)?)
 			},
-		};
 
-		for (lang, dict) in &options.dictionary {
-			lt.allow_words(lang.clone(), dict).await?;
-		}
-		for (lang, checks) in &options.disabled_checks {
-			lt.disable_checks(lang.clone(), checks).await?;
-		}
+			#[cfg(not(feature = "hunspell"))]
+			BackendOptions::Hunspell { hunspell_directory: _ } => {
+				Err(anyhow::anyhow!("Feature 'hunspell' is disabled."))?
+			},
 
+			BackendOptions::Composite { backends } => {
+				let backends = backends
+					.iter()
+					.map(Self::from_backend)
+					.collect::<anyhow::Result<Vec<_>>>()?;
+				Self::Composite(composite::LanguageToolComposite::new(backends)?)
+			},
+		};
 		Ok(lt)
 	}
 }
@@ -78,8 +351,20 @@ impl LanguageToolBackend for LanguageTool {
 		match self {
 			#[cfg(any(feature = "bundle", feature = "jar"))]
 			Self::JNI(lt) => lt.allow_words(lang, words).await,
-			#[cfg(feature = "server")]
+			#[cfg(feature = "remote")]
 			Self::Remote(lt) => lt.allow_words(lang, words).await,
+			#[cfg(feature = "server")]
+			Self::Managed(lt) => lt.allow_words(lang, words).await,
+			#[cfg(feature = "hunspell")]
+			Self::Hunspell(lt) => lt.allow_words(lang, words).await,
+			#[cfg(feature = "mock")]
+			Self::Mock(lt) => lt.allow_words(lang, words).await,
+			Self::Composite(lt) => lt.allow_words(lang, words).await,
+			Self::Rules(lt) => lt.allow_words(lang, words).await,
+			#[cfg(feature = "fs")]
+			Self::Vale(lt) => lt.allow_words(lang, words).await,
+			Self::Fallback(lt) => lt.allow_words(lang, words).await,
+			Self::Dictionary(lt) => lt.allow_words(lang, words).await,
 
 			#[allow(unreachable_patterns)]
 			_ => unreachable!("{:?} {:?}", lang, words),
@@ -89,53 +374,222 @@ impl LanguageToolBackend for LanguageTool {
 		match self {
 			#[cfg(any(feature = "bundle", feature = "jar"))]
 			Self::JNI(lt) => lt.disable_checks(lang, checks).await,
-			#[cfg(feature = "server")]
+			#[cfg(feature = "remote")]
 			Self::Remote(lt) => lt.disable_checks(lang, checks).await,
+			#[cfg(feature = "server")]
+			Self::Managed(lt) => lt.disable_checks(lang, checks).await,
+			#[cfg(feature = "hunspell")]
+			Self::Hunspell(lt) => lt.disable_checks(lang, checks).await,
+			#[cfg(feature = "mock")]
+			Self::Mock(lt) => lt.disable_checks(lang, checks).await,
+			Self::Composite(lt) => lt.disable_checks(lang, checks).await,
+			Self::Rules(lt) => lt.disable_checks(lang, checks).await,
+			#[cfg(feature = "fs")]
+			Self::Vale(lt) => lt.disable_checks(lang, checks).await,
+			Self::Fallback(lt) => lt.disable_checks(lang, checks).await,
+			Self::Dictionary(lt) => lt.disable_checks(lang, checks).await,
 
 			#[allow(unreachable_patterns)]
 			_ => unreachable!("{:?} {:?}", lang, checks),
 		}
 	}
-	async fn check_text(&mut self, lang: String, text: &str) -> anyhow::Result<Vec<Suggestion>> {
+	async fn set_check_mode(&mut self, mode: CheckMode) -> anyhow::Result<()> {
 		match self {
 			#[cfg(any(feature = "bundle", feature = "jar"))]
-			Self::JNI(lt) => lt.check_text(lang, text).await,
+			Self::JNI(lt) => lt.set_check_mode(mode).await,
+			#[cfg(feature = "remote")]
+			Self::Remote(lt) => lt.set_check_mode(mode).await,
 			#[cfg(feature = "server")]
-			Self::Remote(lt) => lt.check_text(lang, text).await,
+			Self::Managed(lt) => lt.set_check_mode(mode).await,
+			#[cfg(feature = "hunspell")]
+			Self::Hunspell(lt) => lt.set_check_mode(mode).await,
+			#[cfg(feature = "mock")]
+			Self::Mock(lt) => lt.set_check_mode(mode).await,
+			Self::Composite(lt) => lt.set_check_mode(mode).await,
+			Self::Rules(lt) => lt.set_check_mode(mode).await,
+			#[cfg(feature = "fs")]
+			Self::Vale(lt) => lt.set_check_mode(mode).await,
+			Self::Fallback(lt) => lt.set_check_mode(mode).await,
+			Self::Dictionary(lt) => lt.set_check_mode(mode).await,
+
+			#[allow(unreachable_patterns)]
+			_ => unreachable!("{:?}", mode),
+		}
+	}
+	async fn check_text(
+		&mut self,
+		lang: String,
+		text: &str,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Suggestion>> {
+		cancellation.check()?;
+		match self {
+			#[cfg(any(feature = "bundle", feature = "jar"))]
+			Self::JNI(lt) => lt.check_text(lang, text, cancellation).await,
+			#[cfg(feature = "remote")]
+			Self::Remote(lt) => lt.check_text(lang, text, cancellation).await,
+			#[cfg(feature = "server")]
+			Self::Managed(lt) => lt.check_text(lang, text, cancellation).await,
+			#[cfg(feature = "hunspell")]
+			Self::Hunspell(lt) => lt.check_text(lang, text, cancellation).await,
+			#[cfg(feature = "mock")]
+			Self::Mock(lt) => lt.check_text(lang, text, cancellation).await,
+			Self::Composite(lt) => lt.check_text(lang, text, cancellation).await,
+			Self::Rules(lt) => lt.check_text(lang, text, cancellation).await,
+			#[cfg(feature = "fs")]
+			Self::Vale(lt) => lt.check_text(lang, text, cancellation).await,
+			Self::Fallback(lt) => lt.check_text(lang, text, cancellation).await,
+			Self::Dictionary(lt) => lt.check_text(lang, text, cancellation).await,
 
 			#[allow(unreachable_patterns)]
 			_ => unreachable!("{:?} {:?}", lang, text),
 		}
 	}
+	async fn check_texts(
+		&mut self,
+		texts: &[(String, String)],
+		jobs: usize,
+		cancellation: &Cancellation,
+	) -> anyhow::Result<Vec<Vec<Suggestion>>> {
+		cancellation.check()?;
+		match self {
+			#[cfg(any(feature = "bundle", feature = "jar"))]
+			Self::JNI(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			#[cfg(feature = "remote")]
+			Self::Remote(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			#[cfg(feature = "server")]
+			Self::Managed(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			#[cfg(feature = "hunspell")]
+			Self::Hunspell(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			#[cfg(feature = "mock")]
+			Self::Mock(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			Self::Composite(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			Self::Rules(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			#[cfg(feature = "fs")]
+			Self::Vale(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			Self::Fallback(lt) => lt.check_texts(texts, jobs, cancellation).await,
+			Self::Dictionary(lt) => lt.check_texts(texts, jobs, cancellation).await,
+
+			#[allow(unreachable_patterns)]
+			_ => unreachable!("{:?}", texts),
+		}
+	}
+	async fn list_languages(&mut self) -> anyhow::Result<Vec<Language>> {
+		match self {
+			#[cfg(any(feature = "bundle", feature = "jar"))]
+			Self::JNI(lt) => lt.list_languages().await,
+			#[cfg(feature = "remote")]
+			Self::Remote(lt) => lt.list_languages().await,
+			#[cfg(feature = "server")]
+			Self::Managed(lt) => lt.list_languages().await,
+			#[cfg(feature = "hunspell")]
+			Self::Hunspell(lt) => lt.list_languages().await,
+			#[cfg(feature = "mock")]
+			Self::Mock(lt) => lt.list_languages().await,
+			Self::Composite(lt) => lt.list_languages().await,
+			Self::Rules(lt) => lt.list_languages().await,
+			#[cfg(feature = "fs")]
+			Self::Vale(lt) => lt.list_languages().await,
+			Self::Fallback(lt) => lt.list_languages().await,
+			Self::Dictionary(lt) => lt.list_languages().await,
+
+			#[allow(unreachable_patterns)]
+			_ => unreachable!(),
+		}
+	}
+	async fn list_rules(&mut self, lang: String) -> anyhow::Result<Vec<RuleInfo>> {
+		match self {
+			#[cfg(any(feature = "bundle", feature = "jar"))]
+			Self::JNI(lt) => lt.list_rules(lang).await,
+			#[cfg(feature = "remote")]
+			Self::Remote(lt) => lt.list_rules(lang).await,
+			#[cfg(feature = "server")]
+			Self::Managed(lt) => lt.list_rules(lang).await,
+			#[cfg(feature = "hunspell")]
+			Self::Hunspell(lt) => lt.list_rules(lang).await,
+			#[cfg(feature = "mock")]
+			Self::Mock(lt) => lt.list_rules(lang).await,
+			Self::Composite(lt) => lt.list_rules(lang).await,
+			Self::Rules(lt) => lt.list_rules(lang).await,
+			#[cfg(feature = "fs")]
+			Self::Vale(lt) => lt.list_rules(lang).await,
+			Self::Fallback(lt) => lt.list_rules(lang).await,
+			Self::Dictionary(lt) => lt.list_rules(lang).await,
+
+			#[allow(unreachable_patterns)]
+			_ => unreachable!("{:?}", lang),
+		}
+	}
 }
 
+/// Maps the suggestions for the chunks to diagnostics in the source files.
+///
+/// Diagnostics with the same main location and rule are only reported once,
+/// for text which is part of multiple chunks.
 pub struct FileCollector {
 	source: Option<Source>,
 	diagnostics: Vec<Diagnostic>,
+	seen: HashSet<(FileId, Range<usize>, Arc<str>)>,
 }
 
 impl FileCollector {
+	/// Only keep diagnostics in the file, or in all files for `None`.
 	pub fn new(file_id: Option<FileId>, world: &impl World) -> Self {
 		let source = file_id.map(|id| world.source(id).unwrap());
-		Self { source, diagnostics: Vec::new() }
+		Self {
+			source,
+			diagnostics: Vec::new(),
+			seen: HashSet::new(),
+		}
 	}
 
-	pub fn add(&mut self, world: &impl World, suggestions: &[Suggestion], mapping: &Mapping) {
+	/// Add the suggestions for the chunk with the mapping, returns the added diagnostics.
+	pub fn add(
+		&mut self,
+		world: &impl World,
+		suggestions: &[Suggestion],
+		mapping: &Mapping,
+	) -> &[Diagnostic] {
+		let start = self.diagnostics.len();
 		let diagnostics = suggestions.iter().filter_map(|suggestion| {
+			if mapping.skips(suggestion) {
+				return None;
+			}
 			let locations = mapping.location(suggestion, world, self.source.as_ref());
-			if locations.is_empty() {
+			let (id, range) = locations.first()?.clone();
+			if self
+				.seen
+				.insert((id, range, suggestion.rule_id.clone()))
+				.not()
+			{
 				return None;
 			}
+			let replacements = convert::markup_replacements(
+				&suggestion.replacements,
+				&locations,
+				world,
+				self.source.as_ref(),
+			);
 			let dia = Diagnostic {
 				locations,
 				message: suggestion.message.clone(),
-				replacements: suggestion.replacements.clone(),
+				replacements,
 				rule_description: suggestion.rule_description.clone(),
 				rule_id: suggestion.rule_id.clone(),
+				category: suggestion.category.clone(),
+				severity: Severity::Info,
+				unnecessary: false,
+				comment: mapping.comment,
 			};
 			Some(dia)
 		});
-		self.diagnostics.extend(diagnostics)
+		self.diagnostics.extend(diagnostics);
+		&self.diagnostics[start..]
+	}
+
+	/// Diagnostics added so far.
+	pub fn diagnostics(&self) -> &[Diagnostic] {
+		&self.diagnostics
 	}
 
 	pub fn finish(self) -> Vec<Diagnostic> {
@@ -143,26 +597,248 @@ impl FileCollector {
 	}
 }
 
+/// Offset in UTF-16 code units, the unit of [`Suggestion::start`] and [`Suggestion::end`], for the byte offset in the text.
+pub fn utf16_offset(text: &str, byte: usize) -> usize {
+	text[..byte].chars().map(char::len_utf16).sum()
+}
+
+impl Suggestion {
+	/// Byte range of the suggestion in the chunk, `None` if the offsets are outside of the chunk or inside a char.
+	pub fn byte_range(&self, text: &str) -> Option<Range<usize>> {
+		let mut offset = 0;
+		let mut start = None;
+		for (index, c) in text.char_indices().chain([(text.len(), ' ')]) {
+			if offset == self.start {
+				start = Some(index);
+			}
+			if offset == self.end {
+				return Some(start?..index);
+			}
+			offset += c.len_utf16();
+		}
+		None
+	}
+}
+
+/// Suggestion mapped to the source.
 #[derive(Debug, Clone)]
 pub struct Diagnostic {
+	/// Byte ranges in the source files, the first location is the main location
 	pub locations: Vec<(FileId, Range<usize>)>,
-	pub message: String,
-	pub replacements: Vec<String>,
-	pub rule_description: String,
-	pub rule_id: String,
+	pub message: Arc<str>,
+	pub replacements: Arc<[String]>,
+	pub rule_description: Arc<str>,
+	pub rule_id: Arc<str>,
+	pub category: Arc<str>,
+	pub severity: Severity,
+	/// Shown faded instead of underlined
+	pub unnecessary: bool,
+	/// In a comment in the source
+	pub comment: bool,
+}
+
+impl Diagnostic {
+	/// Use the severity and the unnecessary mark for the rule id or category,
+	/// the severity for comments is used if the rule id and category have no severity.
+	pub fn apply_rule_options(
+		&mut self,
+		severities: &HashMap<String, Severity>,
+		comment_severity: Option<Severity>,
+		unnecessary: &[String],
+	) {
+		self.severity = self.rule_severity(severities, comment_severity);
+		self.unnecessary = unnecessary
+			.iter()
+			.any(|rule| **rule == *self.rule_id || **rule == *self.category);
+	}
+
+	/// Severity for the rule id or category, like [`Diagnostic::apply_rule_options`] without changing the diagnostic.
+	pub fn rule_severity(
+		&self,
+		severities: &HashMap<String, Severity>,
+		comment_severity: Option<Severity>,
+	) -> Severity {
+		severities
+			.get(&*self.rule_id)
+			.or_else(|| severities.get(&*self.category))
+			.copied()
+			.or(comment_severity.filter(|_| self.comment))
+			.unwrap_or(self.severity)
+	}
+
+	/// Drop replacements only differing in whitespace or case from a previous replacement
+	/// and keep at most `max` replacements.
+	pub fn filter_replacements(&mut self, max: Option<usize>, drop_similar: bool) {
+		let mut seen = HashSet::new();
+		let kept = self
+			.replacements
+			.iter()
+			.filter(|replacement| {
+				if drop_similar.not() {
+					return true;
+				}
+				let normalized = replacement
+					.chars()
+					.filter(|c| c.is_whitespace().not())
+					.flat_map(char::to_lowercase)
+					.collect::<String>();
+				seen.insert(normalized)
+			})
+			.take(max.unwrap_or(usize::MAX))
+			.collect::<Vec<_>>();
+		// the replacements are shared with the suggestion, only copy them if something changed
+		if kept.len() != self.replacements.len() {
+			self.replacements = kept.into_iter().cloned().collect();
+		}
+	}
+
+	/// Edits of the source files for the replacement, a replacement for multiple locations is split
+	/// so the markup between the locations is kept. `None` if a source is not available.
+	///
+	/// Only the changed part of the text is replaced, the inserted text is part of the first changed location.
+	pub fn edits(
+		&self,
+		replacement: &str,
+		world: &impl World,
+	) -> Option<Vec<(FileId, Range<usize>, String)>> {
+		if let [(id, range)] = self.locations.as_slice() {
+			return Some(vec![(*id, range.clone(), replacement.to_owned())]);
+		}
+		let mut parts = Vec::with_capacity(self.locations.len());
+		for (id, range) in &self.locations {
+			let source = world.source(*id).ok()?;
+			parts.push(source.get(range.clone())?.to_owned());
+		}
+		let current = parts.concat();
+
+		// unchanged start and end of the text
+		let prefix = current
+			.chars()
+			.zip(replacement.chars())
+			.take_while(|(a, b)| a == b)
+			.map(|(c, _)| c.len_utf8())
+			.sum::<usize>();
+		let suffix = current[prefix..]
+			.chars()
+			.rev()
+			.zip(replacement[prefix..].chars().rev())
+			.take_while(|(a, b)| a == b)
+			.map(|(c, _)| c.len_utf8())
+			.sum::<usize>();
+		let changed = prefix..current.len() - suffix;
+		let mut inserted = Some(&replacement[prefix..replacement.len() - suffix]);
+
+		let mut edits = Vec::new();
+		let mut start = 0;
+		for ((id, range), part) in self.locations.iter().zip(&parts) {
+			let end = start + part.len();
+			let from = changed.start.clamp(start, end);
+			let to = changed.end.clamp(start, end);
+			let insertion = changed.is_empty() && from == changed.start && inserted.is_some();
+			if from < to || insertion {
+				let text = inserted.take().unwrap_or_default().to_owned();
+				edits.push((
+					*id,
+					range.start + from - start..range.start + to - start,
+					text,
+				));
+			}
+			start = end;
+		}
+		Some(edits)
+	}
+}
+
+#[derive(
+	serde::Serialize,
+	serde::Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	Default
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+	#[default]
+	Info,
+	Warning,
+	Error,
+}
+
+impl std::fmt::Display for Severity {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Info => write!(f, "info"),
+			Self::Warning => write!(f, "warning"),
+			Self::Error => write!(f, "error"),
+		}
+	}
+}
+
+impl std::str::FromStr for Severity {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"info" => Ok(Self::Info),
+			"warning" => Ok(Self::Warning),
+			"error" => Ok(Self::Error),
+			_ => Err(anyhow::anyhow!(
+				"Unknown severity '{}', expected 'info', 'warning' or 'error'.",
+				s
+			)),
+		}
+	}
 }
 
+/// Result of a backend for a text chunk.
 #[derive(Debug, Clone)]
 pub struct Suggestion {
+	/// Start in UTF-16 code units of the chunk, like the chars of Java and JavaScript, see [`utf16_offset`]
 	pub start: usize,
+	/// End in UTF-16 code units of the chunk
 	pub end: usize,
-	pub message: String,
-	pub replacements: Vec<String>,
-	pub rule_description: String,
-	pub rule_id: String,
+	pub message: Arc<str>,
+	pub replacements: Arc<[String]>,
+	pub rule_description: Arc<str>,
+	pub rule_id: Arc<str>,
+	/// Category of the rule (`Possible Typo`, `Grammar`, ...)
+	pub category: Arc<str>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Language {
+	/// Language name (`English (US)`, ...)
+	pub name: String,
+	/// Short language code (`en`, ...)
+	pub code: String,
+	/// Long language code (`en-US`, ...)
+	pub long_code: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RuleInfo {
+	/// Rule id to disable the rule (`WHITESPACE_RULE`, ...)
+	pub id: String,
+	pub description: String,
+	pub category: String,
+}
+
+impl Suggestion {
+	/// Heuristic based on the rule ids of the backends (`MORFOLOGIK_RULE_EN_GB`, `GERMAN_SPELLER_RULE`, ...).
+	pub fn is_spelling(&self) -> bool {
+		["SPELL", "MORFOLOGIK", "HUNSPELL"]
+			.iter()
+			.any(|part| self.rule_id.contains(part))
+	}
 }
 
 const DEFAULT_CHUNK_SIZE: usize = 1000;
+const DEFAULT_JOBS: usize = 1;
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 #[serde(default)]
@@ -171,33 +847,262 @@ pub struct LanguageToolOptions {
 	pub root: Option<PathBuf>,
 	/// Project Main File
 	pub main: Option<PathBuf>,
+	/// Main files of a project with multiple documents, a file is checked with the first main using it
+	pub mains: Vec<PathBuf>,
+	/// Main file for files checked without a main, `{{path}}` is replaced with the path of the file (`#include "{{path}}"`)
+	pub wrapper: Option<PathBuf>,
 	/// Size for chunk send to LanguageTool
 	pub chunk_size: usize,
+	/// Adjust the chunk size from the latency of the requests, starting with `chunk_size`
+	pub auto_chunk_size: bool,
+	/// Chunk size for short language codes (`de`, ...) instead of `chunk_size`, not adjusted with `auto_chunk_size`
+	pub language_chunk_sizes: HashMap<String, usize>,
+	/// Chunks checked concurrently, only used by the server backends
+	pub jobs: usize,
+	/// Additional directories with fonts for the document
+	pub font_paths: Vec<PathBuf>,
+	/// Search the fonts installed on the system, disable to start faster
+	pub include_system_fonts: bool,
+	/// Fail for packages which are not downloaded instead of downloading them
+	pub offline: bool,
+	/// Directory with local packages, defaults to the typst data directory
+	pub package_path: Option<PathBuf>,
+	/// Directory for downloaded packages, defaults to the typst cache directory
+	pub package_cache_path: Option<PathBuf>,
+	/// Date for `datetime.today()` (`2024-05-01` or `2024-05-01T12:00:00Z`), defaults to `SOURCE_DATE_EPOCH` or the current date
+	pub creation_date: Option<String>,
 
 	#[serde(flatten)]
 	pub backend: Option<BackendOptions>,
+	/// Backend to use if the main backend fails
+	pub fallback: Option<BackendOptions>,
 
 	/// map for short to long language codes (`en -> en-US`)
 	pub languages: HashMap<String, String>,
+	/// Long language code for the text in English, the language of documents without `#set text(lang: ..)`
+	pub default_language: Option<String>,
+	/// Long language codes for the files matching the patterns (relative to the root), instead of the language of the document
+	pub file_languages: HashMap<String, String>,
 	/// Additional allowed words
 	pub dictionary: HashMap<String, Vec<String>>,
+	/// Files with additional allowed words, one per line and optionally prefixed with the language (`en-US:word`)
+	pub dictionary_files: Vec<PathBuf>,
+	/// Hunspell `.dic` files and LTeX dictionaries (`settings.json` or `ltex.dictionary.en-US.txt`) with allowed words
+	pub dictionary_imports: Vec<PathBuf>,
 	/// Languagetool rules to ignore (WHITESPACE_RULE, ...)
 	pub disabled_checks: HashMap<String, Vec<String>>,
+	/// Rules to ignore in the files matching the patterns (relative to the root), in addition to the `disabled_checks`
+	pub file_disabled_checks: HashMap<String, Vec<String>>,
+	/// Additional regex rules
+	pub rules: Vec<RuleOptions>,
+	/// Directory with vale styles
+	pub styles_path: Option<PathBuf>,
+	/// Vale styles to use, defaults to all styles in `styles_path`
+	pub styles: Vec<String>,
+	/// Only check spelling or grammar, defaults to all
+	pub check_mode: Option<CheckMode>,
+	/// Maximum number of replacements for a diagnostic
+	pub max_suggestions: Option<usize>,
+	/// Drop replacements only differing in whitespace or case from a previous replacement
+	pub drop_similar_suggestions: bool,
+	/// Severity for rule ids or categories, the rule id takes precedence
+	pub rule_severity: HashMap<String, Severity>,
+	/// Rule ids or categories marked as unnecessary, editors show them faded instead of underlined
+	pub unnecessary_rules: Vec<String>,
+	/// Use the LTeX magic comments (`// ltex: language=de-DE`) in the sources
+	pub ltex_comments: bool,
+	/// Check the headings as part of the text, as independent sentences or skip them, defaults to text
+	pub headings: Option<Headings>,
+	/// Rules to ignore in the headings (PUNCTUATION_PARAGRAPH_END, ...)
+	pub heading_disabled_checks: Vec<String>,
+	/// Check the text of the comments in the sources
+	pub check_comments: bool,
+	/// Severity for the diagnostics in comments, the `rule_severity` takes precedence
+	pub comment_severity: Option<Severity>,
+	/// Check the headers and footers of the pages, each distinct header or footer once
+	pub headers_footers: bool,
+	/// Check the entries of the outlines, skipped by default since they repeat the headings and captions
+	pub outline: bool,
+	/// Join the list items with a space instead of a paragraph break, for lists which are part of a sentence
+	pub join_list_items: bool,
+	/// Fields of the entries (`title`, `parent.title`, ...) checked in the text of the `bibliography` and `cite` functions,
+	/// the other text of the functions is skipped
+	pub bibliography_fields: HashMap<String, Vec<String>>,
+	/// Check, skip or attribute to the nearest text the text without a location in the sources, defaults to check
+	pub detached_text: Option<DetachedText>,
+	/// Skip the spelling suggestions for words shorter than this number of chars
+	pub min_word_length: Option<usize>,
+	/// Skip the spelling suggestions for words containing digits (`5km`, `x86_64`)
+	pub skip_numeric_words: bool,
+	/// Preset of disabled checks and rule options, applied below the other options with [`LanguageToolOptions::with_profile`]
+	pub profile: Option<Profile>,
+}
+
+#[derive(
+	serde::Serialize,
+	serde::Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	Default
+)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckMode {
+	#[default]
+	All,
+	Spelling,
+	Grammar,
+}
+
+impl CheckMode {
+	/// Check if the suggestion is reported in this mode.
+	pub fn includes(self, suggestion: &Suggestion) -> bool {
+		match self {
+			Self::All => true,
+			Self::Spelling => suggestion.is_spelling(),
+			Self::Grammar => suggestion.is_spelling().not(),
+		}
+	}
+}
+
+impl std::str::FromStr for CheckMode {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"all" => Ok(Self::All),
+			"spelling" => Ok(Self::Spelling),
+			"grammar" => Ok(Self::Grammar),
+			_ => Err(anyhow::anyhow!(
+				"Unknown check mode '{}', expected 'all', 'spelling' or 'grammar'.",
+				s
+			)),
+		}
+	}
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RuleOptions {
+	/// Rule id, defaults to `USER_RULE_<index>`
+	#[serde(default)]
+	pub id: Option<String>,
+	/// Regex to match
+	pub pattern: String,
+	/// Message for a match
+	pub message: String,
+	/// Replacement for a match, may contain capture groups (`$1`, `${name}`)
+	#[serde(default)]
+	pub replacement: Option<String>,
+	/// Only check for this language (`en` or `en-US`)
+	#[serde(default)]
+	pub language: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "backend")]
 pub enum BackendOptions {
 	#[serde(rename = "bundle")]
-	Bundle,
+	Bundle {
+		#[serde(flatten)]
+		jni: JniOptions,
+	},
 	#[serde(rename = "jar")]
-	Jar { jar_location: String },
+	Jar {
+		jar_location: String,
+		#[serde(flatten)]
+		jni: JniOptions,
+	},
 	#[serde(rename = "server")]
 	Remote {
 		host: String,
 		#[serde(deserialize_with = "string_or_number")]
 		port: String,
+		/// Timeout in seconds for a single request
+		#[serde(default)]
+		timeout: Option<f64>,
+		/// Retries for a failed request
+		#[serde(default)]
+		retries: usize,
+		/// Skip chunks with failed requests instead of aborting the check
+		#[serde(default)]
+		continue_on_error: bool,
+		/// Username for the server dictionary
+		#[serde(default)]
+		username: Option<Secret>,
+		/// API key for the server dictionary
+		#[serde(default)]
+		api_key: Option<Secret>,
+		/// Match dictionary words case-insensitive
+		#[serde(default)]
+		ignore_case: bool,
+		/// Name of the project dictionary on the server, words removed from the options are deleted from it
+		#[serde(default)]
+		server_dictionary: Option<String>,
 	},
+	#[serde(rename = "managed")]
+	Managed {
+		/// languagetool-server.jar or classpath of the LanguageTool distribution
+		server_location: String,
+		/// defaults to a free port
+		#[serde(default)]
+		server_port: Option<u16>,
+		/// defaults to `java`
+		#[serde(default)]
+		java: Option<String>,
+	},
+	#[serde(rename = "hunspell")]
+	Hunspell { hunspell_directory: PathBuf },
+	#[serde(rename = "composite")]
+	Composite { backends: Vec<BackendOptions> },
+}
+
+/// Credential, which is not shown in the logs.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret(pub String);
+
+impl std::fmt::Debug for Secret {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "<redacted>")
+	}
+}
+
+impl std::ops::Deref for Secret {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		&self.0
+	}
+}
+
+/// Options for the bundle and jar backends.
+#[derive(
+	serde::Serialize,
+	serde::Deserialize,
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	Default
+)]
+#[serde(default)]
+pub struct JniOptions {
+	/// Directory with the ngram data (`<ngram_directory>/en`, ...)
+	pub ngram_directory: Option<PathBuf>,
+	/// Custom LanguageTool XML rule files (`grammar.xml`) for language codes
+	pub rule_files: HashMap<String, Vec<PathBuf>>,
+	/// Threads to check the text, defaults to the number of processors
+	pub threads: Option<usize>,
+	/// Maximum number of cached sentence results in LanguageTool
+	pub cache_size: Option<u64>,
+	/// Maximum heap size for the JVM (`512m`, `2g`, ...)
+	pub max_heap: Option<String>,
+	/// Maximum number of languages kept loaded, the least recently used language is unloaded
+	pub max_languages: Option<usize>,
+	/// Additional options for the JVM
+	pub jvm_options: Vec<String>,
 }
 
 impl Default for LanguageToolOptions {
@@ -205,37 +1110,280 @@ impl Default for LanguageToolOptions {
 		Self {
 			root: None,
 			main: None,
+			mains: Vec::new(),
+			wrapper: None,
 			chunk_size: DEFAULT_CHUNK_SIZE,
+			auto_chunk_size: false,
+			language_chunk_sizes: HashMap::new(),
+			jobs: DEFAULT_JOBS,
+			font_paths: Vec::new(),
+			include_system_fonts: true,
+			offline: false,
+			package_path: None,
+			package_cache_path: None,
+			creation_date: None,
 
 			backend: None,
+			fallback: None,
 
 			languages: HashMap::new(),
+			default_language: None,
+			file_languages: HashMap::new(),
 			dictionary: HashMap::new(),
+			dictionary_files: Vec::new(),
+			dictionary_imports: Vec::new(),
 			disabled_checks: HashMap::new(),
+			file_disabled_checks: HashMap::new(),
+			rules: Vec::new(),
+			styles_path: None,
+			styles: Vec::new(),
+			check_mode: None,
+			max_suggestions: None,
+			drop_similar_suggestions: false,
+			rule_severity: HashMap::new(),
+			unnecessary_rules: Vec::new(),
+			ltex_comments: false,
+			headings: None,
+			heading_disabled_checks: Vec::new(),
+			check_comments: false,
+			comment_severity: None,
+			headers_footers: false,
+			outline: false,
+			join_list_items: false,
+			bibliography_fields: HashMap::new(),
+			detached_text: None,
+			min_word_length: None,
+			skip_numeric_words: false,
+			profile: None,
 		}
 	}
 }
 
+/// Options files searched in `typst-languagetool` in the user configuration directory.
+pub const GLOBAL_OPTIONS_FILES: [&str; 4] =
+	["config.json", "config.toml", "config.yaml", "config.yml"];
+
+/// Options files searched in the project root if no options file is specified.
+pub const OPTIONS_FILES: [&str; 4] = [
+	"typst-languagetool.json",
+	"typst-languagetool.toml",
+	"typst-languagetool.yaml",
+	"typst-languagetool.yml",
+];
+
 impl LanguageToolOptions {
+	/// Load the options from a JSON, TOML or YAML file, detected by the extension.
+	/// The `dictionary_files`, `dictionary_imports` and `font_paths` are relative to the directory of the file.
+	#[cfg(feature = "fs")]
+	pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+		let content = std::fs::read_to_string(path)
+			.map_err(|err| anyhow::anyhow!("Failed to read options {}: {}", path.display(), err))?;
+		let mut options: Self = match path.extension().and_then(|ext| ext.to_str()) {
+			Some("toml") => toml::from_str(&content)?,
+			Some("yaml" | "yml") => serde_yaml::from_str(&content)?,
+			_ => serde_json::from_str(&content)?,
+		};
+		let directory = path.parent().unwrap_or(std::path::Path::new(""));
+		for file in options
+			.dictionary_files
+			.iter_mut()
+			.chain(&mut options.dictionary_imports)
+			.chain(&mut options.font_paths)
+		{
+			*file = directory.join(&file);
+		}
+		Ok(options)
+	}
+
+	/// First of the [`OPTIONS_FILES`] in the directory.
+	#[cfg(feature = "fs")]
+	pub fn find(directory: &std::path::Path) -> Option<PathBuf> {
+		OPTIONS_FILES
+			.iter()
+			.map(|name| directory.join(name))
+			.find(|path| path.is_file())
+	}
+
+	/// First of the [`GLOBAL_OPTIONS_FILES`] in the user configuration directory
+	/// (`~/.config/typst-languagetool` on Linux), used before the project and command line options.
+	#[cfg(feature = "fs")]
+	pub fn find_global() -> Option<PathBuf> {
+		let directory = dirs::config_dir()?.join("typst-languagetool");
+		GLOBAL_OPTIONS_FILES
+			.iter()
+			.map(|name| directory.join(name))
+			.find(|path| path.is_file())
+	}
+
+	/// Use the [`MANIFEST_FILE`] in the root, or without a root in the current directory or a parent directory,
+	/// for the root and the main file if they are not specified.
+	#[cfg(feature = "fs")]
+	pub fn use_project(&mut self) -> anyhow::Result<Option<Project>> {
+		let path = match &self.root {
+			Some(root) => Some(root.join(MANIFEST_FILE)).filter(|path| path.is_file()),
+			None => Project::find(std::path::Path::new(".")),
+		};
+		let Some(path) = path else {
+			return Ok(None);
+		};
+		let project = Project::load(&path)?;
+		self.root.get_or_insert_with(|| project.root.clone());
+		if self.mains.is_empty() {
+			self.main.get_or_insert_with(|| project.main.clone());
+		}
+		Ok(Some(project))
+	}
+
 	pub fn overwrite(mut self, other: Self) -> Self {
+		self.mains.extend(other.mains);
 		self.dictionary.extend(other.dictionary);
+		self.dictionary_files.extend(other.dictionary_files);
+		self.dictionary_imports.extend(other.dictionary_imports);
+		self.font_paths.extend(other.font_paths);
 		self.disabled_checks.extend(other.disabled_checks);
+		self.file_disabled_checks.extend(other.file_disabled_checks);
 		self.languages.extend(other.languages);
+		self.file_languages.extend(other.file_languages);
+		self.language_chunk_sizes.extend(other.language_chunk_sizes);
+		self.rules.extend(other.rules);
+		self.rule_severity.extend(other.rule_severity);
+		self.unnecessary_rules.extend(other.unnecessary_rules);
+		self.heading_disabled_checks
+			.extend(other.heading_disabled_checks);
+		self.bibliography_fields.extend(other.bibliography_fields);
 
 		Self {
 			root: other.root.or(self.root),
 			main: other.main.or(self.main),
+			mains: self.mains,
+			wrapper: other.wrapper.or(self.wrapper),
 
-			chunk_size: (other.chunk_size != DEFAULT_CHUNK_SIZE)
-				.then_some(other.chunk_size)
-				.unwrap_or(self.chunk_size),
+			chunk_size: if other.chunk_size != DEFAULT_CHUNK_SIZE {
+				other.chunk_size
+			} else {
+				self.chunk_size
+			},
+			auto_chunk_size: other.auto_chunk_size || self.auto_chunk_size,
+			language_chunk_sizes: self.language_chunk_sizes,
+			jobs: if other.jobs != DEFAULT_JOBS {
+				other.jobs
+			} else {
+				self.jobs
+			},
+			font_paths: self.font_paths,
+			include_system_fonts: other.include_system_fonts && self.include_system_fonts,
+			offline: other.offline || self.offline,
+			package_path: other.package_path.or(self.package_path),
+			package_cache_path: other.package_cache_path.or(self.package_cache_path),
+			creation_date: other.creation_date.or(self.creation_date),
 
 			backend: other.backend.or(self.backend),
+			fallback: other.fallback.or(self.fallback),
 
 			languages: self.languages,
+			default_language: other.default_language.or(self.default_language),
+			file_languages: self.file_languages,
 			dictionary: self.dictionary,
+			dictionary_files: self.dictionary_files,
+			dictionary_imports: self.dictionary_imports,
 			disabled_checks: self.disabled_checks,
+			file_disabled_checks: self.file_disabled_checks,
+			rules: self.rules,
+			styles_path: other.styles_path.or(self.styles_path),
+			styles: if other.styles.is_empty() {
+				self.styles
+			} else {
+				other.styles
+			},
+			check_mode: other.check_mode.or(self.check_mode),
+			max_suggestions: other.max_suggestions.or(self.max_suggestions),
+			drop_similar_suggestions: other.drop_similar_suggestions
+				|| self.drop_similar_suggestions,
+			rule_severity: self.rule_severity,
+			unnecessary_rules: self.unnecessary_rules,
+			ltex_comments: other.ltex_comments || self.ltex_comments,
+			headings: other.headings.or(self.headings),
+			heading_disabled_checks: self.heading_disabled_checks,
+			check_comments: other.check_comments || self.check_comments,
+			comment_severity: other.comment_severity.or(self.comment_severity),
+			headers_footers: other.headers_footers || self.headers_footers,
+			outline: other.outline || self.outline,
+			join_list_items: other.join_list_items || self.join_list_items,
+			bibliography_fields: self.bibliography_fields,
+			detached_text: other.detached_text.or(self.detached_text),
+			min_word_length: other.min_word_length.or(self.min_word_length),
+			skip_numeric_words: other.skip_numeric_words || self.skip_numeric_words,
+			profile: other.profile.or(self.profile),
+		}
+	}
+
+	/// Options of the [`Profile`] with these options applied on top.
+	pub fn with_profile(mut self) -> Self {
+		let Some(profile) = self.profile else {
+			return self;
+		};
+		let mut options = profile.options(&self.languages);
+		// the disabled checks of a language are added to the checks of the profile instead of replacing them
+		for (lang, checks) in std::mem::take(&mut options.disabled_checks) {
+			let disabled = self.disabled_checks.entry(lang).or_default();
+			for check in checks {
+				if disabled.contains(&check).not() {
+					disabled.push(check);
+				}
+			}
+		}
+		options.overwrite(self)
+	}
+
+	/// Long language code for the chunk from the `file_languages`, the `default_language` or the `languages`.
+	/// The longest pattern matching the file of the first char is used.
+	pub fn language(&self, mapping: &Mapping) -> Option<String> {
+		let path = mapping
+			.file_id(0..usize::MAX)
+			.filter(|id| id.package().is_none())
+			.map(|id| id.vpath().as_rootless_path());
+		let file_language = path.and_then(|path| {
+			self.file_languages
+				.iter()
+				.filter(|(pattern, _)| {
+					glob::Pattern::new(pattern).is_ok_and(|pattern| pattern.matches_path(path))
+				})
+				.max_by_key(|(pattern, _)| pattern.len())
+				.map(|(_, lang)| lang.clone())
+		});
+		if file_language.is_some() {
+			return file_language;
+		}
+		if mapping.language == typst::text::Lang::ENGLISH {
+			if let Some(lang) = &self.default_language {
+				return Some(lang.clone());
+			}
+		}
+		self.languages.get(mapping.short_language()).cloned()
+	}
+
+	/// Remove the suggestions for the `file_disabled_checks` of the files of the suggestions.
+	pub fn retain_file_checks(&self, mapping: &Mapping, suggestions: &mut Vec<Suggestion>) {
+		if self.file_disabled_checks.is_empty() {
+			return;
 		}
+		suggestions.retain(|suggestion| {
+			let Some(id) = mapping
+				.file_id(suggestion.start..suggestion.end)
+				.filter(|id| id.package().is_none())
+			else {
+				return true;
+			};
+			let path = id.vpath().as_rootless_path();
+			let disabled = self
+				.file_disabled_checks
+				.iter()
+				.filter(|(pattern, _)| {
+					glob::Pattern::new(pattern).is_ok_and(|pattern| pattern.matches_path(path))
+				})
+				.any(|(_, checks)| checks.iter().any(|check| **check == *suggestion.rule_id));
+			disabled.not()
+		});
 	}
 }
 
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use typst::{
+	syntax::{FileId, Span, SyntaxKind},
+	World,
+};
+
+use crate::convert::{ChunkBuilder, Mapping};
+
+/// Separate the list, enum and term items in the chunks, the markers between the items are removed.
+///
+/// The items are found with the syntax of the sources, so only the markup lists (`- item`) are separated.
+/// The items are separated by a paragraph break, or by a space with `join` for lists which are part of a sentence.
+pub fn list_items(
+	world: &impl World,
+	paragraphs: Vec<(String, Mapping)>,
+	join: bool,
+) -> Vec<(String, Mapping)> {
+	let mut items = HashMap::<Span, Option<(FileId, usize)>>::new();
+	paragraphs
+		.into_iter()
+		.map(|(text, mapping)| {
+			let keys = mapping
+				.chars
+				.iter()
+				.map(|(span, _)| {
+					if span.is_detached() {
+						return None;
+					}
+					*items.entry(*span).or_insert_with(|| item(world, *span))
+				})
+				.collect::<Vec<_>>();
+			separate(text, mapping, &keys, join)
+		})
+		.collect()
+}
+
+/// Innermost list, enum or term item containing the span.
+fn item(world: &impl World, span: Span) -> Option<(FileId, usize)> {
+	let id = span.id()?;
+	let source = world.source(id).ok()?;
+	let mut node = source.find(span)?;
+	loop {
+		if matches!(
+			node.kind(),
+			SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem
+		) {
+			return Some((id, node.offset()));
+		}
+		node = node.parent()?.clone();
+	}
+}
+
+/// Replace the detached chars before the items with the separator.
+fn separate(
+	text: String,
+	mapping: Mapping,
+	keys: &[Option<(FileId, usize)>],
+	join: bool,
+) -> (String, Mapping) {
+	let separator = if join { " " } else { "\n\n" };
+
+	// gaps of detached chars before the start of an item
+	let mut gaps = Vec::new();
+	let mut previous = None::<(usize, Option<(FileId, usize)>)>;
+	for (index, (span, _)) in mapping.chars.iter().enumerate() {
+		if span.is_detached() {
+			continue;
+		}
+		let key = keys[index];
+		let (start, last_key) = match previous {
+			Some((last, last_key)) => (last + 1, last_key),
+			None => (0, None),
+		};
+		if key.is_some() && last_key != key && start < index {
+			gaps.push(start..index);
+		}
+		previous = Some((index, key));
+	}
+	if gaps.is_empty() {
+		return (text, mapping);
+	}
+
+	let mut builder = ChunkBuilder::new(&text, &mapping);
+	let mut last = 0;
+	for gap in gaps {
+		builder.append(last..gap.start);
+		builder.insert(separator);
+		last = gap.end;
+	}
+	builder.append(last..mapping.chars.len());
+
+	let (res, chars) = builder.finish();
+	(res, Mapping { chars, ..mapping })
+}
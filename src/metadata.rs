@@ -0,0 +1,124 @@
+use std::{collections::HashMap, ops::Not};
+
+use typst::{
+	foundations::{Label, Selector},
+	introspection::MetadataElem,
+	model::Document,
+};
+
+use crate::{
+	backends::{dictionary::AllowedWords, rules::matches_language},
+	Suggestion,
+};
+
+/// Label of the metadata with the options of the document.
+pub const METADATA_LABEL: &str = "typst-languagetool";
+
+/// Allowed words and disabled checks from `#metadata((..)) <typst-languagetool>` in the document.
+///
+/// The words and checks are lists for all languages or dictionaries with lists for language codes:
+/// `#metadata((dictionary: ("Typst",), disabled: (en: ("WHITESPACE_RULE",)))) <typst-languagetool>`.
+/// The backend is shared by all documents, so the options are applied to the suggestions.
+#[derive(Debug, Default)]
+pub struct DocumentOptions {
+	/// Allowed words for language codes, `None` for all languages
+	dictionary: Vec<(Option<String>, AllowedWords)>,
+	/// Disabled checks for language codes, `None` for all languages
+	disabled: Vec<(Option<String>, Vec<String>)>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Metadata {
+	dictionary: Entries,
+	disabled: Entries,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Entries {
+	All(Vec<String>),
+	Languages(HashMap<String, Vec<String>>),
+}
+
+impl Default for Entries {
+	fn default() -> Self {
+		Self::All(Vec::new())
+	}
+}
+
+impl Entries {
+	fn into_languages(self) -> Vec<(Option<String>, Vec<String>)> {
+		match self {
+			Self::All(entries) if entries.is_empty() => Vec::new(),
+			Self::All(entries) => vec![(None, entries)],
+			Self::Languages(languages) => languages
+				.into_iter()
+				.map(|(lang, entries)| (Some(lang), entries))
+				.collect(),
+		}
+	}
+}
+
+impl DocumentOptions {
+	/// Options of all metadata with the [`METADATA_LABEL`], invalid metadata is skipped with a warning.
+	pub fn new(doc: &Document) -> Self {
+		let mut options = Self::default();
+		let selector = Selector::Label(Label::new(METADATA_LABEL));
+		for content in doc.introspector.query(&selector) {
+			let Some(metadata) = content.to_packed::<MetadataElem>() else {
+				continue;
+			};
+			let metadata =
+				serde_json::to_value(&metadata.value).and_then(serde_json::from_value::<Metadata>);
+			let metadata = match metadata {
+				Ok(metadata) => metadata,
+				Err(err) => {
+					tracing::warn!("Skipped metadata <{}>: {}", METADATA_LABEL, err);
+					continue;
+				},
+			};
+			for (lang, words) in metadata.dictionary.into_languages() {
+				let mut allowed = AllowedWords::default();
+				for word in &words {
+					allowed.insert(word, false);
+				}
+				options.dictionary.push((lang, allowed));
+			}
+			options.disabled.extend(metadata.disabled.into_languages());
+		}
+		options
+	}
+
+	/// Remove the suggestions for disabled checks and allowed words.
+	pub fn retain(&self, text: &str, lang: &str, suggestions: &mut Vec<Suggestion>) {
+		if self.dictionary.is_empty() && self.disabled.is_empty() {
+			return;
+		}
+		let applies = |filter: &Option<String>| {
+			filter
+				.as_ref()
+				.is_none_or(|filter| matches_language(filter, lang))
+		};
+		let utf16 = text.encode_utf16().collect::<Vec<_>>();
+		suggestions.retain(|suggestion| {
+			let disabled = self
+				.disabled
+				.iter()
+				.filter(|(filter, _)| applies(filter))
+				.any(|(_, checks)| checks.iter().any(|check| **check == *suggestion.rule_id));
+			if disabled {
+				return false;
+			}
+			if suggestion.is_spelling().not() {
+				return true;
+			}
+			let allowed = self
+				.dictionary
+				.iter()
+				.filter(|(filter, _)| applies(filter))
+				.any(|(_, allowed)| allowed.contains(&utf16, suggestion.start, suggestion.end));
+			allowed.not()
+		});
+	}
+}
@@ -0,0 +1,180 @@
+use std::{collections::HashMap, str::FromStr};
+
+use typst::text::Lang;
+
+use crate::{convert::long_language, LanguageToolOptions, Severity};
+
+/// Preset of disabled checks and rule options, the options of the user are applied on top.
+#[derive(
+	serde::Serialize,
+	serde::Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+	/// Formal writing, style issues are warnings
+	Academic,
+	/// Documentation with code, units and symbols
+	Technical,
+	/// Informal writing without the formal style rules
+	Casual,
+}
+
+/// Rule ids of a profile for a short language code.
+struct Preset {
+	language: &'static str,
+	disabled_checks: &'static [&'static str],
+}
+
+const ACADEMIC: &[Preset] = &[
+	Preset {
+		language: "en",
+		disabled_checks: &["EN_QUOTES", "DASH_RULE"],
+	},
+	Preset {
+		language: "de",
+		disabled_checks: &["TYPOGRAFISCHE_ANFUEHRUNGSZEICHEN"],
+	},
+];
+
+const ACADEMIC_WARNINGS: &[&str] = &[
+	"EN_CONTRACTION_SPELLING",
+	"ENGLISH_WORD_REPEAT_BEGINNING_RULE",
+	"PHRASE_REPETITION",
+	"EN_WORD_COHERENCY",
+	"GERMAN_WORD_REPEAT_BEGINNING_RULE",
+	"DE_WORD_COHERENCY",
+];
+
+const TECHNICAL: &[Preset] = &[
+	Preset {
+		language: "en",
+		disabled_checks: &[
+			"EN_QUOTES",
+			"DASH_RULE",
+			"ARROWS",
+			"MULTIPLICATION_SIGN",
+			"PLUS_MINUS",
+			"UPPERCASE_SENTENCE_START",
+			"COMMA_PARENTHESIS_WHITESPACE",
+			"EN_UNPAIRED_BRACKETS",
+			"ENGLISH_WORD_REPEAT_BEGINNING_RULE",
+		],
+	},
+	Preset {
+		language: "de",
+		disabled_checks: &[
+			"TYPOGRAFISCHE_ANFUEHRUNGSZEICHEN",
+			"UPPERCASE_SENTENCE_START",
+			"COMMA_PARENTHESIS_WHITESPACE",
+			"DE_UNPAIRED_BRACKETS",
+			"GERMAN_WORD_REPEAT_BEGINNING_RULE",
+		],
+	},
+];
+
+const CASUAL: &[Preset] = &[
+	Preset {
+		language: "en",
+		disabled_checks: &[
+			"EN_QUOTES",
+			"DASH_RULE",
+			"EN_CONTRACTION_SPELLING",
+			"SENT_START_CONJUNCTIVE_LINKING_ADVERB_COMMA",
+		],
+	},
+	Preset {
+		language: "de",
+		disabled_checks: &["TYPOGRAFISCHE_ANFUEHRUNGSZEICHEN"],
+	},
+];
+
+const CASUAL_UNNECESSARY: &[&str] = &[
+	"ENGLISH_WORD_REPEAT_BEGINNING_RULE",
+	"GERMAN_WORD_REPEAT_BEGINNING_RULE",
+];
+
+impl Profile {
+	fn presets(self) -> &'static [Preset] {
+		match self {
+			Self::Academic => ACADEMIC,
+			Self::Technical => TECHNICAL,
+			Self::Casual => CASUAL,
+		}
+	}
+
+	/// Options of the profile, the disabled checks are used for the long language codes of the `languages`
+	/// or the default long language code.
+	pub fn options(self, languages: &HashMap<String, String>) -> LanguageToolOptions {
+		let mut options = LanguageToolOptions::default();
+		for preset in self.presets() {
+			let checks = preset
+				.disabled_checks
+				.iter()
+				.map(|check| check.to_string())
+				.collect::<Vec<_>>();
+			for lang in long_languages(preset.language, languages) {
+				options.disabled_checks.insert(lang, checks.clone());
+			}
+		}
+		if self == Self::Academic {
+			options.rule_severity = ACADEMIC_WARNINGS
+				.iter()
+				.map(|rule| (rule.to_string(), Severity::Warning))
+				.collect();
+		}
+		if self == Self::Casual {
+			options.unnecessary_rules = CASUAL_UNNECESSARY
+				.iter()
+				.map(|rule| rule.to_string())
+				.collect();
+		}
+		options
+	}
+
+	/// Check is disabled by the profile for the long language code.
+	pub(crate) fn disables(self, lang: &str, check: &str) -> bool {
+		let short = lang.split('-').next().unwrap_or(lang);
+		self.presets()
+			.iter()
+			.filter(|preset| preset.language == short)
+			.any(|preset| preset.disabled_checks.contains(&check))
+	}
+}
+
+/// Long language codes used for the short language code.
+fn long_languages(short: &str, languages: &HashMap<String, String>) -> Vec<String> {
+	let mut res = languages
+		.iter()
+		.filter(|(key, long)| *key == short || long.split('-').next() == Some(short))
+		.map(|(_, long)| long.clone())
+		.collect::<Vec<_>>();
+	if res.is_empty() {
+		if let Ok(lang) = Lang::from_str(short) {
+			res.push(long_language(lang));
+		}
+	}
+	res.sort();
+	res.dedup();
+	res
+}
+
+impl FromStr for Profile {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"academic" => Ok(Self::Academic),
+			"technical" => Ok(Self::Technical),
+			"casual" => Ok(Self::Casual),
+			_ => Err(anyhow::anyhow!(
+				"Unknown profile '{}', expected 'academic', 'technical' or 'casual'.",
+				s
+			)),
+		}
+	}
+}
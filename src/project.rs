@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use typst::syntax::package::PackageManifest;
+
+/// Manifest of a typst package or template.
+pub const MANIFEST_FILE: &str = "typst.toml";
+
+/// Typst package described by a [`MANIFEST_FILE`].
+#[derive(Debug, Clone)]
+pub struct Project {
+	/// Directory with the manifest
+	pub root: PathBuf,
+	/// Entrypoint of the package
+	pub main: PathBuf,
+	pub name: String,
+	pub version: String,
+	/// Globs relative to the root for the template directory and the files excluded from the package
+	pub exclude: Vec<String>,
+}
+
+impl Project {
+	/// Manifest in the directory or the closest parent directory.
+	pub fn find(directory: &Path) -> Option<PathBuf> {
+		let directory = directory.canonicalize().ok()?;
+		directory
+			.ancestors()
+			.map(|directory| directory.join(MANIFEST_FILE))
+			.find(|path| path.is_file())
+	}
+
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		let content = std::fs::read_to_string(path).map_err(|err| {
+			anyhow::anyhow!("Failed to read manifest {}: {}", path.display(), err)
+		})?;
+		let manifest = toml::from_str::<PackageManifest>(&content)
+			.map_err(|err| anyhow::anyhow!("Invalid manifest {}: {}", path.display(), err))?;
+		let root = path
+			.parent()
+			.map(Path::to_owned)
+			.unwrap_or_else(|| PathBuf::from("."));
+
+		let mut exclude = manifest
+			.package
+			.exclude
+			.iter()
+			.map(|glob| glob.trim_start_matches("./").to_string())
+			.collect::<Vec<_>>();
+		if let Some(template) = &manifest.template {
+			let path = template.path.trim_start_matches("./").trim_end_matches('/');
+			exclude.push(format!("{}/**", path));
+		}
+
+		Ok(Self {
+			main: root.join(manifest.package.entrypoint.as_str()),
+			root,
+			name: manifest.package.name.to_string(),
+			version: manifest.package.version.to_string(),
+			exclude,
+		})
+	}
+}
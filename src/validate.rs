@@ -0,0 +1,200 @@
+use std::ops::Not;
+
+use crate::{
+	backends::rules::matches_language, Language, LanguageTool, LanguageToolBackend,
+	LanguageToolOptions,
+};
+
+/// Option which is valid, but has no or an unexpected effect with the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionsWarning {
+	pub message: String,
+	/// How to fix the option
+	pub hint: String,
+}
+
+impl OptionsWarning {
+	fn new(message: impl Into<String>, hint: impl Into<String>) -> Self {
+		Self {
+			message: message.into(),
+			hint: hint.into(),
+		}
+	}
+}
+
+impl std::fmt::Display for OptionsWarning {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} {}", self.message, self.hint)
+	}
+}
+
+const LANGUAGE_HINT: &str = "Use a language code from 'typst-languagetool languages'.";
+
+/// Check the language codes and rule ids of the options with the languages and rules of the backend.
+/// Backends without a list of languages or rules accept every language or rule.
+pub(crate) async fn validate(
+	lt: &mut LanguageTool,
+	options: &LanguageToolOptions,
+) -> Vec<OptionsWarning> {
+	let mut warnings = Vec::new();
+	let languages = match lt.list_languages().await {
+		Ok(languages) => languages,
+		Err(err) => {
+			warnings.push(OptionsWarning::new(
+				format!("Languages not available: {}", err),
+				"The language codes can not be validated.",
+			));
+			Vec::new()
+		},
+	};
+
+	for (short, long) in &options.languages {
+		if known(&languages, long).not() {
+			warnings.push(OptionsWarning::new(
+				format!(
+					"Unknown language '{}' for '{}' in the languages.",
+					long, short
+				),
+				LANGUAGE_HINT,
+			));
+		}
+	}
+
+	for (pattern, lang) in &options.file_languages {
+		if let Err(err) = glob::Pattern::new(pattern) {
+			warnings.push(OptionsWarning::new(
+				format!(
+					"Invalid pattern '{}' in the file languages: {}",
+					pattern, err
+				),
+				"Use a glob pattern relative to the root (`chapters/de/*.typ`).",
+			));
+		}
+		if known(&languages, lang).not() {
+			warnings.push(OptionsWarning::new(
+				format!(
+					"Unknown language '{}' for '{}' in the file languages.",
+					lang, pattern
+				),
+				LANGUAGE_HINT,
+			));
+		}
+	}
+
+	for pattern in options.file_disabled_checks.keys() {
+		if let Err(err) = glob::Pattern::new(pattern) {
+			warnings.push(OptionsWarning::new(
+				format!(
+					"Invalid pattern '{}' in the file disabled checks: {}",
+					pattern, err
+				),
+				"Use a glob pattern relative to the root (`chapters/de/*.typ`).",
+			));
+		}
+	}
+
+	if let Some(lang) = &options.default_language {
+		if known(&languages, lang).not() {
+			warnings.push(OptionsWarning::new(
+				format!("Unknown default language '{}'.", lang),
+				LANGUAGE_HINT,
+			));
+		}
+	}
+
+	for function in options.bibliography_fields.keys() {
+		if ["bibliography", "cite"].contains(&function.as_str()).not() {
+			warnings.push(OptionsWarning::new(
+				format!(
+					"Unknown function '{}' in the bibliography fields.",
+					function
+				),
+				"Use 'bibliography' or 'cite'.",
+			));
+		}
+	}
+
+	for lang in options.dictionary.keys() {
+		if known(&languages, lang).not() {
+			warnings.push(OptionsWarning::new(
+				format!("Unknown language '{}' in the dictionary.", lang),
+				LANGUAGE_HINT,
+			));
+		} else if let Some(warning) = mapped(options, lang, "dictionary") {
+			warnings.push(warning);
+		}
+	}
+
+	for (lang, checks) in &options.disabled_checks {
+		// the presets do not know the languages and rules of the backend
+		let preset = |check: &String| {
+			options
+				.profile
+				.is_some_and(|profile| profile.disables(lang, check))
+		};
+		if checks.iter().all(preset) {
+			continue;
+		}
+		if known(&languages, lang).not() {
+			warnings.push(OptionsWarning::new(
+				format!("Unknown language '{}' in the disabled checks.", lang),
+				LANGUAGE_HINT,
+			));
+			continue;
+		}
+		if let Some(warning) = mapped(options, lang, "disabled checks") {
+			warnings.push(warning);
+		}
+		let rules = match lt.list_rules(lang.clone()).await {
+			Ok(rules) => rules,
+			Err(err) => {
+				warnings.push(OptionsWarning::new(
+					format!("Rules for '{}' not available: {}", lang, err),
+					"The disabled checks can not be validated.",
+				));
+				continue;
+			},
+		};
+		for check in checks {
+			if preset(check) {
+				continue;
+			}
+			if rules.is_empty().not() && rules.iter().all(|rule| &rule.id != check) {
+				warnings.push(OptionsWarning::new(
+					format!(
+						"Unknown rule '{}' in the disabled checks for '{}'.",
+						check, lang
+					),
+					format!(
+						"Use a rule id from 'typst-languagetool rules --language={}'.",
+						lang
+					),
+				));
+			}
+		}
+	}
+	warnings
+}
+
+fn known(languages: &[Language], lang: &str) -> bool {
+	languages.is_empty()
+		|| languages
+			.iter()
+			.any(|language| language.long_code == lang || language.code == lang)
+}
+
+/// Long language code which is never checked, because the `languages` map the short code to another long code.
+fn mapped(options: &LanguageToolOptions, lang: &str, name: &str) -> Option<OptionsWarning> {
+	let (short, _) = lang.split_once('-')?;
+	let long = options.languages.get(short)?;
+	if matches_language(lang, long) {
+		return None;
+	}
+	Some(OptionsWarning::new(
+		format!(
+			"Language '{}' in the {} is not used, '{}' is checked as '{}'.",
+			lang, name, short, long
+		),
+		format!("Use '{}' or '{}' instead.", long, short),
+	))
+}
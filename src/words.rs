@@ -0,0 +1,48 @@
+use std::{ops::Not, ops::Range};
+
+use crate::convert::Mapping;
+
+/// Skip the words shorter than `min_length` chars and with `numeric` the words containing digits (`5km`, `x86_64`).
+///
+/// The words are kept in the text for the grammar, only their spelling suggestions are dropped,
+/// so they are not reported as spelling mistakes in technical documents.
+pub fn skip_words(
+	paragraphs: Vec<(String, Mapping)>,
+	min_length: usize,
+	numeric: bool,
+) -> Vec<(String, Mapping)> {
+	if min_length <= 1 && numeric.not() {
+		return paragraphs;
+	}
+	paragraphs
+		.into_iter()
+		.map(|(text, mut mapping)| {
+			mapping.skipped_words = skipped_words(&text, min_length, numeric);
+			(text, mapping)
+		})
+		.collect()
+}
+
+fn skipped(word: &str, min_length: usize, numeric: bool) -> bool {
+	word.chars().count() < min_length || (numeric && word.chars().any(|c| c.is_ascii_digit()))
+}
+
+/// Skipped words as UTF-16 ranges, without the punctuation around them.
+fn skipped_words(text: &str, min_length: usize, numeric: bool) -> Vec<Range<usize>> {
+	let mut words = Vec::new();
+	let mut offset = 0;
+	for token in text.split_inclusive(char::is_whitespace) {
+		let start = offset;
+		offset += token.encode_utf16().count();
+		let word = token.trim_end_matches(char::is_whitespace);
+		let trimmed = word.trim_start_matches(|c: char| c.is_alphanumeric().not());
+		let leading = word[..word.len() - trimmed.len()].encode_utf16().count();
+		let trimmed = trimmed.trim_end_matches(|c: char| c.is_alphanumeric().not());
+		if trimmed.is_empty() || skipped(trimmed, min_length, numeric).not() {
+			continue;
+		}
+		let start = start + leading;
+		words.push(start..start + trimmed.encode_utf16().count());
+	}
+	words
+}